@@ -59,7 +59,7 @@ fn main() {
     let config = MatchConfig {
         time_per_move: Duration::from_secs(5),
         max_moves: 50,
-
+        ..Default::default()
     };
 
     println!("{}", "=".repeat(60));
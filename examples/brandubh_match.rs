@@ -3,21 +3,21 @@ use std::time::Duration;
 
 fn main() {
     println!("Brandubh Match Demo (7x7 Irish Variant)\n");
-    
+
     // Create two bots
     let bot1 = Box::new(RandomBot::new("Random Alice".to_string()));
     let bot2 = Box::new(GreedyBot::new("Greedy Bob".to_string()));
-    
+
     // Configure match with shorter time limits
     let config = MatchConfig {
         time_per_move: Duration::from_millis(500),
         max_moves: 100,
-
+        ..Default::default()
     };
-    
+
     // Create a Brandubh game state
     let brandubh_state = GameState::new_brandubh();
-    
+
     println!("Starting board for Brandubh:");
     println!("{}\n", brandubh_state.display_board());
     println!("Brandubh is played on a 7x7 board with:");
@@ -27,31 +27,22 @@ fn main() {
     println!("\nRules are the same as Copenhagen Hnefatafl:");
     println!("- Defenders win if king reaches any corner");
     println!("- Attackers win if they capture the king\n");
-    
+
     // Create a Brandubh match
     println!("Running Brandubh match...\n");
-    
+
     let mut match_game = Match::with_variant(bot1, bot2, config, true, Variant::Brandubh);
     let result = match_game.play();
-    
+
     // Print summary
     println!("\n{}", "=".repeat(60));
-    match &result {
-        MatchResult::AttackersWin { winner_name, moves } => {
-            println!("🎉 {} won as Attackers in {} moves!", winner_name, moves);
-        }
-        MatchResult::DefendersWin { winner_name, moves } => {
-            println!("🎉 {} won as Defenders in {} moves!", winner_name, moves);
-        }
-        MatchResult::Draw { moves } => {
-            println!("🤝 Draw after {} moves", moves);
-        }
-        MatchResult::Timeout { violator, winner } => {
-            println!("⏱️  {} wins! {} timed out", winner, violator);
-        }
-        MatchResult::IllegalMove { violator, winner } => {
-            println!("❌ {} wins! {} made an illegal move", winner, violator);
-        }
-    }
+    let icon = match result.termination {
+        Termination::Timeout => "⏱️ ",
+        Termination::IllegalMove | Termination::Crash => "❌",
+        Termination::Resignation => "🏳️ ",
+        Termination::MoveLimit | Termination::DrawAgreed => "🤝",
+        _ => "🎉",
+    };
+    println!("{} {}", icon, result);
     println!("{}", "=".repeat(60));
 }
@@ -1,4 +1,4 @@
-/// Example of how students can create their own bot
+//! Example of how students can create their own bot
 
 use hnefatafl_arena::*;
 use std::time::Duration;
@@ -63,6 +63,7 @@ fn main() {
     let config = MatchConfig {
         time_per_move: Duration::from_secs(1),
         max_moves: 150,
+        ..Default::default()
     };
     
     let mut match_game = Match::new(bot1, bot2, config, true);
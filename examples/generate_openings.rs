@@ -0,0 +1,18 @@
+//! Regenerates the opening suites shipped in `openings/`
+//!
+//! Run with: cargo run --release --example generate_openings
+
+use hnefatafl_arena::{OpeningSuite, Variant};
+
+fn main() {
+    let suites = [
+        ("openings/brandubh.txt", Variant::Brandubh, 4),
+        ("openings/copenhagen.txt", Variant::Copenhagen, 6),
+    ];
+
+    for (path, variant, plies) in suites {
+        let suite = OpeningSuite::generate(variant, 300, plies, 2024);
+        suite.save(path).expect("Failed to write opening suite");
+        println!("{}: {} openings of {} moves", path, suite.len(), plies);
+    }
+}
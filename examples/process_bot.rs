@@ -0,0 +1,33 @@
+// A bot speaking the process protocol on stdin/stdout, wrapping GreedyBot
+//
+// Build it and hand the binary to the arena as a command:
+//   cargo build --example process_bot
+//   cargo run -- play random target/debug/examples/process_bot
+
+use hnefatafl_arena::*;
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut bot = GreedyBot::new("ProcessGreedy".to_string());
+    let mut out = io::stdout().lock();
+
+    for line in io::stdin().lock().lines() {
+        let message: Value = serde_json::from_str(&line?)?;
+        match message["type"].as_str() {
+            // Answer with the options the bot accepts
+            Some("options") => writeln!(out, "{}", serde_json::to_string(&bot.options())?)?,
+            // Answer with the action for this position
+            Some("go") => {
+                let state: GameState = serde_json::from_value(message["state"].clone())?;
+                let time: TimeInfo = serde_json::from_value(message["time"].clone())?;
+                let action = bot.get_action(&state, &time);
+                writeln!(out, "{}", serde_json::to_string(&action)?)?;
+            }
+            // set_option, game_start, move, draw_offer and game_end need no answer
+            _ => {}
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -12,6 +12,7 @@ fn main() {
     let config = MatchConfig {
         time_per_move: Duration::from_millis(500),
         max_moves: 10,
+        ..Default::default()
     };
 
     // Run the match with verbose output
@@ -20,22 +21,13 @@ fn main() {
 
     // Print summary
     println!("\n{}", "=".repeat(60));
-    match &result {
-        MatchResult::AttackersWin { winner_name, moves } => {
-            println!("🎉 {} won as Attackers in {} moves!", winner_name, moves);
-        }
-        MatchResult::DefendersWin { winner_name, moves } => {
-            println!("🎉 {} won as Defenders in {} moves!", winner_name, moves);
-        }
-        MatchResult::Draw { moves } => {
-            println!("🤝 Draw after {} moves", moves);
-        }
-        MatchResult::Timeout { violator, winner } => {
-            println!("⏱️  {} wins! {} timed out", winner, violator);
-        }
-        MatchResult::IllegalMove { violator, winner } => {
-            println!("❌ {} wins! {} made an illegal move", winner, violator);
-        }
-    }
+    let icon = match result.termination {
+        Termination::Timeout => "⏱️ ",
+        Termination::IllegalMove | Termination::Crash => "❌",
+        Termination::Resignation => "🏳️ ",
+        Termination::MoveLimit | Termination::DrawAgreed => "🤝",
+        _ => "🎉",
+    };
+    println!("{} {}", icon, result);
     println!("{}", "=".repeat(60));
 }
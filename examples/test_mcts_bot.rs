@@ -1,14 +1,14 @@
-use hnefatafl_arena::{Bot, GreedyBot, Match, MatchConfig, PluginBot, RandomBot};
+use hnefatafl_arena::{Match, MatchConfig, PluginBot};
 use std::time::Duration;
 
 fn main() {
     // Create a specific folder for this iteration tier
     // e.g., "benchmark_200000_iters"
-    let folder = format!("mcts_vs_greedy");
+    let folder = "mcts_vs_greedy".to_string();
     std::fs::create_dir_all(&folder).ok();
     println!("============================================================");
     println!("Starting Test");
-    println!("Config A: {} @ 200k iters", "MCTS");
+    println!("Config A: MCTS @ 200k iters");
     println!("Config B: {:?} ", "Greedy");
     println!("============================================================");
 
@@ -26,8 +26,6 @@ fn main() {
         let opponent = PluginBot::load("plugins/alphabeta_bot_plugin/target/release/libalphabeta_bot_plugin.so")
             .expect("Failed to load bot");
 
-        let file_name = format!("{}/mcts_white_{}.txt", folder, i);
-
         let config = MatchConfig {
             time_per_move: Duration::from_secs(600),
             ..Default::default()
@@ -55,8 +53,6 @@ fn main() {
         let opponent = PluginBot::load("plugins/alphabeta_bot_plugin/target/release/libalphabeta_bot_plugin.so")
             .expect("Failed to load bot");
 
-        let file_name = format!("{}/mcts_black_{}.txt", folder, i);
-
         let config = MatchConfig {
             time_per_move: Duration::from_secs(600),
             ..Default::default()
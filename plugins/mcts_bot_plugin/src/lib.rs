@@ -3,38 +3,88 @@ pub mod zobrist;
 pub mod transposition;
 pub mod mcts;
 
-use hnefatafl_arena::{Bot, GameState, Move, Player, Position, Piece};
-use std::time::Duration;
+use hnefatafl_arena::{
+    Bot, BotAction, BotOption, GameState, Move, OptionError, OptionValue, Player, Position, SearchInfo,
+    SearchInfoSender, TimeInfo,
+};
+use std::time::{Duration, Instant};
 use std::io::Write;
-use crate::mcts::{SimulationType, MCTS};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use crate::mcts::{SearchProgress, SimulationType, MCTS};
+use crate::transposition::MAX_ITER;
+
+const DEFAULT_SEED: i64 = 0xCAFEBABE;
+const DEFAULT_ITERATIONS: i64 = 200_000;
+const DEFAULT_THREADS: i64 = 8;
 
 pub struct MctsBot {
     name: String,
-    engine: MCTS,
+    // Shared with the pondering thread, which holds the lock while it searches
+    engine: Arc<Mutex<MCTS>>,
     game: hnefatafl::GameState,
     current_player: Option<Player>,
+    seed: u64,
+    iterations: u32,
+    threads: usize,
+    heavy_playouts: bool,
+    resign: bool,
+    ponder: bool,
+    pondering: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
+    info: Option<SearchInfoSender>,
 }
 
 impl Default for MctsBot {
     fn default() -> Self {
-        let engine = MCTS::new(0xCAFEBABE, 200_000, SimulationType::ParallelHeavy(8));
+        let engine = MCTS::new(DEFAULT_SEED as u64, DEFAULT_ITERATIONS as u32, SimulationType::ParallelHeavy(DEFAULT_THREADS as usize));
         let game = hnefatafl::GameState::new(&engine.z_table);
         Self {
             name: "MctsBot".to_string(),
-            engine,
+            engine: Arc::new(Mutex::new(engine)),
             game,
             current_player: None,
+            seed: DEFAULT_SEED as u64,
+            iterations: DEFAULT_ITERATIONS as u32,
+            threads: DEFAULT_THREADS as usize,
+            heavy_playouts: true,
+            resign: true,
+            ponder: true,
+            pondering: None,
+            info: None,
         }
     }
 }
 
+/// Convert internal move format [row, col, row, col] to arena Move
+#[inline]
+fn convert_move(internal_move: &[usize; 4]) -> Move {
+    Move {
+        from: Position::new(internal_move[0], internal_move[1]),
+        to: Position::new(internal_move[2], internal_move[3]),
+    }
+}
+
 impl MctsBot {
-    /// Convert internal move format [row, col, row, col] to arena Move
-    #[inline]
-    fn convert_move(&self, internal_move: &[usize; 4]) -> Move {
-        Move {
-            from: Position::new(internal_move[0], internal_move[1]),
-            to: Position::new(internal_move[2], internal_move[3]),
+    /// Rebuild the engine after an option change (this also resets the internal game)
+    fn rebuild_engine(&mut self) {
+        self.stop_pondering();
+        let sim_type = match (self.threads, self.heavy_playouts) {
+            (1, true) => SimulationType::Heavy,
+            (1, false) => SimulationType::Light,
+            (n, true) => SimulationType::ParallelHeavy(n),
+            (n, false) => SimulationType::ParallelLight(n),
+        };
+        let engine = MCTS::new(self.seed, self.iterations, sim_type);
+        self.game = hnefatafl::GameState::new(&engine.z_table);
+        self.engine = Arc::new(Mutex::new(engine));
+    }
+
+    /// Signal the pondering thread to stop and wait until it releases the engine
+    fn stop_pondering(&mut self) {
+        if let Some((handle, stop)) = self.pondering.take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
         }
     }
 
@@ -50,6 +100,42 @@ impl Bot for MctsBot {
         &self.name
     }
 
+    fn options(&self) -> Vec<BotOption> {
+        vec![
+            BotOption::int("iterations", DEFAULT_ITERATIONS, 1, MAX_ITER as i64 - 1),
+            BotOption::int("seed", DEFAULT_SEED, 0, i64::MAX),
+            BotOption::int("threads", DEFAULT_THREADS, 1, 256),
+            BotOption::bool("heavy_playouts", true),
+            BotOption::bool("resign", true),
+            BotOption::bool("ponder", true),
+        ]
+    }
+
+    fn set_option(&mut self, name: &str, value: &OptionValue) -> Result<(), OptionError> {
+        let invalid = || OptionError::InvalidValue {
+            name: name.to_string(),
+            reason: format!("unexpected value {}", value),
+        };
+        match name {
+            "iterations" => self.iterations = value.as_int().ok_or_else(invalid)? as u32,
+            "seed" => self.seed = value.as_int().ok_or_else(invalid)? as u64,
+            "threads" => self.threads = value.as_int().ok_or_else(invalid)? as usize,
+            "heavy_playouts" => self.heavy_playouts = value.as_bool().ok_or_else(invalid)?,
+            // These don't affect the engine, no rebuild needed
+            "resign" => {
+                self.resign = value.as_bool().ok_or_else(invalid)?;
+                return Ok(());
+            }
+            "ponder" => {
+                self.ponder = value.as_bool().ok_or_else(invalid)?;
+                return Ok(());
+            }
+            _ => return Err(OptionError::Unknown(name.to_string())),
+        }
+        self.rebuild_engine();
+        Ok(())
+    }
+
     fn get_move(&mut self, state: &GameState, _time_limit: Duration) -> Option<Move> {
         // Reset game state to match arena state on each move ?
         // pretty stupid, i dont wanna lose my game state every turn by creating a new one ?!
@@ -66,12 +152,90 @@ impl Bot for MctsBot {
         };
         self.game.player = internal_player;
 
-        // Use stderr for debug output (safe in plugin context)
-        let mut stderr = std::io::stderr();
+        // Collect the engine's debug output for stderr
+        let mut output = Vec::new();
+
+        // Report the search while it runs: real playout counts and the most visited line
+        let start = Instant::now();
+        let info = self.info.clone();
+        let mut report = |progress: SearchProgress| {
+            let Some(info) = &info else { return };
+            let elapsed = start.elapsed().as_secs_f64().max(1e-6);
+            info.send(SearchInfo {
+                depth: Some(progress.pv.len() as u32),
+                // Expected result in thousandths, +1000 for a sure win
+                score: progress.value.map(|value| (value * 1000.0).round() as i32),
+                pv: progress.pv.iter().map(|m| convert_move(m)).collect(),
+                nodes: Some(progress.playouts as u64),
+                nps: Some((progress.playouts as f64 / elapsed) as u64),
+                text: None,
+            });
+        };
 
         // Run MCTS search to find best move
-        let played_move = self.engine.computer_move(&mut self.game, &mut stderr);
-        Some(self.convert_move(&played_move))
+        self.stop_pondering();
+        let played_move = self.engine.lock().unwrap().computer_move(&mut self.game, &mut report, &mut output);
+        let mv = convert_move(&played_move);
+
+        // Use stderr for debug output (safe in plugin context)
+        if self.info.is_none() {
+            let _ = std::io::stderr().write_all(&output);
+        }
+
+        Some(mv)
+    }
+
+    fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+        match self.get_move(state, time.move_budget()) {
+            // Every move loses against best play, stop wasting tournament time
+            Some(_) if self.resign && self.engine.lock().unwrap().proven_loss => BotAction::Resign,
+            mv => mv.into(),
+        }
+    }
+
+    fn set_search_info_sender(&mut self, sender: SearchInfoSender) {
+        self.info = Some(sender);
+    }
+
+    fn ponder_start(&mut self, state: &GameState) -> Option<Move> {
+        if !self.ponder {
+            return None;
+        }
+
+        // Search the position with the opponent to move; the transposition
+        // table keeps the results whichever reply is actually played
+        let mut position = self.game;
+        position.player = match state.current_player() {
+            Player::Attackers => 'B',
+            Player::Defenders => 'W',
+        };
+        // Without statistics for the replies yet, any legal reply will do
+        let expected = match self.engine.lock().unwrap().expected_move(&position) {
+            Some(mv) => convert_move(&mv),
+            None => *state.legal_moves(state.current_player()).first()?,
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let engine = Arc::clone(&self.engine);
+        let thread_stop = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            engine.lock().unwrap().ponder(&position, &thread_stop);
+        });
+        self.pondering = Some((handle, stop));
+
+        Some(expected)
+    }
+
+    fn ponder_hit(&mut self) {
+        self.stop_pondering();
+    }
+
+    fn ponder_miss(&mut self) {
+        self.stop_pondering();
+    }
+
+    fn game_end(&mut self) {
+        self.stop_pondering();
     }
 
     fn game_start(&mut self, player: Player) {
@@ -96,7 +260,15 @@ impl Bot for MctsBot {
         let mut stderr = std::io::stderr();
         let _ = writeln!(stderr, "Notified of move: {:?}", internal_mv);
         
-        self.game.move_piece(&internal_mv, &self.engine.z_table, true, &mut stderr);
+        self.stop_pondering();
+        let engine = self.engine.lock().unwrap();
+        self.game.move_piece(&internal_mv, &engine.z_table, true, &mut stderr);
+    }
+}
+
+impl Drop for MctsBot {
+    fn drop(&mut self) {
+        self.stop_pondering();
     }
 }
 
@@ -1,6 +1,8 @@
 //! MCTS algorithm.
 
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use rand::prelude::*;
 use rayon::prelude::*;
 
@@ -26,6 +28,22 @@ const DRAW: isize = 0;
 /// Threshold to consider a node "Solved" in the TT.
 const SOLVED_THRESHOLD: usize = 1 << (WINS_BITS - 2);
 
+/// How often a running search reports its progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Longest principal variation reported.
+const MAX_PV_LENGTH: usize = 8;
+
+/// Snapshot of a running search, for live analysis.
+#[derive(Clone, Debug)]
+pub struct SearchProgress {
+    /// Playouts run by this search so far.
+    pub playouts: usize,
+    /// Most visited line from the root.
+    pub pv: Vec<[usize; 4]>,
+    /// Expected result of the first move of `pv` for the side to move, from -1 (loss) to 1 (win).
+    pub value: Option<f64>,
+}
+
 /// Maximum number of generations (to prevent data corruption) according to current bit layout.
 const MAX_GEN: u32 = 1 << 15; // = 2^GEN_BITS
 
@@ -52,6 +70,9 @@ pub struct MCTS {
     overwritten_entries_out: usize,
 
     pub sim_type: SimulationType,
+
+    /// Set when the last chosen move was picked because every move is a proven loss.
+    pub proven_loss: bool,
 }
 
 impl MCTS {
@@ -73,6 +94,7 @@ impl MCTS {
             overwritten_entries_in: 0,
             overwritten_entries_out: 0,
             sim_type,
+            proven_loss: false,
         }
     }
 
@@ -152,14 +174,27 @@ impl MCTS {
 /// ======================
 impl MCTS {
     /// Apply engine move to state.
-    pub fn computer_move<W: Write>(&mut self, state: &mut GameState, writer: &mut W) -> [usize; 4] {
-        let m = self.get_move(&state, writer);
+    /// `progress` is called every `PROGRESS_INTERVAL` while the search runs and once at its end.
+    pub fn computer_move<W: Write>(
+        &mut self,
+        state: &mut GameState,
+        progress: &mut dyn FnMut(SearchProgress),
+        writer: &mut W,
+    ) -> [usize; 4] {
+        let m = self.get_move(&state, progress, writer);
         state.move_piece(&m, &self.z_table, true, writer);
         m
     }
 
     /// Get best move according to MCTS.
-    fn get_move<W: Write>(&mut self, root: &GameState, writer: &mut W) -> [usize; 4] {
+    fn get_move<W: Write>(
+        &mut self,
+        root: &GameState,
+        progress: &mut dyn FnMut(SearchProgress),
+        writer: &mut W,
+    ) -> [usize; 4] {
+        self.proven_loss = false;
+
         // Heuristics.
         if root.player == 'W' {
             // 1.
@@ -177,7 +212,7 @@ impl MCTS {
         }
 
         // Search game tree.
-        self.start_search(root, writer);
+        self.start_search(root, None, Some(progress), writer);
 
         // === CHOOSE BEST MOVE: the most visited child, considering solved childs ===
         let mut moves = Vec::with_capacity(MAX_MOVES);
@@ -258,6 +293,7 @@ impl MCTS {
         // b) ALL moves are "Proven Losses" (We are checkmated).
         if let Some(loss_mv) = forced_loss_move {
             writeln!(writer, "Resigning... (All moves lead to proven loss)").ok();
+            self.proven_loss = true;
             return loss_mv;
         }
 
@@ -267,7 +303,67 @@ impl MCTS {
         *moves.choose(&mut rng).unwrap()
     }
 
-    fn start_search<W: Write>(&mut self, root: &GameState, writer: &mut W) {
+    /// Search on the opponent's time until `stop` is set (at most one regular search).
+    /// Results stay in the transposition table and are reused by the next search.
+    pub fn ponder(&mut self, root: &GameState, stop: &AtomicBool) {
+        self.start_search(root, Some(stop), None, &mut std::io::sink());
+    }
+
+    /// Most visited reply from `root` according to the transposition table.
+    pub fn expected_move(&mut self, root: &GameState) -> Option<[usize; 4]> {
+        let mut moves = Vec::with_capacity(MAX_MOVES);
+        root.get_legal_moves(&mut moves, true);
+
+        moves.into_iter()
+            .filter_map(|m| {
+                let child_hash = root.next_hash(&m, &self.z_table);
+                let visits = self.transpositions.get_bucket(child_hash)
+                    .get_entry(child_hash)
+                    .map(|entry| entry.get_n_visits())?;
+                Some((m, visits))
+            })
+            .max_by_key(|&(_, visits)| visits)
+            .map(|(m, _)| m)
+    }
+
+    /// Most visited line from `root` and the value of its first move, from the transposition table.
+    fn progress(&mut self, root: &GameState, playouts: usize) -> SearchProgress {
+        let mut pv = Vec::new();
+        let mut value = None;
+        let mut state = *root;
+        let mut moves = Vec::with_capacity(MAX_MOVES);
+        while pv.len() < MAX_PV_LENGTH && state.check_game_over().is_none() {
+            moves.clear();
+            state.get_legal_moves(&mut moves, true);
+            let best = moves.iter()
+                .filter_map(|m| {
+                    let child_hash = state.next_hash(m, &self.z_table);
+                    let entry = self.transpositions.get_bucket(child_hash).get_entry(child_hash)?;
+                    Some((*m, entry.get_n_visits(), entry.get_n_wins()))
+                })
+                .max_by_key(|&(_, visits, _)| visits);
+            let Some((m, visits, wins)) = best else { break };
+            if pv.is_empty() {
+                // Child entries count wins for the player to move there, i.e. the opponent.
+                value = Some(if visits >= SOLVED_THRESHOLD {
+                    -(wins.signum() as f64)
+                } else {
+                    -(wins as f64) / visits.max(1) as f64
+                });
+            }
+            pv.push(m);
+            state.move_piece(&m, &self.z_table, true, &mut std::io::sink());
+        }
+        SearchProgress { playouts, pv, value }
+    }
+
+    fn start_search<W: Write>(
+        &mut self,
+        root: &GameState,
+        stop: Option<&AtomicBool>,
+        mut progress: Option<&mut dyn FnMut(SearchProgress)>,
+        writer: &mut W,
+    ) {
         self.increase_generation();
 
         // Retrieve stats for root.
@@ -285,10 +381,26 @@ impl MCTS {
 
         // SEARCH GAME TREE: SELECTION
         let batch_size = self.get_batch_size();
+        let mut playouts = 0;
+        let mut last_report = Instant::now();
         for _ in 1..self.iterations_per_move {
+            if stop.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+                break;
+            }
             // Selection and Backpropagation to the root.
             root_wins += self.selection(root, root_visits, writer); // Increment value.
             root_visits += batch_size;
+            playouts += batch_size;
+
+            if let Some(report) = progress.as_mut() {
+                if last_report.elapsed() >= PROGRESS_INTERVAL {
+                    report(self.progress(root, playouts));
+                    last_report = Instant::now();
+                }
+            }
+        }
+        if let Some(report) = progress {
+            report(self.progress(root, playouts));
         }
 
         // BACKPROPAGATION to root.
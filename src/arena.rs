@@ -1,10 +1,24 @@
-use crate::bot::Bot;
-use crate::game::{GameResult, GameState, Player, Variant};
+use crate::bot::{Bot, BotAction, SearchInfo, SearchInfoSender};
+use crate::clock::{Clock, TimeControl, TimeInfo};
+use crate::game::{GameError, GameResult, GameState, Move, Player, Variant};
+use crate::observer::{ConsoleObserver, MatchObserver};
+use crate::opening::OpeningSuite;
+use crate::rating::{elo_difference, expected_score};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone)]
 pub struct MatchConfig {
+    /// Limit for every move, used when there is no `time_control`
     pub time_per_move: Duration,
     pub max_moves: usize,
+    /// Game clock for each side; overrides `time_per_move`
+    pub time_control: Option<TimeControl>,
+    /// Let bots think on the opponent's time (see `Bot::ponder_start`)
+    pub pondering: bool,
 }
 
 impl Default for MatchConfig {
@@ -12,28 +26,207 @@ impl Default for MatchConfig {
         MatchConfig {
             time_per_move: Duration::from_secs(5),
             max_moves: 200,
+            time_control: None,
+            pondering: false,
         }
     }
 }
 
-pub enum MatchResult {
-    AttackersWin { winner_name: String, moves: usize },
-    DefendersWin { winner_name: String, moves: usize },
-    Draw { moves: usize },
-    Timeout { violator: String, winner: String },
-    IllegalMove { violator: String, winner: String },
+/// Why a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Termination {
+    /// The king reached a corner
+    Escape,
+    KingCapture,
+    /// The side to move had no legal moves and lost
+    NoLegalMoves,
+    /// A position occurred for the third time, which the defenders lose
+    Repetition,
+    /// `max_moves` was reached; a draw
+    MoveLimit,
+    Timeout,
+    IllegalMove,
+    Resignation,
+    /// The bot panicked, or its process died or answered nonsense
+    Crash,
+    /// A draw offer was accepted
+    DrawAgreed,
+    /// An observer stopped the match before it was decided; no winner
+    Aborted,
+}
+
+impl Termination {
+    /// Whether the loser forfeited on its own turn rather than being beaten
+    /// on the board
+    pub fn is_forfeit(&self) -> bool {
+        matches!(
+            self,
+            Termination::Timeout
+                | Termination::IllegalMove
+                | Termination::Resignation
+                | Termination::Crash
+        )
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Termination::Escape => "king escape",
+            Termination::KingCapture => "king capture",
+            Termination::NoLegalMoves => "no legal moves",
+            Termination::Repetition => "threefold repetition",
+            Termination::MoveLimit => "move limit",
+            Termination::Timeout => "timeout",
+            Termination::IllegalMove => "illegal move",
+            Termination::Resignation => "resignation",
+            Termination::Crash => "crash",
+            Termination::DrawAgreed => "draw agreement",
+            Termination::Aborted => "abort",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Winning side, `None` for a draw, and why the game ended
+type GameEnd = (Option<Player>, Termination);
+
+/// Outcome of a game with its full record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub attacker: String,
+    pub defender: String,
+    /// `None` for a draw
+    pub winning_side: Option<Player>,
+    pub termination: Termination,
+    /// Forced moves played before the bots took over
+    pub opening: Vec<Move>,
+    /// Every move the bots played, with think times
+    pub record: Vec<MoveRecord>,
 }
 
 impl MatchResult {
+    /// A game that ended before the first move because a bot could not be
+    /// created; the other side wins as if it had crashed
+    pub fn not_started(
+        attacker: &str,
+        defender: &str,
+        winning_side: Option<Player>,
+        opening: &[Move],
+    ) -> Self {
+        MatchResult {
+            attacker: attacker.to_string(),
+            defender: defender.to_string(),
+            winning_side,
+            termination: Termination::Crash,
+            opening: opening.to_vec(),
+            record: Vec::new(),
+        }
+    }
+
+    /// Name of the winner, `None` for a draw
     pub fn winner(&self) -> Option<&str> {
-        match self {
-            MatchResult::AttackersWin { winner_name, .. } => Some(winner_name),
-            MatchResult::DefendersWin { winner_name, .. } => Some(winner_name),
-            MatchResult::Timeout { winner, .. } => Some(winner),
-            MatchResult::IllegalMove { winner, .. } => Some(winner),
-            MatchResult::Draw { .. } => None,
+        self.winning_side.map(|side| self.name(side))
+    }
+
+    /// Name of the loser, `None` for a draw
+    pub fn loser(&self) -> Option<&str> {
+        self.winning_side.map(|side| self.name(side.opponent()))
+    }
+
+    fn name(&self, side: Player) -> &str {
+        match side {
+            Player::Attackers => &self.attacker,
+            Player::Defenders => &self.defender,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winning_side.is_none()
+    }
+
+    /// 1 if the attackers won, 0.5 for a draw, 0 if the defenders won
+    pub fn attacker_score(&self) -> f64 {
+        match self.winning_side {
+            Some(Player::Attackers) => 1.0,
+            Some(Player::Defenders) => 0.0,
+            None => 0.5,
         }
     }
+
+    /// Plies played, opening included
+    /// A forfeit happened while the loser was thinking about ply `move_count() + 1`
+    pub fn move_count(&self) -> usize {
+        self.opening.len() + self.record.len()
+    }
+
+    /// Every move of the game from the starting position, opening included
+    pub fn moves(&self) -> Vec<Move> {
+        self.opening
+            .iter()
+            .copied()
+            .chain(self.record.iter().map(|record| record.mv))
+            .collect()
+    }
+
+    /// How long `player` thought about each of its moves
+    pub fn think_times(&self, player: Player) -> Vec<Duration> {
+        self.record
+            .iter()
+            .filter(|record| record.player == player)
+            .map(|record| record.think_time)
+            .collect()
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let moves = self.move_count();
+        if self.termination == Termination::Aborted {
+            return write!(f, "Stopped after {} moves", moves);
+        }
+        let (Some(side), Some(winner), Some(loser)) =
+            (self.winning_side, self.winner(), self.loser())
+        else {
+            return write!(f, "Draw by {} after {} moves", self.termination, moves);
+        };
+        let role = match side {
+            Player::Attackers => "Attackers",
+            Player::Defenders => "Defenders",
+        };
+
+        if self.termination.is_forfeit() {
+            write!(
+                f,
+                "{} wins as {}: {} by {} on move {}",
+                winner,
+                role,
+                self.termination,
+                loser,
+                moves + 1
+            )
+        } else {
+            write!(
+                f,
+                "{} wins as {} by {} after {} moves",
+                winner, role, self.termination, moves
+            )
+        }
+    }
+}
+
+type Observers = Arc<Mutex<Vec<Box<dyn MatchObserver>>>>;
+
+/// One played move in the game record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    pub player: Player,
+    pub mv: Move,
+    pub think_time: Duration,
+    /// Main time left on the mover's clock after the move, if a clock is used
+    pub clock_remaining: Option<Duration>,
+    /// Last search info the bot reported before returning the move
+    pub search_info: Option<SearchInfo>,
 }
 
 pub struct Match {
@@ -41,10 +234,23 @@ pub struct Match {
     state: GameState,
     attacker_bot: Box<dyn Bot>,
     defender_bot: Box<dyn Bot>,
-    verbose: bool,
+    /// Shared with the search info senders, which bots may call from any thread
+    observers: Observers,
+    record: Vec<MoveRecord>,
+    attacker_info: Arc<Mutex<Option<SearchInfo>>>,
+    defender_info: Arc<Mutex<Option<SearchInfo>>>,
+    /// Side whose draw offer is waiting for the opponent's answer
+    draw_offer: Option<Player>,
+    /// Side that is pondering and the reply it expects
+    ponder: Option<(Player, Move)>,
+    attacker_clock: Option<Clock>,
+    defender_clock: Option<Clock>,
+    /// Forced moves played before the bots take over
+    opening: Vec<Move>,
 }
 
 impl Match {
+    /// `verbose` prints the game through a `ConsoleObserver`
     pub fn new(
         attacker_bot: Box<dyn Bot>,
         defender_bot: Box<dyn Bot>,
@@ -67,260 +273,875 @@ impl Match {
         verbose: bool,
         variant: Variant,
     ) -> Self {
+        let attacker_clock = config.time_control.map(Clock::new);
+        let defender_clock = config.time_control.map(Clock::new);
+        let mut observers: Vec<Box<dyn MatchObserver>> = Vec::new();
+        if verbose {
+            observers.push(Box::new(ConsoleObserver::new()));
+        }
         Match {
             config,
             state: GameState::new(variant),
             attacker_bot,
             defender_bot,
-            verbose,
+            observers: Arc::new(Mutex::new(observers)),
+            record: Vec::new(),
+            attacker_info: Arc::new(Mutex::new(None)),
+            defender_info: Arc::new(Mutex::new(None)),
+            draw_offer: None,
+            ponder: None,
+            attacker_clock,
+            defender_clock,
+            opening: Vec::new(),
         }
     }
 
+    /// Start the game after a forced sequence of moves
+    /// The moves are played immediately; bots are told about them through
+    /// `notify_move` right after `game_start`
+    pub fn set_opening(&mut self, moves: &[Move]) -> Result<(), GameError> {
+        for &mv in moves {
+            self.state.make_move(mv)?;
+        }
+        self.opening.extend_from_slice(moves);
+        Ok(())
+    }
+
+    /// Tell `observer` about everything that happens from now on
+    pub fn add_observer(&mut self, observer: Box<dyn MatchObserver>) {
+        self.observers.lock().unwrap().push(observer);
+    }
+
+    fn notify(&self, mut callback: impl FnMut(&mut dyn MatchObserver)) {
+        for observer in self.observers.lock().unwrap().iter_mut() {
+            callback(observer.as_mut());
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The side's game clock, if the match uses a time control
+    pub fn clock(&self, player: Player) -> Option<&Clock> {
+        match player {
+            Player::Attackers => self.attacker_clock.as_ref(),
+            Player::Defenders => self.defender_clock.as_ref(),
+        }
+    }
+
+    fn time_info(&self, player: Player) -> TimeInfo {
+        match (self.clock(player), self.clock(player.opponent())) {
+            (Some(own), Some(opponent)) => TimeInfo::from_clocks(own, opponent),
+            _ => TimeInfo::per_move(self.config.time_per_move),
+        }
+    }
+
+    /// Moves played so far, with timing and reported search info
+    pub fn record(&self) -> &[MoveRecord] {
+        &self.record
+    }
+
+    fn info_sender(&self, player: Player) -> SearchInfoSender {
+        let slot = match player {
+            Player::Attackers => self.attacker_info.clone(),
+            Player::Defenders => self.defender_info.clone(),
+        };
+        let observers = self.observers.clone();
+
+        SearchInfoSender::new(move |info| {
+            for observer in observers.lock().unwrap().iter_mut() {
+                observer.search_info(player, &info);
+            }
+            *slot.lock().unwrap() = Some(info);
+        })
+    }
+
     pub fn play(&mut self) -> MatchResult {
+        let attacker_sender = self.info_sender(Player::Attackers);
+        let defender_sender = self.info_sender(Player::Defenders);
+        self.attacker_bot.set_search_info_sender(attacker_sender);
+        self.defender_bot.set_search_info_sender(defender_sender);
+
         // Notify bots that game is starting
         self.attacker_bot.game_start(Player::Attackers);
         self.defender_bot.game_start(Player::Defenders);
-
-        if self.verbose {
-            println!("Match starting:");
-            println!("  Attackers: {}", self.attacker_bot.name());
-            println!("  Defenders: {}", self.defender_bot.name());
-            println!("\nInitial board:");
-            println!("{}", self.state.display_board());
+        for &mv in &self.opening {
+            self.attacker_bot.notify_move(mv);
+            self.defender_bot.notify_move(mv);
         }
 
+        let (attacker, defender) = (self.attacker_bot.name(), self.defender_bot.name());
+        self.notify(|o| o.game_start(attacker, defender, &self.opening, &self.state));
+
+        let mut forfeit = None;
         while !self.state.is_game_over() && self.state.move_count() < self.config.max_moves {
             let current_player = self.state.current_player();
-            let result = self.play_move(current_player);
-
-            if let Some(result) = result {
-                return result;
+            forfeit = self.play_move(current_player);
+            if forfeit.is_some() {
+                break;
             }
         }
+        self.stop_pondering(None);
 
-        // Game ended normally
         self.attacker_bot.game_end();
         self.defender_bot.game_end();
 
-        let moves = self.state.move_count();
-
-        if let Some(result) = self.state.result() {
-            match result {
-                GameResult::AttackersWin => {
-                    if self.verbose {
-                        println!("\n{} wins as Attackers!", self.attacker_bot.name());
-                    }
-                    MatchResult::AttackersWin {
-                        winner_name: self.attacker_bot.name().to_string(),
-                        moves,
-                    }
-                }
-                GameResult::DefendersWin => {
-                    if self.verbose {
-                        println!("\n{} wins as Defenders!", self.defender_bot.name());
-                    }
-                    MatchResult::DefendersWin {
-                        winner_name: self.defender_bot.name().to_string(),
-                        moves,
-                    }
-                }
-                GameResult::Draw => {
-                    if self.verbose {
-                        println!("\nGame is a draw!");
-                    }
-                    MatchResult::Draw { moves }
-                }
-            }
-        } else {
-            // Max moves reached
-            if self.verbose {
-                println!("\nMax moves ({}) reached - Draw!", self.config.max_moves);
-            }
-            MatchResult::Draw { moves }
-        }
+        let (winning_side, termination) = forfeit.unwrap_or_else(|| self.board_end());
+        let result = MatchResult {
+            attacker: self.attacker_bot.name().to_string(),
+            defender: self.defender_bot.name().to_string(),
+            winning_side,
+            termination,
+            opening: self.opening.clone(),
+            record: self.record.clone(),
+        };
+        self.notify(|o| o.game_end(&result, &self.state));
+        result
     }
 
-    fn play_move(&mut self, current_player: Player) -> Option<MatchResult> {
+    /// How a game that nobody forfeited ended
+    fn board_end(&self) -> GameEnd {
+        let winning_side = match self.state.result() {
+            Some(GameResult::AttackersWin) => Some(Player::Attackers),
+            Some(GameResult::DefendersWin) => Some(Player::Defenders),
+            Some(GameResult::Draw) => None,
+            None => return (None, Termination::MoveLimit),
+        };
+        let termination = match self.state.king_position() {
+            None => Termination::KingCapture,
+            Some(king) if self.state.is_corner(king) => Termination::Escape,
+            Some(_) if self.state.repetitions() >= 3 => Termination::Repetition,
+            Some(_) => Termination::NoLegalMoves,
+        };
+        (winning_side, termination)
+    }
+
+    fn play_move(&mut self, current_player: Player) -> Option<GameEnd> {
+        // Get move from bot with time limit
+        let time = self.time_info(current_player);
+        self.notify(|o| o.turn_start(current_player, &self.state, &time));
+        let mut stop = false;
+        self.notify(|o| stop |= o.should_stop());
+        if stop {
+            return Some((None, Termination::Aborted));
+        }
         let bot = match current_player {
             Player::Attackers => &mut self.attacker_bot,
             Player::Defenders => &mut self.defender_bot,
         };
-
-        if self.verbose {
-            println!(
-                "\nMove {}: {} to play",
-                self.state.move_count() + 1,
-                bot.name()
-            );
-            println!("Legal moves: {}", self.state.legal_moves(self.state.current_player()).len());
-        }
-
-        // Get move from bot with time limit
+        let info_slot = match current_player {
+            Player::Attackers => &self.attacker_info,
+            Player::Defenders => &self.defender_info,
+        };
+        *info_slot.lock().unwrap() = None;
         let start = Instant::now();
-        let mv = bot.get_move(&self.state, self.config.time_per_move);
+        let action = panic::catch_unwind(AssertUnwindSafe(|| bot.get_action(&self.state, &time)));
         let elapsed = start.elapsed();
 
-        self.handle_move_result(mv, elapsed, current_player)
+        let Ok(action) = action else {
+            return Some((Some(current_player.opponent()), Termination::Crash));
+        };
+        self.handle_move_result(action, elapsed, current_player)
     }
 
     fn handle_move_result(
         &mut self,
-        mv: Option<crate::game::Move>,
+        action: BotAction,
         elapsed: Duration,
         current_player: Player,
-    ) -> Option<MatchResult> {
-        let bot_name = match current_player {
-            Player::Attackers => self.attacker_bot.name(),
-            Player::Defenders => self.defender_bot.name(),
-        };
-
+    ) -> Option<GameEnd> {
         // Check timeout
-        if elapsed > self.config.time_per_move {
-            let violator = bot_name.to_string();
-            let winner = match current_player {
-                Player::Attackers => self.defender_bot.name().to_string(),
-                Player::Defenders => self.attacker_bot.name().to_string(),
-            };
-
-            if self.verbose {
-                println!(
-                    "TIMEOUT: {} took {:?} (limit: {:?})",
-                    violator, elapsed, self.config.time_per_move
-                );
-            }
-
-            return Some(MatchResult::Timeout { violator, winner });
+        let limit = self.time_info(current_player).move_limit;
+        let clock = match current_player {
+            Player::Attackers => self.attacker_clock.as_mut(),
+            Player::Defenders => self.defender_clock.as_mut(),
+        };
+        let in_time = match clock {
+            Some(clock) => clock.charge(elapsed),
+            None => elapsed <= self.config.time_per_move,
+        };
+        if !in_time {
+            self.notify(|o| o.timeout(current_player, elapsed, limit));
+            return Some((Some(current_player.opponent()), Termination::Timeout));
         }
+        let clock_remaining = self.clock(current_player).map(Clock::remaining);
 
-        // Check if bot returned a move
-        let mv = match mv {
-            Some(m) => m,
-            None => {
-                // No legal moves or bot gave up
-                if self.verbose {
-                    println!("{} returned no move", bot_name);
-                }
+        // Any answer other than accepting declines a pending offer
+        let offer = self.draw_offer.take();
 
-                return Some(MatchResult::Draw {
-                    moves: self.state.move_count(),
+        let mv = match action {
+            BotAction::Move(mv) | BotAction::OfferDraw(mv) => mv,
+            BotAction::Resign => {
+                return Some((Some(current_player.opponent()), Termination::Resignation));
+            }
+            BotAction::AcceptDraw if offer == Some(current_player.opponent()) => {
+                return Some((None, Termination::DrawAgreed));
+            }
+            BotAction::AcceptDraw => {
+                self.notify(|o| {
+                    o.illegal_move(current_player, "accepted a draw that was not offered")
                 });
+                return Some((Some(current_player.opponent()), Termination::IllegalMove));
+            }
+            BotAction::Forfeit => {
+                return Some((Some(current_player.opponent()), Termination::Crash));
             }
         };
 
-        if self.verbose {
-            println!("{} plays: {} (took {:?})", bot_name, mv, elapsed);
-        }
-
         // Make the move
-        if let Err(e) = self.state.make_move(mv) {
-            let violator = bot_name.to_string();
-            let winner = match current_player {
-                Player::Attackers => self.defender_bot.name().to_string(),
-                Player::Defenders => self.attacker_bot.name().to_string(),
-            };
-
-            if self.verbose {
-                println!("ILLEGAL MOVE: {} - {}", violator, e);
+        let captures = match self.state.make_move_with_captures(mv) {
+            Ok(captures) => captures,
+            Err(e) => {
+                self.notify(|o| o.illegal_move(current_player, &e.to_string()));
+                return Some((Some(current_player.opponent()), Termination::IllegalMove));
             }
+        };
 
-            return Some(MatchResult::IllegalMove { violator, winner });
-        }
+        let info_slot = match current_player {
+            Player::Attackers => &self.attacker_info,
+            Player::Defenders => &self.defender_info,
+        };
+        let search_info = info_slot.lock().unwrap().take();
+        let record = MoveRecord {
+            player: current_player,
+            mv,
+            think_time: elapsed,
+            clock_remaining,
+            search_info,
+        };
+        self.notify(|o| o.move_played(&record, &captures, &self.state));
+        self.record.push(record);
 
         // Notify both bots of the move
+        self.stop_pondering(Some(mv));
         self.attacker_bot.notify_move(mv);
         self.defender_bot.notify_move(mv);
 
-        if self.verbose {
-            println!("{}", self.state.display_board());
+        if matches!(action, BotAction::OfferDraw(_)) && !self.state.is_game_over() {
+            self.notify(|o| o.draw_offered(current_player));
+            self.draw_offer = Some(current_player);
+            match current_player {
+                Player::Attackers => self.defender_bot.notify_draw_offer(),
+                Player::Defenders => self.attacker_bot.notify_draw_offer(),
+            }
+        }
+
+        if self.config.pondering && !self.state.is_game_over() {
+            let bot = match current_player {
+                Player::Attackers => &mut self.attacker_bot,
+                Player::Defenders => &mut self.defender_bot,
+            };
+            if let Some(expected) = bot.ponder_start(&self.state) {
+                self.notify(|o| o.ponder_start(current_player, expected));
+                self.ponder = Some((current_player, expected));
+            }
         }
 
         None
     }
+
+    /// Tell a pondering bot whether the opponent's move `reply` was expected
+    /// `None` ends pondering without a reply, e.g. when the game is over
+    fn stop_pondering(&mut self, reply: Option<Move>) {
+        let Some((player, expected)) = self.ponder.take() else {
+            return;
+        };
+        let bot = match player {
+            Player::Attackers => &mut self.attacker_bot,
+            Player::Defenders => &mut self.defender_bot,
+        };
+
+        if reply == Some(expected) {
+            bot.ponder_hit();
+        } else {
+            bot.ponder_miss();
+        }
+    }
 }
 
-pub struct Tournament {
-    bots: Vec<(String, Box<dyn Bot>)>,
-    #[allow(dead_code)]
-    config: MatchConfig,
-    verbose: bool,
+/// Creates a fresh bot for every game
+/// May fail, e.g. when a process bot does not start; that side forfeits
+/// the game (see `create_bots`)
+pub type BotFactory = Box<dyn Fn() -> Result<Box<dyn Bot>, String> + Send + Sync>;
+
+/// Attacker and defender of a game, or the side that won because the other
+/// could not be created (`None` if neither could) with the reason
+pub type CreatedBots = Result<(Box<dyn Bot>, Box<dyn Bot>), (Option<Player>, String)>;
+
+/// Create the bots of one game
+pub fn create_bots(attacker: &BotFactory, defender: &BotFactory) -> CreatedBots {
+    match (attacker(), defender()) {
+        (Ok(attacker), Ok(defender)) => Ok((attacker, defender)),
+        (Err(e), Ok(_)) => Err((Some(Player::Defenders), format!("attacker: {}", e))),
+        (Ok(_), Err(e)) => Err((Some(Player::Attackers), format!("defender: {}", e))),
+        (Err(attacker), Err(defender)) => Err((
+            None,
+            format!("attacker: {}; defender: {}", attacker, defender),
+        )),
+    }
 }
 
-impl Tournament {
-    pub fn new(config: MatchConfig, verbose: bool) -> Self {
-        Tournament {
-            bots: Vec::new(),
-            config,
-            verbose,
+/// Settings of a sequential probability ratio test
+#[derive(Debug, Clone)]
+pub struct SprtConfig {
+    /// Elo gain of the candidate under the null hypothesis
+    pub elo0: f64,
+    /// Elo gain of the candidate under the alternative hypothesis
+    pub elo1: f64,
+    /// Probability of accepting H1 when H0 is true
+    pub alpha: f64,
+    /// Probability of accepting H0 when H1 is true
+    pub beta: f64,
+    /// Give up without a decision after this many game pairs
+    pub max_pairs: usize,
+    /// Openings to play, each with both colors; `None` generates
+    /// `max_pairs` balanced openings of `opening_plies` random moves
+    pub openings: Option<OpeningSuite>,
+    pub opening_plies: usize,
+    /// Seed for the generated openings
+    pub seed: u64,
+    /// Board used for generated openings; a suite brings its own
+    pub variant: Variant,
+}
+
+impl Default for SprtConfig {
+    fn default() -> Self {
+        SprtConfig {
+            elo0: 0.0,
+            elo1: 20.0,
+            alpha: 0.05,
+            beta: 0.05,
+            max_pairs: 1000,
+            openings: None,
+            opening_plies: 4,
+            seed: 1,
+            variant: Variant::Brandubh,
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    /// The candidate's gain is closer to `elo0`
+    AcceptH0,
+    /// The candidate's gain is closer to `elo1`
+    AcceptH1,
+    /// `max_pairs` reached without a decision
+    Inconclusive,
+}
+
+/// Running statistics of an SPRT
+#[derive(Debug, Clone, Default)]
+pub struct SprtStatus {
+    /// Number of game pairs by the candidate's pair score 0, 0.5, 1, 1.5, 2
+    pub pairs: [u32; 5],
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Log-likelihood ratio of H1 against H0
+    pub llr: f64,
+    /// Accept H0 below this LLR
+    pub lower_bound: f64,
+    /// Accept H1 above this LLR
+    pub upper_bound: f64,
+    /// Estimated Elo difference of candidate over baseline
+    pub elo: f64,
+    /// Half-width of the 95% confidence interval of `elo`
+    pub elo_error: f64,
+}
 
-    pub fn add_bot(&mut self, name: String, bot: Box<dyn Bot>) {
-        self.bots.push((name, bot));
+impl SprtStatus {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
     }
 
-    pub fn run_round_robin(&mut self) -> TournamentResults {
-        let mut results = TournamentResults::new();
+    /// Mean and variance of the mean of the candidate's score per game
+    /// Based on pairs, which accounts for the correlation between the two
+    /// games of an opening. A small count is added to every outcome so that
+    /// a one-sided record still has a variance
+    fn pair_statistics(&self) -> Option<(f64, f64)> {
+        if self.pairs.iter().all(|&n| n == 0) {
+            return None;
+        }
+        let counts = self.pairs.map(|n| n as f64 + 1e-3);
+        let n: f64 = counts.iter().sum();
+        let score = |i: usize| i as f64 / 4.0;
+        let mean = (0..5).map(|i| score(i) * counts[i]).sum::<f64>() / n;
+        let variance = (0..5)
+            .map(|i| (score(i) - mean).powi(2) * counts[i])
+            .sum::<f64>()
+            / n;
+        Some((mean, variance / n))
+    }
 
-        for i in 0..self.bots.len() {
-            for j in (i + 1)..self.bots.len() {
-                // Play two games: each bot plays as both attacker and defender
-                if self.verbose {
-                    println!("\n{}", "=".repeat(60));
-                    println!("Match: {} vs {}", self.bots[i].0, self.bots[j].0);
-                    println!("{}", "=".repeat(60));
-                }
+    fn update(&mut self, config: &SprtConfig) {
+        self.lower_bound = (config.beta / (1.0 - config.alpha)).ln();
+        self.upper_bound = ((1.0 - config.beta) / config.alpha).ln();
 
-                // Game 1: i as attackers, j as defenders
-                // Note: We can't move bots out, so this is a simplified version
-                // In a real implementation, you'd need to handle bot ownership differently
+        let Some((mean, variance)) = self.pair_statistics() else {
+            return;
+        };
 
-                // For now, just record that matches would happen
-                if self.verbose {
-                    println!(
-                        "Game 1: {} (Attackers) vs {} (Defenders)",
-                        self.bots[i].0, self.bots[j].0
-                    );
-                    println!(
-                        "Game 2: {} (Attackers) vs {} (Defenders)",
-                        self.bots[j].0, self.bots[i].0
-                    );
-                }
+        // Generalized SPRT with a normal approximation (Van den Bergh)
+        let s0 = expected_score(config.elo0);
+        let s1 = expected_score(config.elo1);
+        self.llr = (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance);
 
-                results.add_matchup(self.bots[i].0.clone(), self.bots[j].0.clone());
-            }
+        let margin = 1.96 * variance.sqrt();
+        self.elo = elo_difference(mean);
+        self.elo_error = (elo_difference(mean + margin) - elo_difference(mean - margin)) / 2.0;
+    }
+
+    pub fn decision(&self) -> Option<SprtDecision> {
+        if self.llr >= self.upper_bound {
+            Some(SprtDecision::AcceptH1)
+        } else if self.llr <= self.lower_bound {
+            Some(SprtDecision::AcceptH0)
+        } else {
+            None
         }
+    }
+}
 
-        results
+impl fmt::Display for SprtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Games: {}  W/D/L: {}/{}/{}  Elo: {:.1} ± {:.1}  LLR: {:.2} [{:.2}, {:.2}]",
+            self.games(),
+            self.wins,
+            self.draws,
+            self.losses,
+            self.elo,
+            self.elo_error,
+            self.llr,
+            self.lower_bound,
+            self.upper_bound
+        )
     }
 }
 
-#[derive(Debug)]
-pub struct TournamentResults {
-    matchups: Vec<(String, String)>,
+/// Decides whether a candidate bot is stronger than a baseline
+/// Both play every opening once with each color until the SPRT accepts a hypothesis
+pub struct SprtRunner {
+    baseline: BotFactory,
+    candidate: BotFactory,
+    match_config: MatchConfig,
+    config: SprtConfig,
+    status: SprtStatus,
+    openings: OpeningSuite,
 }
 
-impl TournamentResults {
-    pub fn new() -> Self {
-        TournamentResults {
-            matchups: Vec::new(),
+impl SprtRunner {
+    pub fn new(
+        baseline: BotFactory,
+        candidate: BotFactory,
+        match_config: MatchConfig,
+        config: SprtConfig,
+    ) -> Self {
+        let mut status = SprtStatus::default();
+        status.update(&config);
+        let openings = config.openings.clone().unwrap_or_else(|| {
+            OpeningSuite::generate(
+                config.variant,
+                config.max_pairs,
+                config.opening_plies,
+                config.seed,
+            )
+        });
+        SprtRunner {
+            baseline,
+            candidate,
+            match_config,
+            openings,
+            config,
+            status,
         }
     }
 
-    pub fn add_matchup(&mut self, bot1: String, bot2: String) {
-        self.matchups.push((bot1, bot2));
+    pub fn status(&self) -> &SprtStatus {
+        &self.status
     }
 
-    pub fn display(&self) {
-        println!("\nTournament Results:");
-        println!("==================");
-        for (bot1, bot2) in &self.matchups {
-            println!("{} vs {}", bot1, bot2);
+    /// Play game pairs until a decision
+    pub fn run(&mut self) -> SprtDecision {
+        self.run_with_progress(|_, _| {})
+    }
+
+    /// Like `run`, calling `progress` with the pair number and the status
+    /// after every pair
+    pub fn run_with_progress(
+        &mut self,
+        mut progress: impl FnMut(usize, &SprtStatus),
+    ) -> SprtDecision {
+        for pair in 1..=self.config.max_pairs {
+            let opening = self.openings.get(pair - 1).to_vec();
+            let first = self.play_game(&opening, true);
+            let second = self.play_game(&opening, false);
+            self.status.pairs[((first + second) * 2.0) as usize] += 1;
+            self.status.update(&self.config);
+
+            progress(pair, &self.status);
+            if let Some(decision) = self.status.decision() {
+                return decision;
+            }
         }
+        SprtDecision::Inconclusive
+    }
+
+    /// Candidate's score in one game
+    fn play_game(&mut self, opening: &[Move], candidate_attacks: bool) -> f64 {
+        let bots = match candidate_attacks {
+            true => create_bots(&self.candidate, &self.baseline),
+            false => create_bots(&self.baseline, &self.candidate),
+        };
+        let attacker_score = match bots {
+            Ok((attacker, defender)) => {
+                let mut game = Match::with_variant(
+                    attacker,
+                    defender,
+                    self.match_config.clone(),
+                    false,
+                    self.openings.variant,
+                );
+                game.set_opening(opening)
+                    .expect("openings are checked when the suite is loaded");
+                game.play().attacker_score()
+            }
+            Err((winning_side, _)) => {
+                MatchResult::not_started("", "", winning_side, opening).attacker_score()
+            }
+        };
+
+        let score = match candidate_attacks {
+            true => attacker_score,
+            false => 1.0 - attacker_score,
+        };
+        if score > 0.5 {
+            self.status.wins += 1;
+        } else if score < 0.5 {
+            self.status.losses += 1;
+        } else {
+            self.status.draws += 1;
+        }
+        score
     }
 }
 
-impl Default for TournamentResults {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::GreedyBot;
+
+    fn greedy(name: &str) -> Box<dyn Bot> {
+        Box::new(GreedyBot::new(name.to_string()))
+    }
+
+    /// Plays the first legal move, but answers with a fixed action on a given turn
+    struct ScriptedBot {
+        name: String,
+        turn: usize,
+        action_turn: usize,
+        action: fn(Move) -> BotAction,
+    }
+
+    impl ScriptedBot {
+        fn boxed(name: &str, action_turn: usize, action: fn(Move) -> BotAction) -> Box<Self> {
+            Box::new(ScriptedBot {
+                name: name.to_string(),
+                turn: 0,
+                action_turn,
+                action,
+            })
+        }
+    }
+
+    impl Bot for ScriptedBot {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn get_move(&mut self, state: &GameState, _time_limit: Duration) -> Option<Move> {
+            state.legal_moves(state.current_player()).first().copied()
+        }
+
+        fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+            self.turn += 1;
+            let mv = self.get_move(state, time.move_limit).unwrap();
+            if self.turn == self.action_turn {
+                (self.action)(mv)
+            } else {
+                BotAction::Move(mv)
+            }
+        }
+    }
+
+    fn config(max_moves: usize) -> MatchConfig {
+        MatchConfig {
+            max_moves,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_contains_search_info() {
+        let config = MatchConfig {
+            max_moves: 4,
+            ..Default::default()
+        };
+        let mut game = Match::new(greedy("A"), greedy("B"), config, false);
+        game.play();
+
+        let record = game.record();
+        assert_eq!(record.len(), 4);
+        assert_eq!(record[0].player, Player::Attackers);
+        assert_eq!(record[1].player, Player::Defenders);
+        for entry in record {
+            let info = entry
+                .search_info
+                .as_ref()
+                .expect("GreedyBot reports search info");
+            assert_eq!(info.depth, Some(1));
+            assert_eq!(info.pv.first(), Some(&entry.mv));
+        }
+    }
+
+    #[test]
+    fn test_resignation() {
+        let attacker = ScriptedBot::boxed("A", 2, |_| BotAction::Resign);
+        let mut game = Match::new(attacker, greedy("B"), config(20), false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::Resignation);
+        assert_eq!(result.loser(), Some("A"));
+        assert_eq!(result.winner(), Some("B"));
+        assert_eq!(result.record.len(), 2);
+        assert_eq!(result.think_times(Player::Attackers).len(), 1);
+        assert_eq!(result.think_times(Player::Defenders).len(), 1);
+        assert_eq!(
+            result.to_string(),
+            "B wins as Defenders: resignation by A on move 3"
+        );
+
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(serde_json::from_str::<MatchResult>(&json).unwrap(), result);
+    }
+
+    #[test]
+    fn test_crash() {
+        let attacker = ScriptedBot::boxed("A", 2, |_| panic!("bot bug"));
+        let mut game = Match::new(attacker, greedy("B"), config(20), false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::Crash);
+        assert_eq!(result.winning_side, Some(Player::Defenders));
+        assert_eq!(result.attacker_score(), 0.0);
+    }
+
+    #[test]
+    fn test_move_limit() {
+        let mut game = Match::new(greedy("A"), greedy("B"), config(6), false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::MoveLimit);
+        assert!(result.is_draw());
+        assert_eq!(result.moves().len(), 6);
+    }
+
+    #[test]
+    fn test_draw_offer_accepted() {
+        let attacker = ScriptedBot::boxed("A", 1, BotAction::OfferDraw);
+        let defender = ScriptedBot::boxed("B", 1, |_| BotAction::AcceptDraw);
+        let mut game = Match::new(attacker, defender, config(20), false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::DrawAgreed);
+        assert_eq!(result.move_count(), 1);
+    }
+
+    #[test]
+    fn test_declined_draw_offer_expires() {
+        // The defender ignores the offer, then tries to accept it a turn later
+        let attacker = ScriptedBot::boxed("A", 1, BotAction::OfferDraw);
+        let defender = ScriptedBot::boxed("B", 2, |_| BotAction::AcceptDraw);
+        let mut game = Match::new(attacker, defender, config(20), false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::IllegalMove);
+        assert_eq!(result.loser(), Some("B"));
+    }
+
+    /// Expects the opponent's first legal move and counts hits and misses
+    struct PonderBot {
+        expect_first: bool,
+        stats: Arc<Mutex<(usize, usize)>>,
+    }
+
+    impl Bot for PonderBot {
+        fn name(&self) -> &str {
+            "Ponder"
+        }
+
+        fn get_move(&mut self, state: &GameState, _time_limit: Duration) -> Option<Move> {
+            state.legal_moves(state.current_player()).first().copied()
+        }
+
+        fn ponder_start(&mut self, state: &GameState) -> Option<Move> {
+            let moves = state.legal_moves(state.current_player());
+            if self.expect_first {
+                moves.first().copied()
+            } else {
+                moves.last().copied()
+            }
+        }
+
+        fn ponder_hit(&mut self) {
+            self.stats.lock().unwrap().0 += 1;
+        }
+
+        fn ponder_miss(&mut self) {
+            self.stats.lock().unwrap().1 += 1;
+        }
+    }
+
+    #[test]
+    fn test_pondering_hit_and_miss() {
+        for (pondering, expect_first, expected) in [
+            (true, true, (2, 0)),
+            (true, false, (0, 2)),
+            (false, true, (0, 0)),
+        ] {
+            let stats = Arc::new(Mutex::new((0, 0)));
+            let attacker = Box::new(PonderBot {
+                expect_first,
+                stats: stats.clone(),
+            });
+            let defender = ScriptedBot::boxed("B", 0, BotAction::Move);
+            let config = MatchConfig {
+                pondering,
+                ..config(4)
+            };
+            Match::new(attacker, defender, config, false).play();
+
+            // The attacker ponders after each of its two moves and the
+            // defender's reply resolves it
+            assert_eq!(*stats.lock().unwrap(), expected);
+        }
+    }
+
+    /// Sleeps before playing its first legal move and remembers the time info
+    struct SlowBot {
+        delay: Duration,
+        seen: Arc<Mutex<Vec<TimeInfo>>>,
+    }
+
+    impl Bot for SlowBot {
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn get_move(&mut self, state: &GameState, _time_limit: Duration) -> Option<Move> {
+            std::thread::sleep(self.delay);
+            state.legal_moves(state.current_player()).first().copied()
+        }
+
+        fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+            self.seen.lock().unwrap().push(*time);
+            self.get_move(state, time.move_limit).into()
+        }
+    }
+
+    #[test]
+    fn test_flag_falls_on_game_clock() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let attacker = Box::new(SlowBot {
+            delay: Duration::from_millis(40),
+            seen: seen.clone(),
+        });
+        let config = MatchConfig {
+            time_control: Some(TimeControl::sudden_death(Duration::from_millis(100))),
+            ..config(20)
+        };
+        let mut game = Match::new(attacker, greedy("B"), config, false);
+
+        let result = game.play();
+        assert_eq!(result.termination, Termination::Timeout);
+        assert_eq!(result.loser(), Some("Slow"));
+
+        // 100ms lasts two 40ms moves, the third one flags
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].remaining, Duration::from_millis(100));
+        assert!(seen[1].remaining < seen[0].remaining);
+        assert!(seen[1].opponent_remaining < Duration::from_millis(100));
+        assert!(game.record()[0].clock_remaining.is_some());
+    }
+
+    #[test]
+    fn test_sprt_status() {
+        let config = SprtConfig::default();
+        let mut status = SprtStatus {
+            pairs: [0, 10, 40, 30, 20],
+            ..Default::default()
+        };
+        status.update(&config);
+
+        assert!((status.lower_bound + 2.944).abs() < 0.001);
+        assert!((status.upper_bound - 2.944).abs() < 0.001);
+        // Mean score 0.65
+        assert!((status.elo - 107.5).abs() < 1.0);
+        assert!(status.elo_error > 20.0 && status.elo_error < 80.0);
+        assert_eq!(status.decision(), Some(SprtDecision::AcceptH1));
+    }
+
+    fn resigner() -> Result<Box<dyn Bot>, String> {
+        Ok(ScriptedBot::boxed("Bot", 1, |_| BotAction::Resign))
+    }
+
+    #[test]
+    fn test_sprt_decisions() {
+        let sprt = SprtConfig {
+            max_pairs: 100,
+            ..Default::default()
+        };
+
+        let mut runner = SprtRunner::new(
+            Box::new(resigner),
+            Box::new(|| Ok(greedy("Bot"))),
+            config(100),
+            sprt.clone(),
+        );
+        let mut pairs = 0;
+        assert_eq!(
+            runner.run_with_progress(|pair, _| pairs = pair),
+            SprtDecision::AcceptH1
+        );
+        assert_eq!(pairs as u32 * 2, runner.status().games());
+        assert_eq!(runner.status().losses, 0);
+        assert!(runner.status().elo > 100.0);
+
+        // Identical bots split every pair
+        let mut runner = SprtRunner::new(
+            Box::new(|| Ok(greedy("Bot"))),
+            Box::new(|| Ok(greedy("Bot"))),
+            config(100),
+            sprt,
+        );
+        assert_eq!(runner.run(), SprtDecision::AcceptH0);
+        assert!(runner.status().elo.abs() < 1.0);
+
+        // A candidate that cannot be created loses every game
+        let mut runner = SprtRunner::new(
+            Box::new(|| Ok(greedy("Bot"))),
+            Box::new(|| Err("not built".to_string())),
+            config(100),
+            SprtConfig {
+                max_pairs: 100,
+                ..Default::default()
+            },
+        );
+        assert_eq!(runner.run(), SprtDecision::AcceptH0);
+        assert_eq!(runner.status().wins + runner.status().draws, 0);
     }
 }
@@ -1,15 +1,353 @@
+use crate::clock::TimeInfo;
 use crate::game::{GameState, Move, Player};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
+use thiserror::Error;
+
+/// Type, default and allowed range of a configurable bot option
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OptionKind {
+    Int { default: i64, min: i64, max: i64 },
+    Bool { default: bool },
+    String { default: String },
+}
+
+/// Description of a single option a bot accepts through `Bot::set_option`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotOption {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: OptionKind,
+}
+
+/// Value assigned to a bot option
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OptionError {
+    #[error("Unknown option: {0}")]
+    Unknown(String),
+    #[error("Invalid value for option {name}: {reason}")]
+    InvalidValue { name: String, reason: String },
+}
+
+impl BotOption {
+    pub fn int(name: &str, default: i64, min: i64, max: i64) -> Self {
+        BotOption {
+            name: name.to_string(),
+            kind: OptionKind::Int { default, min, max },
+        }
+    }
+
+    pub fn bool(name: &str, default: bool) -> Self {
+        BotOption {
+            name: name.to_string(),
+            kind: OptionKind::Bool { default },
+        }
+    }
+
+    pub fn string(name: &str, default: &str) -> Self {
+        BotOption {
+            name: name.to_string(),
+            kind: OptionKind::String {
+                default: default.to_string(),
+            },
+        }
+    }
+
+    pub fn default_value(&self) -> OptionValue {
+        match &self.kind {
+            OptionKind::Int { default, .. } => OptionValue::Int(*default),
+            OptionKind::Bool { default } => OptionValue::Bool(*default),
+            OptionKind::String { default } => OptionValue::String(default.clone()),
+        }
+    }
+
+    /// Check a value against this option's type and range
+    /// Strings are parsed for int and bool options so values can come from
+    /// command lines and HTML forms unchanged
+    pub fn check(&self, value: &OptionValue) -> Result<OptionValue, OptionError> {
+        let invalid = |reason: String| OptionError::InvalidValue {
+            name: self.name.clone(),
+            reason,
+        };
+
+        match (&self.kind, value) {
+            (OptionKind::Int { min, max, .. }, OptionValue::Int(v)) => {
+                if v < min || v > max {
+                    Err(invalid(format!("{} is outside {}..={}", v, min, max)))
+                } else {
+                    Ok(OptionValue::Int(*v))
+                }
+            }
+            (OptionKind::Int { .. }, OptionValue::String(s)) => {
+                let v = s
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| invalid(format!("'{}' is not an integer", s)))?;
+                self.check(&OptionValue::Int(v))
+            }
+            (OptionKind::Bool { .. }, OptionValue::Bool(v)) => Ok(OptionValue::Bool(*v)),
+            (OptionKind::Bool { .. }, OptionValue::String(s)) => {
+                match s.trim().to_lowercase().as_str() {
+                    "true" | "on" | "1" => Ok(OptionValue::Bool(true)),
+                    "false" | "off" | "0" => Ok(OptionValue::Bool(false)),
+                    _ => Err(invalid(format!("'{}' is not a boolean", s))),
+                }
+            }
+            (OptionKind::String { .. }, OptionValue::String(s)) => {
+                Ok(OptionValue::String(s.clone()))
+            }
+            (OptionKind::String { .. }, other) => Ok(OptionValue::String(other.to_string())),
+            (_, other) => Err(invalid(format!("unexpected value {}", other))),
+        }
+    }
+}
+
+impl OptionValue {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            OptionValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            OptionValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OptionValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionValue::Bool(v) => write!(f, "{}", v),
+            OptionValue::Int(v) => write!(f, "{}", v),
+            OptionValue::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// What a bot does on its turn
+#[repr(C, u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotAction {
+    /// Play a move
+    Move(Move),
+    /// Give up the game
+    Resign,
+    /// Play a move and offer a draw to the opponent
+    OfferDraw(Move),
+    /// Accept the draw the opponent offered with its last move
+    AcceptDraw,
+    /// The bot cannot go on, e.g. its process exited; scored as a crash
+    Forfeit,
+}
+
+impl BotAction {
+    /// The move played by this action, if any
+    pub fn played_move(&self) -> Option<Move> {
+        match self {
+            BotAction::Move(mv) | BotAction::OfferDraw(mv) => Some(*mv),
+            BotAction::Resign | BotAction::AcceptDraw | BotAction::Forfeit => None,
+        }
+    }
+}
+
+impl From<Option<Move>> for BotAction {
+    /// `None` from `get_move` means the bot gives up
+    fn from(mv: Option<Move>) -> Self {
+        match mv {
+            Some(mv) => BotAction::Move(mv),
+            None => BotAction::Resign,
+        }
+    }
+}
+
+/// Progress report from a thinking bot
+/// Every field is optional so bots can report whatever their search knows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchInfo {
+    pub depth: Option<u32>,
+    /// Evaluation from the point of view of the side to move
+    pub score: Option<i32>,
+    /// Principal variation, starting with the move the bot intends to play
+    pub pv: Vec<Move>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    /// Free-form text, e.g. debug output
+    pub text: Option<String>,
+}
+
+/// Channel a bot uses to report `SearchInfo` while it is thinking
+#[derive(Clone)]
+pub struct SearchInfoSender {
+    callback: Arc<dyn Fn(SearchInfo) + Send + Sync>,
+}
+
+impl SearchInfoSender {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(SearchInfo) + Send + Sync + 'static,
+    {
+        SearchInfoSender {
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub fn send(&self, info: SearchInfo) {
+        (self.callback)(info);
+    }
+}
+
+impl std::fmt::Debug for SearchInfoSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SearchInfoSender")
+    }
+}
+
+impl std::fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(depth) = self.depth {
+            parts.push(format!("depth {}", depth));
+        }
+        if let Some(score) = self.score {
+            parts.push(format!("score {}", score));
+        }
+        if let Some(nodes) = self.nodes {
+            parts.push(format!("nodes {}", nodes));
+        }
+        if let Some(nps) = self.nps {
+            parts.push(format!("nps {}", nps));
+        }
+        if !self.pv.is_empty() {
+            let pv: Vec<String> = self.pv.iter().map(|mv| mv.to_string()).collect();
+            parts.push(format!("pv {}", pv.join(", ")));
+        }
+        if let Some(text) = &self.text {
+            parts.push(text.clone());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Option through which a bot is told how many threads it may use
+pub const THREADS_OPTION: &str = "threads";
+
+/// Limit a bot to `threads` threads if it has an int `threads` option,
+/// clamped to the option's range; bots without one are left alone
+pub fn limit_threads(bot: &mut dyn Bot, threads: usize) -> Result<(), OptionError> {
+    let declared = bot.options();
+    let Some(OptionKind::Int { min, max, .. }) = declared
+        .iter()
+        .find(|o| o.name == THREADS_OPTION)
+        .map(|o| &o.kind)
+    else {
+        return Ok(());
+    };
+    let threads = i64::try_from(threads).unwrap_or(i64::MAX).clamp(*min, *max);
+    bot.set_option(THREADS_OPTION, &OptionValue::Int(threads))
+}
+
+/// Validate a set of option values against the bot's declared options and
+/// apply them in name order
+pub fn apply_options(
+    bot: &mut dyn Bot,
+    values: &HashMap<String, OptionValue>,
+) -> Result<(), OptionError> {
+    let declared = bot.options();
+    let mut names: Vec<&String> = values.keys().collect();
+    names.sort();
+
+    for name in names {
+        let option = declared
+            .iter()
+            .find(|o| &o.name == name)
+            .ok_or_else(|| OptionError::Unknown(name.clone()))?;
+        let value = option.check(&values[name])?;
+        bot.set_option(name, &value)?;
+    }
+
+    Ok(())
+}
 
 /// Trait that all bots must implement
 pub trait Bot: Send {
     /// Get the name of the bot
     fn name(&self) -> &str;
 
+    /// Version of the bot, rated separately from other versions of the same name
+    fn version(&self) -> &str {
+        ""
+    }
+
+    /// Options this bot can be configured with
+    fn options(&self) -> Vec<BotOption> {
+        Vec::new()
+    }
+
+    /// Set an option before the game starts
+    /// Values passed through `apply_options` have already been checked
+    /// against `options()`
+    fn set_option(&mut self, name: &str, _value: &OptionValue) -> Result<(), OptionError> {
+        Err(OptionError::Unknown(name.to_string()))
+    }
+
     /// Get the next move for the current game state
     /// The bot has a time limit to respond
+    /// Returning `None` resigns the game
     fn get_move(&mut self, state: &GameState, time_limit: Duration) -> Option<Move>;
 
+    /// Decide what to do on this turn: move, resign, offer or accept a draw
+    /// `time` describes both clocks; override this for real time management
+    /// The default plays the move from `get_move` with `time.move_budget()`
+    fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+        self.get_move(state, time.move_budget()).into()
+    }
+
+    /// Notified when the opponent offers a draw
+    /// Answer with `BotAction::AcceptDraw` on the next turn, any other action declines
+    fn notify_draw_offer(&mut self) {}
+
+    /// Receive a channel for reporting search progress
+    /// Bots that don't report anything can ignore it
+    fn set_search_info_sender(&mut self, _sender: SearchInfoSender) {}
+
+    /// Start thinking on the opponent's time, right after this bot's own move
+    /// `state` is the position with the opponent to move. Return the reply the
+    /// bot expects, or `None` to not ponder. The bot must do the thinking on
+    /// its own thread and return from this call immediately
+    fn ponder_start(&mut self, _state: &GameState) -> Option<Move> {
+        None
+    }
+
+    /// The opponent played the expected reply: stop pondering and keep the results
+    /// Called before `notify_move` for that reply
+    fn ponder_hit(&mut self) {}
+
+    /// The opponent played something else or the game ended: stop pondering
+    /// and discard the results. Called before `notify_move` for that reply
+    fn ponder_miss(&mut self) {}
+
     /// Notified when the game starts
     fn game_start(&mut self, _player: Player) {}
 
@@ -51,11 +389,12 @@ impl Bot for RandomBot {
 /// A simple greedy bot that tries to capture pieces
 pub struct GreedyBot {
     name: String,
+    info: Option<SearchInfoSender>,
 }
 
 impl GreedyBot {
     pub fn new(name: String) -> Self {
-        GreedyBot { name }
+        GreedyBot { name, info: None }
     }
 
     fn evaluate_move(&self, state: &GameState, mv: Move) -> i32 {
@@ -109,8 +448,137 @@ impl Bot for GreedyBot {
         }
 
         // Find the move with the best evaluation
-        moves
+        let nodes = moves.len() as u64;
+        let (best, score) = moves
             .into_iter()
-            .max_by_key(|&mv| self.evaluate_move(state, mv))
+            .map(|mv| (mv, self.evaluate_move(state, mv)))
+            .max_by_key(|&(_, score)| score)?;
+
+        if let Some(info) = &self.info {
+            info.send(SearchInfo {
+                depth: Some(1),
+                score: Some(score),
+                pv: vec![best],
+                nodes: Some(nodes),
+                ..Default::default()
+            });
+        }
+
+        Some(best)
+    }
+
+    fn set_search_info_sender(&mut self, sender: SearchInfoSender) {
+        self.info = Some(sender);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TunableBot {
+        depth: i64,
+        threads: i64,
+        name: String,
+    }
+
+    impl Bot for TunableBot {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn options(&self) -> Vec<BotOption> {
+            vec![
+                BotOption::int("depth", 3, 1, 8),
+                BotOption::int(THREADS_OPTION, 2, 1, 4),
+                BotOption::string("name", "Tunable"),
+            ]
+        }
+
+        fn set_option(&mut self, name: &str, value: &OptionValue) -> Result<(), OptionError> {
+            match (name, value) {
+                ("depth", OptionValue::Int(v)) => self.depth = *v,
+                (THREADS_OPTION, OptionValue::Int(v)) => self.threads = *v,
+                ("name", OptionValue::String(v)) => self.name = v.clone(),
+                _ => return Err(OptionError::Unknown(name.to_string())),
+            }
+            Ok(())
+        }
+
+        fn get_move(&mut self, _state: &GameState, _time_limit: Duration) -> Option<Move> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_option_check_parses_strings() {
+        let depth = BotOption::int("depth", 3, 1, 8);
+        assert_eq!(
+            depth.check(&OptionValue::String("5".to_string())),
+            Ok(OptionValue::Int(5))
+        );
+        assert!(depth.check(&OptionValue::Int(9)).is_err());
+        assert!(depth.check(&OptionValue::Bool(true)).is_err());
+
+        let flag = BotOption::bool("flag", false);
+        assert_eq!(
+            flag.check(&OptionValue::String("on".to_string())),
+            Ok(OptionValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_option_json_round_trip() {
+        let option = BotOption::int("depth", 3, 1, 8);
+        let json = serde_json::to_string(&option).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"depth","type":"int","default":3,"min":1,"max":8}"#
+        );
+        assert_eq!(serde_json::from_str::<BotOption>(&json).unwrap(), option);
+
+        let values: HashMap<String, OptionValue> =
+            serde_json::from_str(r#"{"a": 4, "b": true, "c": "x"}"#).unwrap();
+        assert_eq!(values["a"], OptionValue::Int(4));
+        assert_eq!(values["b"], OptionValue::Bool(true));
+        assert_eq!(values["c"], OptionValue::String("x".to_string()));
+    }
+
+    #[test]
+    fn test_apply_options() {
+        let mut bot = TunableBot {
+            depth: 3,
+            threads: 2,
+            name: "Tunable".to_string(),
+        };
+
+        let mut values = HashMap::new();
+        values.insert("depth".to_string(), OptionValue::String("6".to_string()));
+        values.insert("name".to_string(), OptionValue::String("Deep".to_string()));
+        apply_options(&mut bot, &values).unwrap();
+        assert_eq!(bot.depth, 6);
+        assert_eq!(bot.name(), "Deep");
+
+        values.insert("width".to_string(), OptionValue::Int(2));
+        assert_eq!(
+            apply_options(&mut bot, &values),
+            Err(OptionError::Unknown("width".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_limit_threads_clamps_to_the_declared_range() {
+        let mut bot = TunableBot {
+            depth: 3,
+            threads: 2,
+            name: "Tunable".to_string(),
+        };
+        limit_threads(&mut bot, 3).unwrap();
+        assert_eq!(bot.threads, 3);
+        limit_threads(&mut bot, 16).unwrap();
+        assert_eq!(bot.threads, 4);
+
+        // Bots without the option are left alone
+        limit_threads(&mut GreedyBot::new("Greedy".to_string()), 3).unwrap();
     }
 }
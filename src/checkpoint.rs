@@ -0,0 +1,358 @@
+use crate::game::{Move, Variant};
+use crate::rating::BotId;
+use crate::scheduler::GameOutcome;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const SCHEDULE_FILE: &str = "schedule.jsonl";
+const GAMES_FILE: &str = "games.jsonl";
+/// Present once the tournament's games were added to a ratings file
+const RATED_FILE: &str = "rated";
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0} already holds a tournament; resume it or remove it first")]
+    Exists(PathBuf),
+    #[error("{0} holds no tournament to resume")]
+    Missing(PathBuf),
+}
+
+/// A game in the order it was scheduled, with bots given by name and
+/// version so that games of a bot that changed are played again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub attacker: BotId,
+    pub defender: BotId,
+    pub variant: Variant,
+    pub opening: Vec<Move>,
+}
+
+#[derive(Serialize)]
+struct FinishedGame<'a> {
+    index: usize,
+    outcome: &'a GameOutcome,
+}
+
+#[derive(Deserialize)]
+struct RecordedGame {
+    index: usize,
+    outcome: GameOutcome,
+}
+
+/// Progress of a tournament on disk, so an interrupted run can be resumed
+///
+/// Every game is appended to `schedule.jsonl` when it is scheduled and to
+/// `games.jsonl` when it finishes. Tournaments are deterministic given the
+/// results so far, so running the same tournament again schedules the same
+/// games; those already finished are taken from the files instead of being
+/// played again
+pub struct Checkpoint {
+    dir: PathBuf,
+    schedule: Vec<ScheduleEntry>,
+    finished: HashMap<usize, GameOutcome>,
+    /// Index the next scheduled game gets
+    next: usize,
+    schedule_file: File,
+    games_file: File,
+}
+
+impl Checkpoint {
+    /// Start recording a new tournament in `dir`
+    /// Fails if `dir` already holds one, to not overwrite its results
+    pub fn create<P: AsRef<Path>>(dir: P) -> Result<Self, CheckpointError> {
+        let dir = dir.as_ref().to_path_buf();
+        if dir.join(SCHEDULE_FILE).exists() {
+            return Err(CheckpointError::Exists(dir));
+        }
+        fs::create_dir_all(&dir)?;
+        match fs::remove_file(dir.join(RATED_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        Self::open(dir, Vec::new(), HashMap::new())
+    }
+
+    /// Continue the tournament recorded in `dir`
+    pub fn resume<P: AsRef<Path>>(dir: P) -> Result<Self, CheckpointError> {
+        let dir = dir.as_ref().to_path_buf();
+        if !dir.join(SCHEDULE_FILE).exists() {
+            return Err(CheckpointError::Missing(dir));
+        }
+        let schedule = read_lines::<ScheduleEntry>(&dir.join(SCHEDULE_FILE))?;
+        let finished = read_lines::<RecordedGame>(&dir.join(GAMES_FILE))?
+            .into_iter()
+            .filter(|game| game.index < schedule.len())
+            .map(|game| (game.index, game.outcome))
+            .collect();
+        Self::open(dir, schedule, finished)
+    }
+
+    fn open(
+        dir: PathBuf,
+        schedule: Vec<ScheduleEntry>,
+        finished: HashMap<usize, GameOutcome>,
+    ) -> Result<Self, CheckpointError> {
+        let (schedule_file, games_file) = write_files(&dir, &schedule, &finished)?;
+        Ok(Checkpoint {
+            schedule_file,
+            games_file,
+            dir,
+            schedule,
+            finished,
+            next: 0,
+        })
+    }
+
+    /// Rewrite both files from memory
+    fn rewrite(&mut self) -> io::Result<()> {
+        (self.schedule_file, self.games_file) =
+            write_files(&self.dir, &self.schedule, &self.finished)?;
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the tournament recorded in `dir` was already rated, so that
+    /// resuming it does not count its games twice
+    pub fn is_rated<P: AsRef<Path>>(dir: P) -> bool {
+        dir.as_ref().join(RATED_FILE).exists()
+    }
+
+    /// Note that the tournament recorded in `dir` was rated
+    pub fn set_rated<P: AsRef<Path>>(dir: P) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+        File::create(dir.as_ref().join(RATED_FILE))?.sync_all()
+    }
+
+    /// Finished games recorded so far, including ones from earlier runs
+    pub fn finished(&self) -> usize {
+        self.finished.len()
+    }
+
+    /// Schedule the next game and return its index, with its outcome if it
+    /// was already played
+    /// If the game differs from the recorded schedule the tournament was set
+    /// up differently; everything recorded from there on is discarded
+    pub fn schedule(
+        &mut self,
+        entry: ScheduleEntry,
+    ) -> Result<(usize, Option<GameOutcome>), CheckpointError> {
+        let index = self.next;
+        self.next += 1;
+
+        if let Some(recorded) = self.schedule.get(index) {
+            if *recorded == entry {
+                return Ok((index, self.finished.get(&index).cloned()));
+            }
+            eprintln!(
+                "Game {} differs from the checkpoint in {}, replaying from there",
+                index + 1,
+                self.dir.display()
+            );
+            self.schedule.truncate(index);
+            self.finished.retain(|&i, _| i < index);
+            self.rewrite()?;
+        }
+
+        write_line(&mut self.schedule_file, &entry)?;
+        self.schedule.push(entry);
+        Ok((index, None))
+    }
+
+    /// Record the outcome of the game scheduled as `index`
+    pub fn record(&mut self, index: usize, outcome: &GameOutcome) -> Result<(), CheckpointError> {
+        write_line(&mut self.games_file, &FinishedGame { index, outcome })?;
+        Ok(())
+    }
+}
+
+/// Write both files, dropping any line cut off by a crash, and open them
+/// for appending
+fn write_files(
+    dir: &Path,
+    schedule: &[ScheduleEntry],
+    finished: &HashMap<usize, GameOutcome>,
+) -> io::Result<(File, File)> {
+    let mut indices: Vec<usize> = finished.keys().copied().collect();
+    indices.sort();
+    let games = indices.into_iter().map(|index| FinishedGame {
+        index,
+        outcome: &finished[&index],
+    });
+    Ok((
+        replace_lines(&dir.join(SCHEDULE_FILE), schedule)?,
+        replace_lines(&dir.join(GAMES_FILE), games)?,
+    ))
+}
+
+/// Replace the file at `path` with one line per value
+/// Written next to it and renamed, so an interruption leaves the old file
+fn replace_lines<T: Serialize>(
+    path: &Path,
+    values: impl IntoIterator<Item = T>,
+) -> io::Result<File> {
+    let temp = path.with_extension("jsonl.tmp");
+    let mut file = File::create(&temp)?;
+    for value in values {
+        let line = serde_json::to_string(&value).map_err(io::Error::other)?;
+        writeln!(file, "{}", line)?;
+    }
+    file.sync_data()?;
+    fs::rename(&temp, path)?;
+    OpenOptions::new().append(true).open(path)
+}
+
+fn write_line<T: Serialize>(file: &mut File, value: &T) -> io::Result<()> {
+    let line = serde_json::to_string(value).map_err(io::Error::other)?;
+    writeln!(file, "{}", line)?;
+    file.sync_data()
+}
+
+/// Parse one value per line, stopping at the first line that does not parse
+/// (the one being written when the run was interrupted)
+fn read_lines<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .map_while(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::MatchConfig;
+    use crate::bot::{Bot, GreedyBot, RandomBot};
+    use crate::game::GameState;
+    use crate::scheduler::SchedulerConfig;
+    use crate::tournament::{Tournament, TournamentFormat};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Greedy bot that reports a version
+    struct VersionedBot(GreedyBot, &'static str);
+
+    impl Bot for VersionedBot {
+        fn name(&self) -> &str {
+            self.0.name()
+        }
+
+        fn version(&self) -> &str {
+            self.1
+        }
+
+        fn get_move(&mut self, state: &GameState, time_limit: Duration) -> Option<Move> {
+            self.0.get_move(state, time_limit)
+        }
+    }
+
+    /// Swiss tournament that counts the bots it creates, `Bot0` at `version`
+    fn tournament(created: &Arc<AtomicUsize>, version: &'static str) -> Tournament {
+        let config = MatchConfig {
+            max_moves: 40,
+            ..Default::default()
+        };
+        let mut tournament = Tournament::with_scheduler(
+            config,
+            SchedulerConfig {
+                workers: 2,
+                ..Default::default()
+            },
+        );
+        for i in 0..4 {
+            let name = format!("Bot{}", i);
+            let created = created.clone();
+            tournament.add_bot(
+                name.clone(),
+                Box::new(move || {
+                    created.fetch_add(1, Ordering::SeqCst);
+                    Ok(match i {
+                        0 => Box::new(VersionedBot(GreedyBot::new(name.clone()), version))
+                            as Box<dyn Bot>,
+                        2 => Box::new(GreedyBot::new(name.clone())),
+                        _ => Box::new(RandomBot::new(name.clone())),
+                    })
+                }),
+            );
+        }
+        tournament
+    }
+
+    #[test]
+    fn test_resume_skips_finished_games() {
+        let dir = std::env::temp_dir().join(format!("checkpoint-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let format = TournamentFormat::Swiss { rounds: 3 };
+
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut first = tournament(&created, "1");
+        first.set_checkpoint(Checkpoint::create(&dir).unwrap());
+        let expected = first.run(format);
+        let games = expected.games().len();
+        // Plus one bot each to learn its version
+        assert_eq!(created.load(Ordering::SeqCst), 2 * games + 4);
+        assert!(Checkpoint::create(&dir).is_err());
+
+        // Interrupted after five games, in the middle of writing the sixth
+        let path = dir.join(GAMES_FILE);
+        let text = fs::read_to_string(&path).unwrap();
+        let mut kept: Vec<&str> = text.lines().take(5).collect();
+        kept.push("{\"index\":5,\"outc");
+        fs::write(&path, kept.join("\n")).unwrap();
+
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut resumed = tournament(&created, "1");
+        let checkpoint = Checkpoint::resume(&dir).unwrap();
+        assert_eq!(checkpoint.finished(), 5);
+        resumed.set_checkpoint(checkpoint);
+        let results = resumed.run(format);
+
+        assert_eq!(created.load(Ordering::SeqCst), 2 * (games - 5) + 4);
+        assert_eq!(results.games(), expected.games());
+        assert_eq!(
+            results.standings().entries(),
+            expected.standings().entries()
+        );
+        assert_eq!(Checkpoint::resume(&dir).unwrap().finished(), games);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_changed_bots_play_again() {
+        let dir = std::env::temp_dir().join(format!("checkpoint-version-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let format = TournamentFormat::Swiss { rounds: 2 };
+
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut first = tournament(&created, "1");
+        first.set_checkpoint(Checkpoint::create(&dir).unwrap());
+        first.run(format);
+
+        let mut resumed = tournament(&created, "2");
+        resumed.set_checkpoint(Checkpoint::resume(&dir).unwrap());
+        let results = resumed.run(format);
+        let bot0 = results
+            .games()
+            .iter()
+            .flat_map(|game| [&game.attacker, &game.defender])
+            .filter(|bot| bot.name == "Bot0")
+            .collect::<Vec<_>>();
+        assert!(!bot0.is_empty());
+        assert!(bot0.iter().all(|bot| bot.version == "2"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Time control for one side of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    /// Main thinking time for the game, or for each period when
+    /// `moves_per_period` is set
+    pub initial: Duration,
+    /// Fischer increment added after every move
+    pub increment: Duration,
+    /// Add `initial` again after this many moves (e.g. 40 moves in 90 minutes)
+    /// `Some(0)` counts as no periods
+    pub moves_per_period: Option<u32>,
+    /// Once the main time is used up, every move may take up to this long
+    pub byoyomi: Duration,
+}
+
+impl TimeControl {
+    /// Fixed total time for the whole game
+    pub fn sudden_death(initial: Duration) -> Self {
+        TimeControl {
+            initial,
+            increment: Duration::ZERO,
+            moves_per_period: None,
+            byoyomi: Duration::ZERO,
+        }
+    }
+
+    /// Total time plus an increment after every move
+    pub fn fischer(initial: Duration, increment: Duration) -> Self {
+        TimeControl {
+            increment,
+            ..Self::sudden_death(initial)
+        }
+    }
+
+    /// Main time followed by a fixed time for every move
+    pub fn byoyomi(initial: Duration, byoyomi: Duration) -> Self {
+        TimeControl {
+            byoyomi,
+            ..Self::sudden_death(initial)
+        }
+    }
+
+    /// `initial` for every `moves` moves
+    pub fn periods(initial: Duration, moves: u32) -> Self {
+        TimeControl {
+            moves_per_period: Some(moves),
+            ..Self::sudden_death(initial)
+        }
+    }
+}
+
+/// Chess clock for one side
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Duration,
+    moves: u32,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            remaining: control.initial,
+            moves: 0,
+        }
+    }
+
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Main time left, not counting byoyomi
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    /// Longest the side can think on its next move without losing on time
+    pub fn available(&self) -> Duration {
+        self.remaining + self.control.byoyomi
+    }
+
+    /// Moves left until the next period starts
+    pub fn moves_to_go(&self) -> Option<u32> {
+        self.periods().map(|n| n - self.moves % n)
+    }
+
+    fn periods(&self) -> Option<u32> {
+        self.control.moves_per_period.filter(|&n| n > 0)
+    }
+
+    /// Charge a move that took `elapsed`
+    /// Returns false when the flag fell; the clock is left unchanged then
+    pub fn charge(&mut self, elapsed: Duration) -> bool {
+        if elapsed > self.available() {
+            return false;
+        }
+
+        // Time beyond the main time was spent in byoyomi, which resets every move
+        self.remaining = self.remaining.saturating_sub(elapsed);
+        self.remaining += self.control.increment;
+        self.moves += 1;
+
+        if let Some(n) = self.periods()
+            && self.moves.is_multiple_of(n)
+        {
+            self.remaining += self.control.initial;
+        }
+
+        true
+    }
+}
+
+/// Time situation handed to a bot on its turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeInfo {
+    /// Exceeding this on the current move loses on time
+    pub move_limit: Duration,
+    /// Main time left on the bot's clock
+    pub remaining: Duration,
+    pub increment: Duration,
+    pub byoyomi: Duration,
+    /// Moves until the next time period, if the control has periods
+    pub moves_to_go: Option<u32>,
+    /// Main time left on the opponent's clock
+    pub opponent_remaining: Duration,
+}
+
+impl TimeInfo {
+    /// Fixed time per move without a game clock
+    /// Behaves like a period of one move: the whole limit is for this move
+    pub fn per_move(limit: Duration) -> Self {
+        TimeInfo {
+            move_limit: limit,
+            remaining: limit,
+            increment: Duration::ZERO,
+            byoyomi: Duration::ZERO,
+            moves_to_go: Some(1),
+            opponent_remaining: limit,
+        }
+    }
+
+    pub fn from_clocks(own: &Clock, opponent: &Clock) -> Self {
+        TimeInfo {
+            move_limit: own.available(),
+            remaining: own.remaining(),
+            increment: own.control().increment,
+            byoyomi: own.control().byoyomi,
+            moves_to_go: own.moves_to_go(),
+            opponent_remaining: opponent.remaining(),
+        }
+    }
+
+    /// A simple time budget for this move
+    /// Spreads the main time over the moves to go (30 if unknown) and adds the
+    /// increment, never exceeding the hard limit. This is what bots that only
+    /// implement `Bot::get_move` receive as their time limit
+    pub fn move_budget(&self) -> Duration {
+        let moves = self.moves_to_go.unwrap_or(30).max(1);
+        let budget = self.remaining / moves + self.increment + self.byoyomi;
+        budget.min(self.move_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_fischer_clock() {
+        let mut clock = Clock::new(TimeControl::fischer(secs(10), secs(2)));
+        assert!(clock.charge(secs(3)));
+        assert_eq!(clock.remaining(), secs(9));
+        assert!(!clock.charge(secs(10)));
+        assert_eq!(clock.remaining(), secs(9));
+    }
+
+    #[test]
+    fn test_byoyomi_clock() {
+        let mut clock = Clock::new(TimeControl::byoyomi(secs(5), secs(3)));
+        assert_eq!(clock.available(), secs(8));
+        assert!(clock.charge(secs(7)));
+        assert_eq!(clock.remaining(), Duration::ZERO);
+        // Every further move gets the full byoyomi again
+        assert!(clock.charge(secs(3)));
+        assert!(clock.charge(secs(3)));
+        assert!(!clock.charge(secs(4)));
+    }
+
+    #[test]
+    fn test_period_clock() {
+        let mut clock = Clock::new(TimeControl::periods(secs(10), 2));
+        assert_eq!(clock.moves_to_go(), Some(2));
+        assert!(clock.charge(secs(4)));
+        assert_eq!(clock.moves_to_go(), Some(1));
+        assert!(clock.charge(secs(4)));
+        assert_eq!(clock.remaining(), secs(12));
+        assert_eq!(clock.moves_to_go(), Some(2));
+    }
+
+    #[test]
+    fn test_zero_moves_per_period_means_no_periods() {
+        let mut clock = Clock::new(TimeControl::periods(secs(10), 0));
+        assert_eq!(clock.moves_to_go(), None);
+        assert!(clock.charge(secs(4)));
+        assert_eq!(clock.remaining(), secs(6));
+        let info = TimeInfo::from_clocks(&clock, &clock);
+        assert_eq!(info.moves_to_go, None);
+    }
+
+    #[test]
+    fn test_move_budget() {
+        assert_eq!(TimeInfo::per_move(secs(5)).move_budget(), secs(5));
+
+        let own = Clock::new(TimeControl::fischer(secs(60), secs(1)));
+        let opponent = Clock::new(TimeControl::sudden_death(secs(60)));
+        let info = TimeInfo::from_clocks(&own, &opponent);
+        assert_eq!(info.move_budget(), secs(3));
+        assert_eq!(info.opponent_remaining, secs(60));
+    }
+}
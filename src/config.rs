@@ -0,0 +1,619 @@
+use crate::arena::{BotFactory, MatchConfig};
+use crate::bot::{
+    Bot, GreedyBot, OptionValue, RandomBot, THREADS_OPTION, apply_options, limit_threads,
+};
+use crate::checkpoint::Checkpoint;
+use crate::clock::TimeControl;
+use crate::game::Variant;
+use crate::opening::{OpeningError, OpeningSuite};
+use crate::plugin::PluginBot;
+use crate::process::ProcessBot;
+use crate::rating::RatingList;
+use crate::registry::{BotRegistry, DEFAULT_BOT_DIR};
+use crate::scheduler::SchedulerConfig;
+use crate::tournament::{Tournament, TournamentFormat, TournamentResults};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Openings: {0}")]
+    Opening(#[from] OpeningError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Where a participant's bot comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotSource {
+    /// `random` or `greedy`
+    Builtin(String),
+    /// Shared library built with `export_bot!`
+    Plugin(PathBuf),
+    /// Program speaking the `ProcessBot` protocol
+    Command(String),
+}
+
+impl BotSource {
+    /// Read a bot given on the command line: a built-in name, a path to a
+    /// shared library, or anything else as a command to run
+    pub fn parse(spec: &str) -> Self {
+        let is_library = [".so", ".dll", ".dylib"]
+            .iter()
+            .any(|suffix| spec.ends_with(suffix));
+        if is_builtin(spec) {
+            BotSource::Builtin(spec.to_string())
+        } else if is_library {
+            BotSource::Plugin(PathBuf::from(spec))
+        } else {
+            BotSource::Command(spec.to_string())
+        }
+    }
+
+    /// Create one bot called `name`
+    pub fn create(&self, name: &str) -> Result<Box<dyn Bot>, String> {
+        match self {
+            BotSource::Builtin(builtin) => match builtin.to_lowercase().as_str() {
+                "random" | "randombot" => Ok(Box::new(RandomBot::new(name.to_string()))),
+                "greedy" | "greedybot" => Ok(Box::new(GreedyBot::new(name.to_string()))),
+                other => Err(format!("Unknown built-in bot {}", other)),
+            },
+            BotSource::Plugin(path) => Ok(Box::new(PluginBot::load(path)?)),
+            BotSource::Command(command) => {
+                Ok(Box::new(ProcessBot::spawn(name.to_string(), command)?))
+            }
+        }
+    }
+
+    /// Factory for `name` with `options` applied to every bot it creates
+    /// Bots with a `threads` option that `options` leaves unset are limited
+    /// to `threads`. One bot is created right away so mistakes show up before
+    /// any game; if creating one fails later, that game is forfeited
+    pub fn factory(
+        &self,
+        name: &str,
+        options: &HashMap<String, OptionValue>,
+        threads: Option<usize>,
+    ) -> Result<BotFactory, String> {
+        let source = self.clone();
+        let bot_name = name.to_string();
+        let options = options.clone();
+        let threads = threads.filter(|_| !options.contains_key(THREADS_OPTION));
+        let factory: BotFactory = Box::new(move || {
+            let mut bot = source.create(&bot_name)?;
+            apply_options(bot.as_mut(), &options).map_err(|e| e.to_string())?;
+            if let Some(threads) = threads {
+                limit_threads(bot.as_mut(), threads).map_err(|e| e.to_string())?;
+            }
+            Ok(bot)
+        });
+        factory().map_err(|e| format!("{}: {}", name, e))?;
+        Ok(factory)
+    }
+}
+
+fn is_builtin(name: &str) -> bool {
+    matches!(
+        name.to_lowercase().as_str(),
+        "random" | "randombot" | "greedy" | "greedybot"
+    )
+}
+
+/// One bot of a tournament; exactly one of `builtin`, `plugin`, `command`
+/// and `registered` must be given
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParticipantConfig {
+    pub name: String,
+    pub builtin: Option<String>,
+    pub plugin: Option<PathBuf>,
+    pub command: Option<String>,
+    /// ID of a bot uploaded through the web interface, see `BotRegistry`
+    pub registered: Option<String>,
+    /// Values for the bot's options, see `Bot::options`
+    #[serde(default)]
+    pub options: HashMap<String, OptionValue>,
+}
+
+impl ParticipantConfig {
+    /// Where the bot comes from; `registered` bots are looked up in `registry`
+    pub fn source(&self, registry: &BotRegistry) -> Result<BotSource, ConfigError> {
+        match (&self.builtin, &self.plugin, &self.command, &self.registered) {
+            (Some(builtin), None, None, None) => Ok(BotSource::Builtin(builtin.clone())),
+            (None, Some(plugin), None, None) => Ok(BotSource::Plugin(plugin.clone())),
+            (None, None, Some(command), None) => Ok(BotSource::Command(command.clone())),
+            (None, None, None, Some(id)) => registry
+                .source(id)
+                .map_err(|e| ConfigError::Invalid(format!("{}: {}", self.name, e))),
+            _ => Err(ConfigError::Invalid(format!(
+                "{}: give exactly one of builtin, plugin, command and registered",
+                self.name
+            ))),
+        }
+    }
+
+    /// Factory for this participant, its bots limited to `threads` unless
+    /// its options set them; asking for more than `threads` is an error
+    pub fn factory(
+        &self,
+        registry: &BotRegistry,
+        threads: usize,
+    ) -> Result<BotFactory, ConfigError> {
+        if let Some(value) = self.options.get(THREADS_OPTION) {
+            let asked = match value {
+                OptionValue::Int(n) => usize::try_from(*n).ok(),
+                OptionValue::String(s) => s.parse().ok(),
+                OptionValue::Bool(_) => None,
+            };
+            if asked.is_some_and(|asked| asked > threads) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} asks for {} threads but cpus_per_game is {}",
+                    self.name, value, threads
+                )));
+            }
+        }
+        self.source(registry)?
+            .factory(&self.name, &self.options, Some(threads))
+            .map_err(ConfigError::Invalid)
+    }
+}
+
+/// Time limits in milliseconds
+/// Without `initial_ms` every move gets `per_move_ms`; with it each side has
+/// a game clock and the other fields work as in `TimeControl`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeControlConfig {
+    pub per_move_ms: Option<u64>,
+    pub initial_ms: Option<u64>,
+    #[serde(default)]
+    pub increment_ms: u64,
+    #[serde(default)]
+    pub byoyomi_ms: u64,
+    pub moves_per_period: Option<u32>,
+}
+
+/// Longest time a time control field may give; more is surely a typo
+const MAX_TIME_MS: u64 = 24 * 60 * 60 * 1000;
+
+impl TimeControlConfig {
+    /// Reject settings no game can be played with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::Invalid(reason.to_string()));
+        let times = [
+            ("per_move_ms", self.per_move_ms),
+            ("initial_ms", self.initial_ms),
+            ("increment_ms", Some(self.increment_ms)),
+            ("byoyomi_ms", Some(self.byoyomi_ms)),
+        ];
+        for (name, ms) in times {
+            if ms.is_some_and(|ms| ms > MAX_TIME_MS) {
+                return Err(ConfigError::Invalid(format!("{} is more than a day", name)));
+            }
+        }
+        if self.per_move_ms == Some(0) {
+            return invalid("per_move_ms must be more than 0");
+        }
+        if self.moves_per_period == Some(0) {
+            return invalid("moves_per_period must be at least 1");
+        }
+        if self.initial_ms == Some(0) && self.byoyomi_ms == 0 {
+            return invalid("A game clock needs initial_ms or byoyomi_ms");
+        }
+        Ok(())
+    }
+
+    /// Set the time limits of `config`, leaving what is not given
+    pub fn apply(&self, config: &mut MatchConfig) {
+        if let Some(per_move) = self.per_move_ms {
+            config.time_per_move = Duration::from_millis(per_move);
+        }
+        if let Some(time_control) = self.time_control() {
+            config.time_control = Some(time_control);
+        }
+    }
+
+    /// The game clock, if `initial_ms` asks for one
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.initial_ms.map(|initial| TimeControl {
+            initial: Duration::from_millis(initial),
+            increment: Duration::from_millis(self.increment_ms),
+            moves_per_period: self.moves_per_period,
+            byoyomi: Duration::from_millis(self.byoyomi_ms),
+        })
+    }
+}
+
+/// A tournament described in a TOML or JSON file
+///
+/// ```toml
+/// name = "Spring Cup"
+/// variant = "brandubh"
+/// format = "swiss"
+/// rounds = 5
+/// output_dir = "results"
+///
+/// [time_control]
+/// initial_ms = 60000
+/// increment_ms = 500
+///
+/// [[participants]]
+/// name = "Greedy"
+/// builtin = "greedy"
+///
+/// [[participants]]
+/// name = "Mcts"
+/// plugin = "plugins/mcts_bot_plugin/target/release/libmcts_bot_plugin.so"
+/// options = { iterations = 5000 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TournamentConfig {
+    /// Title of the reports
+    pub name: Option<String>,
+    #[serde(default = "default_variant")]
+    pub variant: String,
+    /// `round-robin`, `swiss`, `single-elimination` or `double-elimination`
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Rounds of a Swiss tournament
+    pub rounds: Option<usize>,
+    #[serde(default = "default_games_per_pairing")]
+    pub games_per_pairing: usize,
+    #[serde(default = "default_max_moves")]
+    pub max_moves: usize,
+    #[serde(default)]
+    pub time_control: TimeControlConfig,
+    /// Opening suite file; its variant must match `variant`
+    pub openings: Option<PathBuf>,
+    /// Games played at the same time, 0 for one per `cpus_per_game` cores
+    #[serde(default)]
+    pub workers: usize,
+    /// Cores for each game, passed to bots through their `threads` option
+    #[serde(default = "default_cpus_per_game")]
+    pub cpus_per_game: usize,
+    /// Where results are written
+    pub output_dir: Option<PathBuf>,
+    /// Registry for `registered` participants, `bots` unless given
+    pub bot_dir: Option<PathBuf>,
+    /// Ratings kept across tournaments, updated with this one's games
+    pub ratings_file: Option<PathBuf>,
+    pub participants: Vec<ParticipantConfig>,
+}
+
+fn default_cpus_per_game() -> usize {
+    1
+}
+
+fn default_variant() -> String {
+    "brandubh".to_string()
+}
+
+fn default_format() -> String {
+    "round-robin".to_string()
+}
+
+fn default_games_per_pairing() -> usize {
+    2
+}
+
+fn default_max_moves() -> usize {
+    MatchConfig::default().max_moves
+}
+
+impl TournamentConfig {
+    /// Load a `.json` file as JSON and anything else as TOML
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&text)?),
+            _ => Ok(toml::from_str(&text)?),
+        }
+    }
+
+    pub fn variant(&self) -> Result<Variant, ConfigError> {
+        self.variant
+            .parse()
+            .map_err(|e: crate::game::GameError| ConfigError::Invalid(e.to_string()))
+    }
+
+    pub fn format(&self) -> Result<TournamentFormat, ConfigError> {
+        match self.format.to_lowercase().as_str() {
+            "round-robin" => Ok(TournamentFormat::RoundRobin),
+            "swiss" => match self.rounds {
+                Some(rounds) if rounds > 0 => Ok(TournamentFormat::Swiss { rounds }),
+                _ => Err(ConfigError::Invalid(
+                    "A Swiss tournament needs rounds".to_string(),
+                )),
+            },
+            "single-elimination" => Ok(TournamentFormat::SingleElimination),
+            "double-elimination" => Ok(TournamentFormat::DoubleElimination),
+            other => Err(ConfigError::Invalid(format!("Unknown format {}", other))),
+        }
+    }
+
+    pub fn match_config(&self) -> MatchConfig {
+        let mut config = MatchConfig {
+            max_moves: self.max_moves,
+            ..Default::default()
+        };
+        self.time_control.apply(&mut config);
+        config
+    }
+
+    /// Check the whole configuration and set up the tournament
+    /// Every participant is created once, so broken plugins or commands are
+    /// reported before the first game
+    pub fn build(&self, verbose: bool) -> Result<Tournament, ConfigError> {
+        let variant = self.variant()?;
+        self.format()?;
+        self.time_control.validate()?;
+        if self.participants.len() < 2 {
+            return Err(ConfigError::Invalid(
+                "A tournament needs at least two participants".to_string(),
+            ));
+        }
+        let mut names = HashSet::new();
+        for participant in &self.participants {
+            if !names.insert(participant.name.as_str()) {
+                return Err(ConfigError::Invalid(format!(
+                    "Participant {} appears twice",
+                    participant.name
+                )));
+            }
+        }
+
+        let mut tournament = Tournament::with_scheduler(
+            self.match_config(),
+            SchedulerConfig {
+                workers: self.workers,
+                cpus_per_game: self.cpus_per_game,
+                verbose,
+            },
+        );
+        tournament.set_variant(variant);
+        tournament.set_games_per_pairing(self.games_per_pairing);
+        if let Some(path) = &self.openings {
+            let suite = OpeningSuite::load(path)?;
+            if suite.variant != variant {
+                return Err(ConfigError::Invalid(format!(
+                    "Openings in {} are for {}, not {}",
+                    path.display(),
+                    suite.variant.name(),
+                    variant.name()
+                )));
+            }
+            tournament.set_openings(suite);
+        }
+        let registry = BotRegistry::new(
+            self.bot_dir
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_BOT_DIR)),
+        );
+        for participant in &self.participants {
+            tournament.add_bot(
+                participant.name.clone(),
+                participant.factory(&registry, self.cpus_per_game.max(1))?,
+            );
+        }
+        Ok(tournament)
+    }
+
+    /// Add the games of `results` to `ratings_file`, creating it if needed
+    /// A tournament in `output_dir` is only rated once, however often it is
+    /// resumed. Returns the ratings, or `None` without a ratings file
+    pub fn update_ratings(
+        &self,
+        results: &TournamentResults,
+    ) -> Result<Option<RatingList>, ConfigError> {
+        let Some(path) = &self.ratings_file else {
+            return Ok(None);
+        };
+        let mut ratings = RatingList::load(path)?;
+        if let Some(dir) = &self.output_dir
+            && Checkpoint::is_rated(dir)
+        {
+            return Ok(Some(ratings));
+        }
+        ratings.update(results.games());
+        ratings.save(path)?;
+        if let Some(dir) = &self.output_dir {
+            Checkpoint::set_rated(dir)?;
+        }
+        Ok(Some(ratings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        variant = "brandubh"
+        format = "swiss"
+        rounds = 3
+        max_moves = 60
+
+        [time_control]
+        initial_ms = 10000
+        increment_ms = 100
+
+        [[participants]]
+        name = "Greedy"
+        builtin = "greedy"
+
+        [[participants]]
+        name = "Random"
+        builtin = "random"
+    "#;
+
+    #[test]
+    fn test_toml_and_json_agree() {
+        let from_toml: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        let json = r#"{
+            "format": "swiss",
+            "rounds": 3,
+            "max_moves": 60,
+            "time_control": {"initial_ms": 10000, "increment_ms": 100},
+            "participants": [
+                {"name": "Greedy", "builtin": "greedy"},
+                {"name": "Random", "builtin": "random"}
+            ]
+        }"#;
+        let from_json: TournamentConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(from_toml, from_json);
+
+        assert_eq!(
+            from_toml.format().unwrap(),
+            TournamentFormat::Swiss { rounds: 3 }
+        );
+        let config = from_toml.match_config();
+        assert_eq!(config.max_moves, 60);
+        assert_eq!(
+            config.time_control,
+            Some(TimeControl::fischer(
+                Duration::from_secs(10),
+                Duration::from_millis(100)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.participants[1].plugin = Some(PathBuf::from("bot.so"));
+        assert!(config.build(false).is_err());
+
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.participants[1].builtin = None;
+        config.participants[1].registered = Some("nobody".to_string());
+        config.bot_dir = Some(std::env::temp_dir().join("no-such-bot-dir"));
+        assert!(config.build(false).is_err());
+
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.participants[1].name = "Greedy".to_string();
+        assert!(config.build(false).is_err());
+
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.cpus_per_game = 2;
+        config.participants[0]
+            .options
+            .insert("threads".to_string(), OptionValue::Int(4));
+        let error = config.build(false).err().unwrap().to_string();
+        assert_eq!(error, "Greedy asks for 4 threads but cpus_per_game is 2");
+
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.rounds = None;
+        assert!(config.format().is_err());
+
+        assert!(toml::from_str::<TournamentConfig>("participants = []\nround = 3").is_err());
+    }
+
+    #[test]
+    fn test_invalid_time_controls() {
+        let valid = TimeControlConfig {
+            initial_ms: Some(60000),
+            moves_per_period: Some(40),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = [
+            TimeControlConfig {
+                moves_per_period: Some(0),
+                ..valid.clone()
+            },
+            TimeControlConfig {
+                per_move_ms: Some(0),
+                ..Default::default()
+            },
+            TimeControlConfig {
+                increment_ms: 2 * MAX_TIME_MS,
+                ..valid.clone()
+            },
+            TimeControlConfig {
+                initial_ms: Some(0),
+                ..Default::default()
+            },
+        ];
+        for time_control in invalid {
+            assert!(time_control.validate().is_err(), "{:?}", time_control);
+        }
+
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.time_control.moves_per_period = Some(0);
+        assert!(config.build(false).is_err());
+    }
+
+    #[test]
+    fn test_ratings_file_is_updated() {
+        let path = std::env::temp_dir().join(format!("config-ratings-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut config: TournamentConfig = toml::from_str(CONFIG).unwrap();
+        config.format = "round-robin".to_string();
+        config.max_moves = 20;
+        config.ratings_file = Some(path.clone());
+
+        for tournaments in 1..=2 {
+            let results = config.build(false).unwrap().run(config.format().unwrap());
+            let ratings = config.update_ratings(&results).unwrap().unwrap();
+            let saved = RatingList::load(&path).unwrap();
+            let board = saved.leaderboard();
+            assert_eq!(board.len(), 2);
+            assert_eq!(board[0].id, ratings.leaderboard()[0].id);
+            assert!(board.iter().all(|r| r.tournaments == tournaments));
+            assert!(board.iter().all(|r| r.games() == 2 * tournaments));
+        }
+
+        // Resuming a tournament does not rate its games again
+        let dir = std::env::temp_dir().join(format!("config-rated-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        config.output_dir = Some(dir.clone());
+        let results = config.build(false).unwrap().run(config.format().unwrap());
+        for _ in 0..2 {
+            let ratings = config.update_ratings(&results).unwrap().unwrap();
+            assert!(ratings.leaderboard().iter().all(|r| r.tournaments == 3));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bot_source() {
+        assert_eq!(
+            BotSource::parse("Greedy"),
+            BotSource::Builtin("Greedy".to_string())
+        );
+        assert_eq!(
+            BotSource::parse("bots/libmine.so"),
+            BotSource::Plugin(PathBuf::from("bots/libmine.so"))
+        );
+        assert_eq!(
+            BotSource::parse("python3 bot.py"),
+            BotSource::Command("python3 bot.py".to_string())
+        );
+
+        let options = HashMap::from([("depth".to_string(), OptionValue::Int(2))]);
+        assert!(
+            BotSource::parse("greedy")
+                .factory("G", &options, None)
+                .is_err()
+        );
+        let factory = BotSource::parse("greedy")
+            .factory("G", &HashMap::new(), None)
+            .unwrap();
+        assert_eq!(factory().unwrap().name(), "G");
+    }
+}
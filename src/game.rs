@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use thiserror::Error;
 
 /// Board size constants
@@ -32,6 +33,19 @@ impl Variant {
     }
 }
 
+/// Parses `brandubh` or `copenhagen`, in any case
+impl FromStr for Variant {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "brandubh" => Ok(Variant::Brandubh),
+            "copenhagen" => Ok(Variant::Copenhagen),
+            _ => Err(GameError::InvalidNotation(format!("unknown variant {}", s))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Piece {
     Attacker,
@@ -74,6 +88,24 @@ impl fmt::Display for Position {
     }
 }
 
+/// Parses the `Display` form, e.g. `(3, 0)`
+impl FromStr for Position {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GameError::InvalidNotation(s.to_string());
+        let (row, col) = s
+            .trim()
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .ok_or_else(invalid)?;
+        let row = row.trim().parse().map_err(|_| invalid())?;
+        let col = col.trim().parse().map_err(|_| invalid())?;
+        Ok(Position::new(row, col))
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Move {
@@ -93,6 +125,18 @@ impl fmt::Display for Move {
     }
 }
 
+/// Parses the `Display` form, e.g. `(3, 0) -> (3, 2)`
+impl FromStr for Move {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once("->")
+            .ok_or_else(|| GameError::InvalidNotation(s.to_string()))?;
+        Ok(Move::new(from.parse()?, to.parse()?))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum GameError {
     #[error("Invalid move: {0}")]
@@ -101,6 +145,10 @@ pub enum GameError {
     GameOver,
     #[error("Not your turn")]
     NotYourTurn,
+    #[error("Invalid notation: {0}")]
+    InvalidNotation(String),
+    #[error("Invalid position: {0}")]
+    InvalidPosition(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -148,6 +196,57 @@ impl GameState {
         state
     }
 
+    /// Start a game from any position, e.g. an endgame to practise
+    /// The position needs exactly one king, nothing on the corners, nobody
+    /// but the king on the throne and a legal move for `to_move`
+    pub fn from_position(
+        variant: Variant,
+        pieces: &[(Position, Piece)],
+        to_move: Player,
+    ) -> Result<Self, GameError> {
+        let invalid = |reason: String| Err(GameError::InvalidPosition(reason));
+        let mut state = GameState {
+            board: [[None; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+            variant,
+            board_size: variant.board_size(),
+            current_player: to_move,
+            king_position: None,
+            move_count: 0,
+            result: None,
+            position_history: HashMap::new(),
+        };
+
+        for &(pos, piece) in pieces {
+            if pos.row >= state.board_size || pos.col >= state.board_size {
+                return invalid(format!("{} is off the board", pos));
+            }
+            if state.board[pos.row][pos.col].is_some() {
+                return invalid(format!("two pieces on {}", pos));
+            }
+            if state.is_corner(pos) {
+                return invalid(format!("piece on corner {}", pos));
+            }
+            if piece == Piece::King {
+                if state.king_position.is_some() {
+                    return invalid("more than one king".to_string());
+                }
+                state.king_position = Some(pos);
+            } else if state.is_throne(pos) {
+                return invalid("only the king may stand on the throne".to_string());
+            }
+            state.board[pos.row][pos.col] = Some(piece);
+        }
+
+        if state.king_position.is_none() {
+            return invalid("no king".to_string());
+        }
+        if state.legal_moves(to_move).is_empty() {
+            return invalid(format!("{:?} have no legal moves", to_move));
+        }
+        state.record_position();
+        Ok(state)
+    }
+
     /// Create a new game with Copenhagen Hnefatafl (default)
     pub fn new_copenhagen() -> Self {
         Self::new(Variant::Copenhagen)
@@ -282,6 +381,19 @@ impl GameState {
         self.result.is_some()
     }
 
+    /// Square of the king, `None` once it has been captured
+    pub fn king_position(&self) -> Option<Position> {
+        self.king_position
+    }
+
+    /// How often the current position has occurred, counting this time
+    pub fn repetitions(&self) -> usize {
+        self.position_history
+            .get(&self.hash_position())
+            .copied()
+            .unwrap_or(0)
+    }
+
     pub fn move_count(&self) -> usize {
         self.move_count
     }
@@ -295,7 +407,7 @@ impl GameState {
     }
 
     /// Check if a position is a corner (throne)
-    fn is_corner(&self, pos: Position) -> bool {
+    pub fn is_corner(&self, pos: Position) -> bool {
         (pos.row == self.board_size - 1 || pos.row == 0)
             && (pos.col == self.board_size - 1 || pos.col == 0)
     }
@@ -317,10 +429,10 @@ impl GameState {
         for row in 0..self.board_size {
             for col in 0..self.board_size {
                 let pos = Position::new(row, col);
-                if let Some(piece) = self.get_piece(pos) {
-                    if self.piece_belongs_to_player(piece, player) {
-                        moves.extend(self.legal_moves_for_piece(pos));
-                    }
+                if let Some(piece) = self.get_piece(pos)
+                    && self.piece_belongs_to_player(piece, player)
+                {
+                    moves.extend(self.legal_moves_for_piece(pos));
                 }
             }
         }
@@ -380,6 +492,11 @@ impl GameState {
 
     /// Make a move and update the game state
     pub fn make_move(&mut self, mv: Move) -> Result<(), GameError> {
+        self.make_move_with_captures(mv).map(|_| ())
+    }
+
+    /// Make a move and return the squares of the pieces it captured
+    pub fn make_move_with_captures(&mut self, mv: Move) -> Result<Vec<Position>, GameError> {
         if self.is_game_over() {
             return Err(GameError::GameOver);
         }
@@ -400,7 +517,7 @@ impl GameState {
         }
 
         // Check for captures
-        self.check_captures(mv.to);
+        let captures = self.check_captures(mv.to);
 
         // Check win conditions
         self.check_game_end();
@@ -413,11 +530,12 @@ impl GameState {
         self.record_position();
         self.check_threefold_repetition();
 
-        Ok(())
+        Ok(captures)
     }
 
-    fn check_captures(&mut self, moved_to: Position) {
+    fn check_captures(&mut self, moved_to: Position) -> Vec<Position> {
         let directions = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        let mut captures = Vec::new();
 
         for &(dr, dc) in &directions {
             let target_r = moved_to.row as i32 + dr;
@@ -437,6 +555,7 @@ impl GameState {
                 // Check if we can capture this piece
                 if self.can_capture(moved_to, target) {
                     self.board[target.row][target.col] = None;
+                    captures.push(target);
 
                     // If king was captured, update king position
                     if target_piece == Piece::King {
@@ -445,6 +564,7 @@ impl GameState {
                 }
             }
         }
+        captures
     }
 
     fn can_capture(&self, attacker: Position, target: Position) -> bool {
@@ -574,10 +694,10 @@ impl GameState {
             let attacker_pos = Position::new(attacker_r as usize, attacker_c as usize);
 
             // If there's an attacker in this direction, check if it would capture
-            if let Some(Piece::Attacker) = self.get_piece(attacker_pos) {
-                if self.is_king_captured_from(king_pos, attacker_pos) {
-                    return true;
-                }
+            if let Some(Piece::Attacker) = self.get_piece(attacker_pos)
+                && self.is_king_captured_from(king_pos, attacker_pos)
+            {
+                return true;
             }
         }
 
@@ -695,11 +815,11 @@ impl GameState {
         }
 
         let hash = self.hash_position();
-        if let Some(&count) = self.position_history.get(&hash) {
-            if count >= 3 {
-                // Threefold repetition - defender loses
-                self.result = Some(GameResult::AttackersWin);
-            }
+        if let Some(&count) = self.position_history.get(&hash)
+            && count >= 3
+        {
+            // Threefold repetition - defender loses
+            self.result = Some(GameResult::AttackersWin);
         }
     }
 
@@ -760,6 +880,22 @@ impl GameState {
 
         result
     }
+
+    /// Count the positions reached after `depth` plies
+    /// Finished games are leaves, so this checks move generation and game end
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 || self.is_game_over() {
+            return 1;
+        }
+        self.legal_moves(self.current_player)
+            .into_iter()
+            .map(|mv| {
+                let mut next = self.clone();
+                next.make_move(mv).expect("legal move");
+                next.perft(depth - 1)
+            })
+            .sum()
+    }
 }
 
 impl Default for GameState {
@@ -924,8 +1060,10 @@ mod tests {
         game.current_player = Player::Defenders;
 
         // Make move to capture attacker between corner and defender
-        game.make_move(Move::new(Position::new(0, 3), Position::new(0, 2)))
+        let captures = game
+            .make_move_with_captures(Move::new(Position::new(0, 3), Position::new(0, 2)))
             .unwrap();
+        assert_eq!(captures, vec![Position::new(0, 1)]);
 
         // Check that attacker is captured
         assert_eq!(game.get_piece(Position::new(0, 1)), None);
@@ -1750,4 +1888,54 @@ mod tests {
             "Attackers should win (defender loses) on threefold repetition"
         );
     }
+
+    #[test]
+    fn test_move_notation_round_trip() {
+        let mv = Move::new(Position::new(3, 0), Position::new(3, 2));
+        assert_eq!(mv.to_string().parse::<Move>().unwrap(), mv);
+        assert_eq!("(10,1)->( 4 ,1 )".parse::<Move>().unwrap().to.row, 4);
+        assert!("(3, 0) -> 3, 2".parse::<Move>().is_err());
+        assert!("(a, 0) -> (3, 2)".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn test_perft() {
+        let game = GameState::new_brandubh();
+        let moves = game.legal_moves(Player::Attackers).len() as u64;
+        assert_eq!(game.perft(0), 1);
+        assert_eq!(game.perft(1), moves);
+        assert!(game.perft(2) > moves);
+    }
+
+    #[test]
+    fn test_from_position() {
+        let king = (Position::new(1, 2), Piece::King);
+        let attacker = (Position::new(5, 5), Piece::Attacker);
+        let mut game =
+            GameState::from_position(Variant::Brandubh, &[king, attacker], Player::Defenders)
+                .unwrap();
+        assert_eq!(game.current_player(), Player::Defenders);
+        assert_eq!(game.king_position(), Some(Position::new(1, 2)));
+        game.make_move(Move::new(Position::new(1, 2), Position::new(1, 0)))
+            .unwrap();
+        game.make_move(Move::new(Position::new(5, 5), Position::new(5, 4)))
+            .unwrap();
+        game.make_move(Move::new(Position::new(1, 0), Position::new(0, 0)))
+            .unwrap();
+        assert_eq!(game.result(), Some(&GameResult::DefendersWin));
+
+        let invalid = [
+            vec![attacker],
+            vec![king, (Position::new(0, 0), Piece::Attacker)],
+            vec![king, (Position::new(3, 3), Piece::Defender)],
+            vec![king, (Position::new(1, 2), Piece::Attacker)],
+            vec![king, (Position::new(7, 1), Piece::Attacker)],
+        ];
+        for pieces in invalid {
+            assert!(matches!(
+                GameState::from_position(Variant::Brandubh, &pieces, Player::Defenders),
+                Err(GameError::InvalidPosition(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,425 @@
+use crate::arena::{BotFactory, Match, MatchConfig, MatchResult, create_bots};
+use crate::bot::{GreedyBot, RandomBot};
+use crate::game::Variant;
+use crate::opening::OpeningSuite;
+use crate::plugin::PluginBot;
+use crate::rating::{BotId, RatedGame, elo_estimate};
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+
+/// Rough ratings of the reference bots, used as anchors for the performance
+/// rating. Override them with `Gauntlet::add_opponent` for calibrated values
+pub const RANDOM_BOT_RATING: f64 = 800.0;
+pub const GREEDY_BOT_RATING: f64 = 1200.0;
+pub const ALPHABETA_BOT_RATING: f64 = 1600.0;
+pub const MCTS_BOT_RATING: f64 = 1600.0;
+
+/// Wins, draws and losses from one bot's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+
+    /// Points per game, 0.5 if no games were played
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            n => self.points() / n as f64,
+        }
+    }
+
+    pub fn add(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn combined(&self, other: &Record) -> Record {
+        Record {
+            wins: self.wins + other.wins,
+            draws: self.draws + other.draws,
+            losses: self.losses + other.losses,
+        }
+    }
+}
+
+/// The candidate's results against one reference bot
+#[derive(Debug, Clone)]
+pub struct OpponentRecord {
+    pub name: String,
+    pub rating: f64,
+    /// Games where the candidate played the attackers
+    pub as_attacker: Record,
+    /// Games where the candidate played the defenders
+    pub as_defender: Record,
+}
+
+impl OpponentRecord {
+    pub fn total(&self) -> Record {
+        self.as_attacker.combined(&self.as_defender)
+    }
+}
+
+struct Opponent {
+    name: String,
+    factory: BotFactory,
+    rating: f64,
+}
+
+/// Plays one bot against a fixed pool of reference opponents
+/// Unlike a round-robin the opponents never play each other
+pub struct Gauntlet {
+    name: String,
+    candidate: BotFactory,
+    opponents: Vec<Opponent>,
+    config: MatchConfig,
+    variant: Variant,
+    games_per_side: usize,
+    openings: Option<OpeningSuite>,
+    verbose: bool,
+}
+
+impl Gauntlet {
+    pub fn new(name: String, candidate: BotFactory, config: MatchConfig, verbose: bool) -> Self {
+        Gauntlet {
+            name,
+            candidate,
+            opponents: Vec::new(),
+            config,
+            variant: Variant::Brandubh,
+            games_per_side: 1,
+            openings: None,
+            verbose,
+        }
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Games against every opponent as attacker, and as many as defender
+    pub fn set_games_per_side(&mut self, games: usize) {
+        self.games_per_side = games;
+    }
+
+    /// Start games from the suite's openings, each played with both colors
+    /// The suite also sets the variant
+    pub fn set_openings(&mut self, suite: OpeningSuite) {
+        self.variant = suite.variant;
+        self.openings = Some(suite);
+    }
+
+    pub fn add_opponent(&mut self, name: String, factory: BotFactory, rating: f64) {
+        self.opponents.push(Opponent {
+            name,
+            factory,
+            rating,
+        });
+    }
+
+    /// Add `RandomBot`, `GreedyBot` and the alpha-beta and MCTS plugins
+    /// Plugins are looked up in `plugins_dir` (the repository's `plugins/`);
+    /// the ones that are missing or fail to load are skipped and their
+    /// errors returned
+    pub fn add_reference_pool<P: AsRef<Path>>(&mut self, plugins_dir: P) -> Vec<String> {
+        self.add_opponent(
+            "RandomBot".to_string(),
+            Box::new(|| Ok(Box::new(RandomBot::new("RandomBot".to_string())))),
+            RANDOM_BOT_RATING,
+        );
+        self.add_opponent(
+            "GreedyBot".to_string(),
+            Box::new(|| Ok(Box::new(GreedyBot::new("GreedyBot".to_string())))),
+            GREEDY_BOT_RATING,
+        );
+
+        let plugins = [
+            ("AlphaBetaBot", "alphabeta_bot_plugin", ALPHABETA_BOT_RATING),
+            ("MctsBot", "mcts_bot_plugin", MCTS_BOT_RATING),
+        ];
+        let mut errors = Vec::new();
+        for (name, crate_name, rating) in plugins {
+            let Some(path) = find_plugin(plugins_dir.as_ref(), crate_name) else {
+                errors.push(format!("{}: plugin {} is not built", name, crate_name));
+                continue;
+            };
+            if let Err(e) = PluginBot::load(&path) {
+                errors.push(format!("{}: {}", name, e));
+                continue;
+            }
+            self.add_opponent(
+                name.to_string(),
+                // The library may be rebuilt or removed during the run; its
+                // games are then forfeited
+                Box::new(move || Ok(Box::new(PluginBot::load(&path)?))),
+                rating,
+            );
+        }
+        errors
+    }
+
+    pub fn run(&mut self) -> GauntletResults {
+        let mut results = GauntletResults {
+            candidate: self.name.clone(),
+            opponents: Vec::new(),
+            games: Vec::new(),
+        };
+
+        for opponent in &self.opponents {
+            let mut record = OpponentRecord {
+                name: opponent.name.clone(),
+                rating: opponent.rating,
+                as_attacker: Record::default(),
+                as_defender: Record::default(),
+            };
+
+            for game in 0..2 * self.games_per_side {
+                let candidate_attacks = game % 2 == 0;
+                let opening = self
+                    .openings
+                    .as_ref()
+                    .map_or(&[][..], |suite| suite.get(game / 2));
+                let (attacker_name, defender_name, bots) = match candidate_attacks {
+                    true => (
+                        &self.name,
+                        &opponent.name,
+                        create_bots(&self.candidate, &opponent.factory),
+                    ),
+                    false => (
+                        &opponent.name,
+                        &self.name,
+                        create_bots(&opponent.factory, &self.candidate),
+                    ),
+                };
+
+                let (result, attacker_id, defender_id) = match bots {
+                    Ok((attacker, defender)) => {
+                        let attacker_id = BotId::new(attacker_name, attacker.version());
+                        let defender_id = BotId::new(defender_name, defender.version());
+                        let mut game_match = Match::with_variant(
+                            attacker,
+                            defender,
+                            self.config.clone(),
+                            false,
+                            self.variant,
+                        );
+                        game_match
+                            .set_opening(opening)
+                            .expect("openings are checked when the suite is loaded");
+                        (game_match.play(), attacker_id, defender_id)
+                    }
+                    Err((winning_side, error)) => {
+                        if self.verbose {
+                            println!("{} vs {}: {}", attacker_name, defender_name, error);
+                        }
+                        let result = MatchResult::not_started(
+                            attacker_name,
+                            defender_name,
+                            winning_side,
+                            opening,
+                        );
+                        (
+                            result,
+                            BotId::new(attacker_name, ""),
+                            BotId::new(defender_name, ""),
+                        )
+                    }
+                };
+                let attacker_score = result.attacker_score();
+                results
+                    .games
+                    .push(RatedGame::new(attacker_id, defender_id, attacker_score));
+
+                if candidate_attacks {
+                    record.as_attacker.add(attacker_score);
+                } else {
+                    record.as_defender.add(1.0 - attacker_score);
+                }
+
+                if self.verbose {
+                    let role = if candidate_attacks {
+                        "attacker"
+                    } else {
+                        "defender"
+                    };
+                    println!("{} ({}) vs {}: {}", self.name, role, opponent.name, result);
+                }
+            }
+
+            results.opponents.push(record);
+        }
+
+        results
+    }
+}
+
+/// Shared library of a plugin crate in `plugins_dir`, release build preferred
+fn find_plugin(plugins_dir: &Path, crate_name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}{}", DLL_PREFIX, crate_name, DLL_SUFFIX);
+    ["release", "debug"]
+        .iter()
+        .map(|profile| {
+            plugins_dir
+                .join(crate_name)
+                .join("target")
+                .join(profile)
+                .join(&file)
+        })
+        .find(|path| path.exists())
+}
+
+pub struct GauntletResults {
+    pub candidate: String,
+    pub opponents: Vec<OpponentRecord>,
+    /// Every game, ready for `RatingList::update`
+    pub games: Vec<RatedGame>,
+}
+
+impl GauntletResults {
+    pub fn total(&self) -> Record {
+        self.opponents
+            .iter()
+            .fold(Record::default(), |total, opponent| {
+                total.combined(&opponent.total())
+            })
+    }
+
+    /// Rating at which the candidate's score would be expected against this
+    /// pool, with the half-width of its 95% confidence interval
+    pub fn performance_rating(&self) -> (f64, f64) {
+        let total = self.total();
+        if total.games() == 0 {
+            return (0.0, f64::INFINITY);
+        }
+
+        let average_opponent = self
+            .opponents
+            .iter()
+            .map(|opponent| opponent.rating * opponent.total().games() as f64)
+            .sum::<f64>()
+            / total.games() as f64;
+        let (difference, margin) = elo_estimate(total.wins, total.draws, total.losses);
+        (average_opponent + difference, margin)
+    }
+
+    pub fn display(&self) {
+        let format = |r: &Record| format!("{}/{}/{}", r.wins, r.draws, r.losses);
+
+        println!("\nGauntlet Results for {}:", self.candidate);
+        println!(
+            "{:<16} {:>7} {:>12} {:>12} {:>12} {:>7}",
+            "Opponent", "Rating", "Attacker", "Defender", "Total", "Score"
+        );
+        for opponent in &self.opponents {
+            let total = opponent.total();
+            println!(
+                "{:<16} {:>7.0} {:>12} {:>12} {:>12} {:>6.1}%",
+                opponent.name,
+                opponent.rating,
+                format(&opponent.as_attacker),
+                format(&opponent.as_defender),
+                format(&total),
+                100.0 * total.score()
+            );
+        }
+
+        let (rating, margin) = self.performance_rating();
+        println!(
+            "\nTotal (W/D/L): {}  Performance rating: {:.0} ± {:.0}",
+            format(&self.total()),
+            rating,
+            margin
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauntlet_records_per_role() {
+        let config = MatchConfig {
+            max_moves: 60,
+            ..Default::default()
+        };
+        let mut gauntlet = Gauntlet::new(
+            "Candidate".to_string(),
+            Box::new(|| Ok(Box::new(GreedyBot::new("Candidate".to_string())))),
+            config,
+            false,
+        );
+        gauntlet.set_games_per_side(2);
+        let errors = gauntlet.add_reference_pool("does/not/exist");
+        assert_eq!(errors.len(), 2);
+
+        let results = gauntlet.run();
+        assert_eq!(results.opponents.len(), 2);
+        assert_eq!(results.games.len(), 8);
+        for opponent in &results.opponents {
+            assert_eq!(opponent.as_attacker.games(), 2);
+            assert_eq!(opponent.as_defender.games(), 2);
+        }
+        assert_eq!(results.total().games(), 8);
+        assert!(results.performance_rating().0.is_finite());
+    }
+
+    #[test]
+    fn test_opponent_that_fails_to_load_forfeits() {
+        let mut gauntlet = Gauntlet::new(
+            "Candidate".to_string(),
+            Box::new(|| Ok(Box::new(GreedyBot::new("Candidate".to_string())))),
+            MatchConfig::default(),
+            false,
+        );
+        gauntlet.add_opponent(
+            "Gone".to_string(),
+            Box::new(|| Ok(Box::new(PluginBot::load("does/not/exist.so")?))),
+            1000.0,
+        );
+
+        let results = gauntlet.run();
+        assert_eq!(results.opponents[0].total().wins, 2);
+        assert_eq!(results.games.len(), 2);
+    }
+
+    #[test]
+    fn test_performance_rating() {
+        let results = GauntletResults {
+            candidate: "Candidate".to_string(),
+            opponents: vec![OpponentRecord {
+                name: "Opponent".to_string(),
+                rating: 1500.0,
+                as_attacker: Record {
+                    wins: 5,
+                    draws: 0,
+                    losses: 5,
+                },
+                as_defender: Record {
+                    wins: 5,
+                    draws: 0,
+                    losses: 5,
+                },
+            }],
+            games: Vec::new(),
+        };
+        let (rating, margin) = results.performance_rating();
+        assert!((rating - 1500.0).abs() < 1e-9);
+        assert!(margin > 0.0);
+    }
+}
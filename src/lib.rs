@@ -1,10 +1,38 @@
 pub mod arena;
 pub mod bot;
+pub mod checkpoint;
+pub mod clock;
+pub mod config;
 pub mod game;
+pub mod gauntlet;
+pub mod observer;
+pub mod opening;
 pub mod plugin;
+pub mod process;
+pub mod rating;
+pub mod registry;
+pub mod report;
+pub mod scheduler;
+pub mod tournament;
 pub mod web;
 
 pub use arena::*;
 pub use bot::*;
+pub use checkpoint::*;
+pub use clock::*;
+pub use config::*;
 pub use game::*;
+pub use gauntlet::*;
+pub use observer::*;
+pub use opening::*;
 pub use plugin::*;
+pub use process::*;
+pub use rating::*;
+pub use registry::*;
+pub use report::*;
+pub use scheduler::*;
+pub use tournament::*;
+
+// Used by `export_bot!` so plugin crates don't need their own dependency
+#[doc(hidden)]
+pub use serde_json;
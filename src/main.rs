@@ -1,43 +1,349 @@
+use clap::{Parser, Subcommand};
 use hnefatafl_arena::*;
-use std::time::Duration;
-
-fn main() {
-    println!("Hnefatafl Arena - Bot Tournament System");
-    println!("========================================\n");
-    
-    // Create some example bots
-    let bot1 = Box::new(RandomBot::new("RandomBot1".to_string()));
-    let bot2 = Box::new(GreedyBot::new("GreedyBot1".to_string()));
-    
-    // Configure match
-    let config = MatchConfig {
-        time_per_move: Duration::from_secs(2),
-        max_moves: 150,
-    };
-    
-    // Play a match
-    let mut match_game = Match::new(bot1, bot2, config, true);
-    let result = match_game.play();
-    
-    // Display result
-    println!("\n========================================");
-    println!("Match Result:");
-    match result {
-        MatchResult::AttackersWin { winner_name, moves } => {
-            println!("  {} wins as Attackers in {} moves!", winner_name, moves);
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Hnefatafl Arena - Bot Tournament System
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play one game between two bots
+    ///
+    /// A bot is `random`, `greedy`, a plugin library (.so, .dll, .dylib), a
+    /// command that starts a process bot or `bot:<id>` for a bot registered
+    /// through the web interface
+    Play {
+        attacker: String,
+        defender: String,
+        #[arg(long, default_value = "brandubh")]
+        variant: Variant,
+        /// Time limit per move in milliseconds
+        #[arg(long, default_value_t = 2000)]
+        time_ms: u64,
+        #[arg(long, default_value_t = 150)]
+        max_moves: usize,
+        /// Write the game to this file for `replay`
+        #[arg(long)]
+        save: Option<PathBuf>,
+        /// Log every match event to this file as JSON lines
+        #[arg(long)]
+        events: Option<PathBuf>,
+        /// Only print the result
+        #[arg(long)]
+        quiet: bool,
+        /// Where `bot:<id>` bots are registered
+        #[arg(long, default_value = DEFAULT_BOT_DIR)]
+        bot_dir: PathBuf,
+    },
+    /// Run a tournament described by a TOML or JSON file
+    ///
+    /// Games are saved to the output directory as they finish
+    Tournament {
+        config: PathBuf,
+        /// Continue the tournament saved in the output directory
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Play a bot against the reference bots and estimate its rating
+    Gauntlet {
+        bot: String,
+        /// Name of the bot in the results
+        #[arg(long, default_value = "Candidate")]
+        name: String,
+        #[arg(long, default_value_t = 1)]
+        games_per_side: usize,
+        /// Directory with the reference plugin crates
+        #[arg(long, default_value = "plugins")]
+        plugins_dir: PathBuf,
+        #[arg(long, default_value = "brandubh")]
+        variant: Variant,
+        /// Opening suite; sets the variant
+        #[arg(long)]
+        openings: Option<PathBuf>,
+        #[arg(long, default_value_t = 1000)]
+        time_ms: u64,
+        #[arg(long, default_value_t = 200)]
+        max_moves: usize,
+        /// Where a `bot:<id>` candidate is registered
+        #[arg(long, default_value = DEFAULT_BOT_DIR)]
+        bot_dir: PathBuf,
+    },
+    /// Start the web interface
+    ///
+    /// Bots can be uploaded when an admin token is given, either with
+    /// `--admin-token` or in HNEFATAFL_ADMIN_TOKEN
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: String,
+        /// Where uploaded bots are kept
+        #[arg(long, default_value = DEFAULT_BOT_DIR)]
+        bot_dir: PathBuf,
+        /// Token that authorizes uploading and removing bots
+        #[arg(long)]
+        admin_token: Option<String>,
+        /// Bot matches watched in browsers that may run at the same time
+        #[arg(long, default_value_t = 8)]
+        max_matches: usize,
+    },
+    /// Show a game saved with `play --save` move by move
+    Replay {
+        file: PathBuf,
+        /// Pause between moves in milliseconds
+        #[arg(long, default_value_t = 0)]
+        delay_ms: u64,
+    },
+    /// Count positions after every depth up to `depth`
+    Perft {
+        depth: u32,
+        #[arg(long, default_value = "brandubh")]
+        variant: Variant,
+    },
+}
+
+/// A finished game as written by `play --save`
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    variant: Variant,
+    #[serde(flatten)]
+    result: MatchResult,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Play {
+            attacker,
+            defender,
+            variant,
+            time_ms,
+            max_moves,
+            save,
+            events,
+            quiet,
+            bot_dir,
+        } => {
+            let config = MatchConfig {
+                time_per_move: Duration::from_millis(time_ms),
+                max_moves,
+                ..Default::default()
+            };
+            play_bots(&attacker, &defender, &bot_dir).and_then(|(attacker, defender)| {
+                play(attacker, defender, variant, config, save, events, quiet)
+            })
         }
-        MatchResult::DefendersWin { winner_name, moves } => {
-            println!("  {} wins as Defenders in {} moves!", winner_name, moves);
+        Command::Tournament { config, resume } => tournament(&config, resume),
+        Command::Gauntlet {
+            bot,
+            name,
+            games_per_side,
+            plugins_dir,
+            variant,
+            openings,
+            time_ms,
+            max_moves,
+            bot_dir,
+        } => {
+            let config = MatchConfig {
+                time_per_move: Duration::from_millis(time_ms),
+                max_moves,
+                ..Default::default()
+            };
+            let candidate = bot_source(&bot, &bot_dir)
+                .and_then(|bot| bot.factory(&name, &HashMap::new(), None));
+            candidate.and_then(|candidate| {
+                let mut gauntlet = Gauntlet::new(name, candidate, config, true);
+                gauntlet.set_variant(variant);
+                gauntlet.set_games_per_side(games_per_side);
+                if let Some(path) = openings {
+                    gauntlet.set_openings(OpeningSuite::load(path).map_err(|e| e.to_string())?);
+                }
+                for error in gauntlet.add_reference_pool(plugins_dir) {
+                    eprintln!("Skipping {}", error);
+                }
+                gauntlet.run().display();
+                Ok(())
+            })
         }
-        MatchResult::Draw { moves } => {
-            println!("  Draw after {} moves", moves);
+        Command::Serve {
+            addr,
+            bot_dir,
+            admin_token,
+            max_matches,
+        } => {
+            let mut app_state = web::AppState::new();
+            app_state.set_bot_registry(BotRegistry::new(bot_dir));
+            app_state.set_max_matches(max_matches);
+            let admin_token = admin_token
+                .or_else(|| std::env::var("HNEFATAFL_ADMIN_TOKEN").ok())
+                .filter(|token| !token.is_empty());
+            if let Some(token) = admin_token {
+                app_state.set_admin_token(token);
+            }
+            tokio::runtime::Runtime::new()
+                .map_err(|e| e.to_string())
+                .and_then(|runtime| {
+                    runtime
+                        .block_on(web::serve_with_state(&addr, app_state))
+                        .map_err(|e| e.to_string())
+                })
         }
-        MatchResult::Timeout { violator, winner } => {
-            println!("  {} wins by timeout (opponent: {})", winner, violator);
+        Command::Replay { file, delay_ms } => replay(&file, Duration::from_millis(delay_ms)),
+        Command::Perft { depth, variant } => {
+            let state = GameState::new(variant);
+            for depth in 1..=depth {
+                let start = Instant::now();
+                let nodes = state.perft(depth);
+                println!(
+                    "perft({}) = {} ({:.2}s)",
+                    depth,
+                    nodes,
+                    start.elapsed().as_secs_f64()
+                );
+            }
+            Ok(())
         }
-        MatchResult::IllegalMove { violator, winner } => {
-            println!("  {} wins by illegal move (opponent: {})", winner, violator);
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
         }
     }
-    println!("========================================");
+}
+
+/// A bot given on the command line; `bot:<id>` is looked up among the bots
+/// registered in `bot_dir`
+fn bot_source(spec: &str, bot_dir: &Path) -> Result<BotSource, String> {
+    match spec.strip_prefix(REGISTERED_PREFIX) {
+        Some(id) => BotRegistry::new(bot_dir)
+            .source(id)
+            .map_err(|e| e.to_string()),
+        None => Ok(BotSource::parse(spec)),
+    }
+}
+
+/// Attacker and defender of a game
+type BotPair = (Box<dyn Bot>, Box<dyn Bot>);
+
+/// Start the two bots of `play`
+fn play_bots(attacker: &str, defender: &str, bot_dir: &Path) -> Result<BotPair, String> {
+    // Built-in and process bots are named after how they were given
+    let defender_name = match attacker == defender {
+        true => format!("{} 2", defender),
+        false => defender.to_string(),
+    };
+    let attacker_bot = bot_source(attacker, bot_dir)?.create(attacker)?;
+    let defender_bot = bot_source(defender, bot_dir)?.create(&defender_name)?;
+    Ok((attacker_bot, defender_bot))
+}
+
+fn play(
+    attacker_bot: Box<dyn Bot>,
+    defender_bot: Box<dyn Bot>,
+    variant: Variant,
+    config: MatchConfig,
+    save: Option<PathBuf>,
+    events: Option<PathBuf>,
+    quiet: bool,
+) -> Result<(), String> {
+    let mut game = Match::with_variant(attacker_bot, defender_bot, config, !quiet, variant);
+    if let Some(path) = events {
+        let log =
+            JsonLinesObserver::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        game.add_observer(Box::new(log));
+    }
+    let result = game.play();
+    if quiet {
+        println!("{}", result);
+    }
+
+    if let Some(path) = save {
+        let saved = SavedGame { variant, result };
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn tournament(path: &Path, resume: bool) -> Result<(), String> {
+    let config = TournamentConfig::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let format = config.format().map_err(|e| e.to_string())?;
+    let mut tournament = config.build(true).map_err(|e| e.to_string())?;
+    match (&config.output_dir, resume) {
+        (Some(dir), true) => {
+            let checkpoint = Checkpoint::resume(dir).map_err(|e| e.to_string())?;
+            println!("Resuming: {} games already played", checkpoint.finished());
+            tournament.set_checkpoint(checkpoint);
+        }
+        (Some(dir), false) => {
+            tournament.set_checkpoint(Checkpoint::create(dir).map_err(|e| e.to_string())?)
+        }
+        (None, true) => return Err("--resume needs output_dir in the config".to_string()),
+        (None, false) => {}
+    }
+    let results = tournament.run(format);
+    let report = Report::new(
+        config.name.as_deref().unwrap_or("Tournament Results"),
+        &results,
+    );
+    print!("{}", report.to_text());
+    if let Some(ratings) = config.update_ratings(&results).map_err(|e| e.to_string())? {
+        ratings.display();
+    }
+
+    if let Some(dir) = &config.output_dir {
+        let write = |name: &str, json: serde_json::Result<String>| {
+            let path = dir.join(name);
+            let json = json.map_err(|e| e.to_string())?;
+            fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+        };
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        write(
+            "standings.json",
+            serde_json::to_string_pretty(results.standings().entries()),
+        )?;
+        write("games.json", serde_json::to_string_pretty(results.games()))?;
+        report
+            .save(dir)
+            .map_err(|e| format!("{}: {}", dir.display(), e))?;
+        println!("\nResults written to {}", dir.display());
+    }
+    Ok(())
+}
+
+fn replay(path: &Path, delay: Duration) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let saved: SavedGame =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    println!(
+        "{} (attackers) vs {} (defenders)",
+        saved.result.attacker, saved.result.defender
+    );
+    let mut state = GameState::new(saved.variant);
+    println!("{}", state.display_board());
+    for (index, mv) in saved.result.moves().into_iter().enumerate() {
+        let player = state.current_player();
+        state
+            .make_move(mv)
+            .map_err(|e| format!("Move {}: {}", index + 1, e))?;
+        println!("{}. {:?}: {}", index + 1, player, mv);
+        println!("{}", state.display_board());
+        thread::sleep(delay);
+    }
+    println!("{}", saved.result);
+    Ok(())
 }
@@ -0,0 +1,357 @@
+use crate::arena::{MatchResult, MoveRecord};
+use crate::bot::SearchInfo;
+use crate::clock::TimeInfo;
+use crate::game::{GameState, Move, Player, Position, Variant};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+/// Something that happened in a match, in the order it happened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MatchEvent {
+    /// The bots start from the `variant`'s starting position after `opening`
+    GameStart {
+        attacker: String,
+        defender: String,
+        variant: Variant,
+        opening: Vec<Move>,
+    },
+    TurnStart {
+        player: Player,
+        time: TimeInfo,
+    },
+    SearchInfo {
+        player: Player,
+        info: SearchInfo,
+    },
+    Move {
+        record: MoveRecord,
+        captures: Vec<Position>,
+    },
+    DrawOffer {
+        player: Player,
+    },
+    Timeout {
+        player: Player,
+        elapsed: Duration,
+        limit: Duration,
+    },
+    IllegalMove {
+        player: Player,
+        reason: String,
+    },
+    GameEnd {
+        result: MatchResult,
+    },
+}
+
+/// Hooks into a `Match` as it is played
+///
+/// The callbacks forward to `event` by default, so an observer that treats
+/// all events alike only implements that. `search_info` is called from
+/// whatever thread the bot reports from
+pub trait MatchObserver: Send {
+    fn game_start(&mut self, attacker: &str, defender: &str, opening: &[Move], state: &GameState) {
+        self.event(MatchEvent::GameStart {
+            attacker: attacker.to_string(),
+            defender: defender.to_string(),
+            variant: state.variant(),
+            opening: opening.to_vec(),
+        });
+    }
+
+    /// `player` is about to think about the next move in `state`
+    fn turn_start(&mut self, player: Player, _state: &GameState, time: &TimeInfo) {
+        self.event(MatchEvent::TurnStart {
+            player,
+            time: *time,
+        });
+    }
+
+    /// Asked after `turn_start`, before the bot thinks; `true` ends the game
+    /// unfinished as `Termination::Aborted`
+    fn should_stop(&mut self) -> bool {
+        false
+    }
+
+    fn search_info(&mut self, player: Player, info: &SearchInfo) {
+        self.event(MatchEvent::SearchInfo {
+            player,
+            info: info.clone(),
+        });
+    }
+
+    /// A move was played; `state` is the position after it
+    fn move_played(&mut self, record: &MoveRecord, captures: &[Position], _state: &GameState) {
+        self.event(MatchEvent::Move {
+            record: record.clone(),
+            captures: captures.to_vec(),
+        });
+    }
+
+    fn draw_offered(&mut self, player: Player) {
+        self.event(MatchEvent::DrawOffer { player });
+    }
+
+    /// `player` thinks on the opponent's time, expecting `expected`
+    fn ponder_start(&mut self, _player: Player, _expected: Move) {}
+
+    fn timeout(&mut self, player: Player, elapsed: Duration, limit: Duration) {
+        self.event(MatchEvent::Timeout {
+            player,
+            elapsed,
+            limit,
+        });
+    }
+
+    fn illegal_move(&mut self, player: Player, reason: &str) {
+        self.event(MatchEvent::IllegalMove {
+            player,
+            reason: reason.to_string(),
+        });
+    }
+
+    fn game_end(&mut self, result: &MatchResult, _state: &GameState) {
+        self.event(MatchEvent::GameEnd {
+            result: result.clone(),
+        });
+    }
+
+    fn event(&mut self, _event: MatchEvent) {}
+}
+
+/// Prints the game as it goes, board after every move
+/// This is what `verbose` in `Match::new` turns on
+#[derive(Debug, Default)]
+pub struct ConsoleObserver {
+    attacker: String,
+    defender: String,
+}
+
+impl ConsoleObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn name(&self, player: Player) -> &str {
+        match player {
+            Player::Attackers => &self.attacker,
+            Player::Defenders => &self.defender,
+        }
+    }
+}
+
+impl MatchObserver for ConsoleObserver {
+    fn game_start(&mut self, attacker: &str, defender: &str, _opening: &[Move], state: &GameState) {
+        self.attacker = attacker.to_string();
+        self.defender = defender.to_string();
+        println!("Match starting:");
+        println!("  Attackers: {}", attacker);
+        println!("  Defenders: {}", defender);
+        println!("\nInitial board:");
+        println!("{}", state.display_board());
+    }
+
+    fn turn_start(&mut self, player: Player, state: &GameState, _time: &TimeInfo) {
+        println!(
+            "\nMove {}: {} to play",
+            state.move_count() + 1,
+            self.name(player)
+        );
+        println!("Legal moves: {}", state.legal_moves(player).len());
+    }
+
+    fn search_info(&mut self, player: Player, info: &SearchInfo) {
+        println!("  [{}] {}", self.name(player), info);
+    }
+
+    fn move_played(&mut self, record: &MoveRecord, _captures: &[Position], state: &GameState) {
+        let name = self.name(record.player);
+        match record.clock_remaining {
+            Some(remaining) => println!(
+                "{} plays: {} (took {:?}, {:?} left)",
+                name, record.mv, record.think_time, remaining
+            ),
+            None => println!(
+                "{} plays: {} (took {:?})",
+                name, record.mv, record.think_time
+            ),
+        }
+        println!("{}", state.display_board());
+    }
+
+    fn draw_offered(&mut self, player: Player) {
+        println!("{} offers a draw", self.name(player));
+    }
+
+    fn ponder_start(&mut self, player: Player, expected: Move) {
+        println!("{} ponders on {}", self.name(player), expected);
+    }
+
+    fn timeout(&mut self, player: Player, elapsed: Duration, limit: Duration) {
+        println!(
+            "TIMEOUT: {} took {:?} (limit: {:?})",
+            self.name(player),
+            elapsed,
+            limit
+        );
+    }
+
+    fn illegal_move(&mut self, player: Player, reason: &str) {
+        println!("ILLEGAL MOVE: {} - {}", self.name(player), reason);
+    }
+
+    fn game_end(&mut self, result: &MatchResult, _state: &GameState) {
+        println!("\n{}", result);
+    }
+}
+
+/// Writes every event as one line of JSON
+pub struct JsonLinesObserver<W: Write + Send> {
+    writer: W,
+}
+
+impl JsonLinesObserver<BufWriter<File>> {
+    /// Log to a new file at `path`, replacing any existing one
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send> JsonLinesObserver<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesObserver { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> MatchObserver for JsonLinesObserver<W> {
+    fn event(&mut self, event: MatchEvent) {
+        // A log that can't be written must not stop the game
+        let line = serde_json::to_string(&event).expect("events serialize");
+        let _ = writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush());
+    }
+}
+
+/// Sends every event down a channel, e.g. to stream a live game
+pub struct ChannelObserver {
+    sender: Sender<MatchEvent>,
+}
+
+impl ChannelObserver {
+    pub fn new(sender: Sender<MatchEvent>) -> Self {
+        ChannelObserver { sender }
+    }
+
+    /// An observer with the receiving end of its channel
+    pub fn channel() -> (Self, Receiver<MatchEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::new(sender), receiver)
+    }
+}
+
+impl MatchObserver for ChannelObserver {
+    fn event(&mut self, event: MatchEvent) {
+        // Nobody listening any more is fine; the game goes on
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{Match, MatchConfig, Termination};
+    use crate::bot::GreedyBot;
+
+    fn greedy_match() -> Match {
+        let config = MatchConfig {
+            max_moves: 10,
+            ..Default::default()
+        };
+        Match::new(
+            Box::new(GreedyBot::new("A".to_string())),
+            Box::new(GreedyBot::new("B".to_string())),
+            config,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_channel_observer_sees_whole_game() {
+        let mut game = greedy_match();
+        let (observer, events) = ChannelObserver::channel();
+        game.add_observer(Box::new(observer));
+        let result = game.play();
+
+        let events: Vec<MatchEvent> = events.try_iter().collect();
+        assert!(matches!(events.first(), Some(MatchEvent::GameStart { .. })));
+        let moves: Vec<&MoveRecord> = events
+            .iter()
+            .filter_map(|event| match event {
+                MatchEvent::Move { record, .. } => Some(record),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moves.len(), result.record.len());
+        let turns = events
+            .iter()
+            .filter(|event| matches!(event, MatchEvent::TurnStart { .. }))
+            .count();
+        assert_eq!(turns, moves.len());
+        match events.last() {
+            Some(MatchEvent::GameEnd { result: ended }) => assert_eq!(*ended, result),
+            other => panic!("expected game end, got {:?}", other),
+        }
+    }
+
+    /// Stops the match once `turns` turns have started
+    struct StopAfter {
+        turns: usize,
+    }
+
+    impl MatchObserver for StopAfter {
+        fn should_stop(&mut self) -> bool {
+            self.turns = self.turns.saturating_sub(1);
+            self.turns == 0
+        }
+    }
+
+    #[test]
+    fn test_observer_stops_match() {
+        let mut game = greedy_match();
+        game.add_observer(Box::new(StopAfter { turns: 4 }));
+        let result = game.play();
+
+        assert_eq!(result.termination, Termination::Aborted);
+        assert_eq!(result.winning_side, None);
+        assert_eq!(result.record.len(), 3);
+        assert_eq!(result.to_string(), "Stopped after 3 moves");
+    }
+
+    #[test]
+    fn test_json_lines_observer() {
+        let path = std::env::temp_dir().join(format!("events-test-{}.jsonl", std::process::id()));
+        let mut game = greedy_match();
+        game.add_observer(Box::new(JsonLinesObserver::create(&path).unwrap()));
+        let result = game.play();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let events: Vec<MatchEvent> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(text.starts_with("{\"type\":\"game_start\""));
+        match events.last() {
+            Some(MatchEvent::GameEnd { result: logged }) => assert_eq!(*logged, result),
+            other => panic!("expected game end, got {:?}", other),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
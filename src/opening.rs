@@ -0,0 +1,238 @@
+use crate::game::{GameError, GameState, Move, Position, Variant};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OpeningError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Line {line}: {message}")]
+    Invalid { line: usize, message: String },
+}
+
+/// Forced move sequences that games start from
+///
+/// The file format has one opening per line, moves separated by `;` in the
+/// notation of `Move`'s `Display` (`(3, 0) -> (3, 2)`). A `variant` line
+/// selects the board and `#` starts a comment:
+///
+/// ```text
+/// variant brandubh
+/// (0, 3) -> (0, 1); (2, 3) -> (2, 1)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningSuite {
+    pub variant: Variant,
+    pub openings: Vec<Vec<Move>>,
+}
+
+impl OpeningSuite {
+    pub fn new(variant: Variant) -> Self {
+        OpeningSuite {
+            variant,
+            openings: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, OpeningError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    /// Parse a suite and check that every opening is legal
+    pub fn parse(text: &str) -> Result<Self, OpeningError> {
+        let mut suite = OpeningSuite::new(Variant::Brandubh);
+        let mut lines = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |message: String| OpeningError::Invalid {
+                line: line_number,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(variant) = line.strip_prefix("variant") {
+                suite.variant = variant
+                    .parse()
+                    .map_err(|e: GameError| invalid(e.to_string()))?;
+                continue;
+            }
+
+            let moves = line
+                .split(';')
+                .map(|mv| mv.parse::<Move>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(e.to_string()))?;
+            suite.openings.push(moves);
+            lines.push(line_number);
+        }
+
+        for (opening, line) in suite.openings.iter().zip(lines) {
+            play(suite.variant, opening).map_err(|e| OpeningError::Invalid {
+                line,
+                message: e.to_string(),
+            })?;
+        }
+        Ok(suite)
+    }
+
+    pub fn to_text(&self) -> String {
+        let variant = match self.variant {
+            Variant::Brandubh => "brandubh",
+            Variant::Copenhagen => "copenhagen",
+        };
+        let mut text = format!("variant {}\n", variant);
+        for opening in &self.openings {
+            let moves: Vec<String> = opening.iter().map(Move::to_string).collect();
+            text.push_str(&moves.join("; "));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn len(&self) -> usize {
+        self.openings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.openings.is_empty()
+    }
+
+    /// Opening number `index`, wrapping around at the end of the suite
+    pub fn get(&self, index: usize) -> &[Move] {
+        match self.openings.is_empty() {
+            true => &[],
+            false => &self.openings[index % self.openings.len()],
+        }
+    }
+
+    /// Generate up to `count` distinct openings of `plies` random moves
+    /// An opening is kept only if it is balanced: nothing was captured and
+    /// the side to move can neither win nor capture right away
+    pub fn generate(variant: Variant, count: usize, plies: usize, seed: u64) -> Self {
+        let mut suite = OpeningSuite::new(variant);
+        if plies == 0 {
+            suite.openings.push(Vec::new());
+            return suite;
+        }
+        let mut rng = XorShift(seed.max(1));
+        let mut seen = HashSet::new();
+        let start = GameState::new(variant);
+        let pieces = piece_count(&start);
+
+        for _ in 0..count * 100 {
+            if suite.openings.len() == count {
+                break;
+            }
+
+            let mut state = start.clone();
+            let mut moves = Vec::new();
+            while moves.len() < plies && !state.is_game_over() {
+                let legal = state.legal_moves(state.current_player());
+                if legal.is_empty() {
+                    break;
+                }
+                let mv = legal[rng.below(legal.len())];
+                state.make_move(mv).expect("legal move");
+                moves.push(mv);
+            }
+
+            // Every opening has the same length, so the board alone identifies it
+            if moves.len() == plies
+                && is_balanced(&state, pieces)
+                && seen.insert(state.display_board())
+            {
+                suite.openings.push(moves);
+            }
+        }
+        suite
+    }
+}
+
+/// Position after playing `opening` from the start
+pub fn play(variant: Variant, opening: &[Move]) -> Result<GameState, GameError> {
+    let mut state = GameState::new(variant);
+    for &mv in opening {
+        state.make_move(mv)?;
+    }
+    Ok(state)
+}
+
+fn piece_count(state: &GameState) -> usize {
+    let size = state.board_size();
+    (0..size)
+        .flat_map(|row| (0..size).map(move |col| Position::new(row, col)))
+        .filter(|&pos| state.get_piece(pos).is_some())
+        .count()
+}
+
+fn is_balanced(state: &GameState, pieces: usize) -> bool {
+    if state.is_game_over() || piece_count(state) != pieces {
+        return false;
+    }
+    state
+        .legal_moves(state.current_player())
+        .into_iter()
+        .all(|mv| {
+            let mut next = state.clone();
+            next.make_move(mv).is_ok() && !next.is_game_over() && piece_count(&next) == pieces
+        })
+}
+
+/// xorshift64, enough to pick varied openings reproducibly
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_round_trip() {
+        let suite = OpeningSuite::generate(Variant::Brandubh, 20, 4, 7);
+        assert_eq!(suite.len(), 20);
+        assert!(suite.openings.iter().all(|opening| opening.len() == 4));
+        assert_eq!(OpeningSuite::parse(&suite.to_text()).unwrap(), suite);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let text = "# comment\nvariant brandubh\n(0, 3) -> (0, 0)\n";
+        match OpeningSuite::parse(text) {
+            Err(OpeningError::Invalid { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("moving onto a corner is illegal"),
+        }
+        assert!(OpeningSuite::parse("variant chess\n").is_err());
+        assert!(OpeningSuite::parse("(0, 3) -> \n").is_err());
+    }
+
+    #[test]
+    fn test_shipped_suites() {
+        for (file, variant) in [
+            ("openings/brandubh.txt", Variant::Brandubh),
+            ("openings/copenhagen.txt", Variant::Copenhagen),
+        ] {
+            let suite = OpeningSuite::load(file).unwrap();
+            assert_eq!(suite.variant, variant);
+            assert!(suite.len() >= 200, "{} has {} openings", file, suite.len());
+        }
+    }
+}
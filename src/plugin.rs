@@ -1,6 +1,11 @@
-use crate::bot::Bot;
-use crate::game::{GameState, Move, Player};
+use crate::bot::{
+    Bot, BotAction, BotOption, OptionError, OptionValue, SearchInfo, SearchInfoSender,
+};
+use crate::clock::TimeInfo;
+use crate::game::{GameState, Move, Player, Position};
 use libloading::{Library, Symbol};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
 use std::path::Path;
 use std::time::Duration;
 
@@ -22,6 +27,117 @@ pub struct BotVTable {
     pub notify_move: unsafe extern "C" fn(*mut (), Move),
     pub game_end: unsafe extern "C" fn(*mut ()),
     pub drop: unsafe extern "C" fn(*mut ()),
+    /// Options as a JSON array of `BotOption`, released with `free_string`
+    pub options: unsafe extern "C" fn(*mut ()) -> *mut c_char,
+    /// Set an option from its name and JSON value
+    /// Returns null on success or an error message released with `free_string`
+    pub set_option: unsafe extern "C" fn(*mut (), *const c_char, *const c_char) -> *mut c_char,
+    pub free_string: unsafe extern "C" fn(*mut c_char),
+    /// Install a callback the plugin calls with `SearchInfo` encoded as JSON
+    pub set_info_callback: unsafe extern "C" fn(*mut (), InfoCallbackFn, *mut ()),
+    pub get_action:
+        unsafe extern "C" fn(*mut (), *const GameState, *const PluginTimeInfo) -> BotAction,
+    pub notify_draw_offer: unsafe extern "C" fn(*mut ()),
+    /// Returns true and writes the expected reply to the out pointer when pondering
+    pub ponder_start: unsafe extern "C" fn(*mut (), *const GameState, *mut Move) -> bool,
+    pub ponder_hit: unsafe extern "C" fn(*mut ()),
+    pub ponder_miss: unsafe extern "C" fn(*mut ()),
+    /// Bot version, released with `free_string`
+    pub version: unsafe extern "C" fn(*mut ()) -> *mut c_char,
+}
+
+/// Callback a plugin calls to report search progress
+/// The first argument is the context pointer passed to `set_info_callback`
+pub type InfoCallbackFn = unsafe extern "C" fn(*mut (), *const c_char);
+
+/// Host-side end of the info callback: decode the JSON and forward it
+unsafe extern "C" fn forward_search_info(ctx: *mut (), json: *const c_char) {
+    if ctx.is_null() || json.is_null() {
+        return;
+    }
+    unsafe {
+        let sender = &*(ctx as *const SearchInfoSender);
+        let json = CStr::from_ptr(json).to_string_lossy();
+        if let Ok(info) = serde_json::from_str::<SearchInfo>(&json) {
+            sender.send(info);
+        }
+    }
+}
+
+/// Context pointer handed to the plugin together with the info callback
+struct InfoCallbackContext(*mut ());
+
+// The host keeps the pointed-to sender alive and it is itself Send + Sync
+unsafe impl Send for InfoCallbackContext {}
+unsafe impl Sync for InfoCallbackContext {}
+
+/// Wrap a callback received over FFI in a `SearchInfoSender`
+/// Used by `export_bot!` on the plugin side
+#[doc(hidden)]
+pub fn info_sender_from_ffi(callback: InfoCallbackFn, ctx: *mut ()) -> SearchInfoSender {
+    let ctx = InfoCallbackContext(ctx);
+    SearchInfoSender::new(move |info| {
+        let ctx = &ctx;
+        if let Ok(json) = serde_json::to_string(&info)
+            && let Ok(json) = CString::new(json)
+        {
+            unsafe { callback(ctx.0, json.as_ptr()) };
+        }
+    })
+}
+
+/// Version of the plugin interface, bumped whenever `BotVTable` or a type
+/// passed through it changes
+/// Plugins export it as `HNEFATAFL_PLUGIN_ABI` through `export_bot!`
+///
+/// 1. options and `set_option`
+/// 2. search info callback
+/// 3. `get_action` and draw offers
+/// 4. pondering
+/// 5. time info for game clocks
+/// 6. bot version
+/// 7. time info as `PluginTimeInfo`
+/// 8. `BotAction::Forfeit`
+pub const PLUGIN_ABI_VERSION: u32 = 8;
+
+/// `TimeInfo` in plain milliseconds, as it crosses the plugin interface
+/// `moves_to_go` is 0 when the time control has no periods
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginTimeInfo {
+    pub move_limit_ms: u64,
+    pub remaining_ms: u64,
+    pub increment_ms: u64,
+    pub byoyomi_ms: u64,
+    pub moves_to_go: u32,
+    pub opponent_remaining_ms: u64,
+}
+
+impl From<&TimeInfo> for PluginTimeInfo {
+    fn from(time: &TimeInfo) -> Self {
+        let ms = |duration: Duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        PluginTimeInfo {
+            move_limit_ms: ms(time.move_limit),
+            remaining_ms: ms(time.remaining),
+            increment_ms: ms(time.increment),
+            byoyomi_ms: ms(time.byoyomi),
+            moves_to_go: time.moves_to_go.unwrap_or(0),
+            opponent_remaining_ms: ms(time.opponent_remaining),
+        }
+    }
+}
+
+impl From<PluginTimeInfo> for TimeInfo {
+    fn from(time: PluginTimeInfo) -> Self {
+        TimeInfo {
+            move_limit: Duration::from_millis(time.move_limit_ms),
+            remaining: Duration::from_millis(time.remaining_ms),
+            increment: Duration::from_millis(time.increment_ms),
+            byoyomi: Duration::from_millis(time.byoyomi_ms),
+            moves_to_go: (time.moves_to_go > 0).then_some(time.moves_to_go),
+            opponent_remaining: Duration::from_millis(time.opponent_remaining_ms),
+        }
+    }
 }
 
 /// Type signature for the plugin creation function
@@ -31,6 +147,9 @@ pub type CreateBotFn = unsafe extern "C" fn() -> *mut BotPlugin;
 /// Wrapper that loads a bot from a dynamic library
 pub struct PluginBot {
     plugin: Box<BotPlugin>,
+    // Referenced by the plugin through the info callback context pointer
+    info_sender: Option<Box<SearchInfoSender>>,
+    version: String,
     _library: Library, // Keep library alive
 }
 
@@ -41,6 +160,17 @@ impl PluginBot {
             let library = Library::new(path.as_ref())
                 .map_err(|e| format!("Failed to load library: {}", e))?;
 
+            // Calling into a plugin with a different vtable layout would crash
+            let abi: Symbol<*const u32> = library.get(b"HNEFATAFL_PLUGIN_ABI").map_err(|_| {
+                "Plugin was built for an older hnefatafl-arena, rebuild it".to_string()
+            })?;
+            if **abi != PLUGIN_ABI_VERSION {
+                return Err(format!(
+                    "Plugin interface version {} does not match {}, rebuild it",
+                    **abi, PLUGIN_ABI_VERSION
+                ));
+            }
+
             let create_bot: Symbol<CreateBotFn> = library
                 .get(b"create_bot")
                 .map_err(|e| format!("Failed to find create_bot function: {}", e))?;
@@ -52,10 +182,28 @@ impl PluginBot {
 
             let plugin = Box::from_raw(plugin_ptr);
 
-            Ok(PluginBot {
+            let mut bot = PluginBot {
                 plugin,
+                info_sender: None,
+                version: String::new(),
                 _library: library,
-            })
+            };
+            bot.version = bot
+                .take_string((bot.plugin.vtable.version)(bot.plugin.bot_ptr))
+                .unwrap_or_default();
+            Ok(bot)
+        }
+    }
+
+    /// Copy a string returned by the plugin and hand it back to be freed
+    unsafe fn take_string(&self, ptr: *mut c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        unsafe {
+            let s = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            (self.plugin.vtable.free_string)(ptr);
+            Some(s)
         }
     }
 }
@@ -72,6 +220,40 @@ impl Bot for PluginBot {
         }
     }
 
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn options(&self) -> Vec<BotOption> {
+        unsafe {
+            let json = self.take_string((self.plugin.vtable.options)(self.plugin.bot_ptr));
+            json.and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default()
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &OptionValue) -> Result<(), OptionError> {
+        let invalid = |reason: String| OptionError::InvalidValue {
+            name: name.to_string(),
+            reason,
+        };
+        let c_name = CString::new(name).map_err(|e| invalid(e.to_string()))?;
+        let json = serde_json::to_string(value).map_err(|e| invalid(e.to_string()))?;
+        let c_value = CString::new(json).map_err(|e| invalid(e.to_string()))?;
+
+        unsafe {
+            let err = (self.plugin.vtable.set_option)(
+                self.plugin.bot_ptr,
+                c_name.as_ptr(),
+                c_value.as_ptr(),
+            );
+            match self.take_string(err) {
+                None => Ok(()),
+                Some(reason) => Err(invalid(reason)),
+            }
+        }
+    }
+
     fn get_move(&mut self, state: &GameState, time_limit: Duration) -> Option<Move> {
         unsafe {
             let move_ptr = (self.plugin.vtable.get_move)(
@@ -88,6 +270,57 @@ impl Bot for PluginBot {
         }
     }
 
+    fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+        let time = PluginTimeInfo::from(time);
+        unsafe {
+            (self.plugin.vtable.get_action)(
+                self.plugin.bot_ptr,
+                state as *const GameState,
+                &time as *const PluginTimeInfo,
+            )
+        }
+    }
+
+    fn notify_draw_offer(&mut self) {
+        unsafe {
+            (self.plugin.vtable.notify_draw_offer)(self.plugin.bot_ptr);
+        }
+    }
+
+    fn ponder_start(&mut self, state: &GameState) -> Option<Move> {
+        let mut expected = Move::new(Position::new(0, 0), Position::new(0, 0));
+        let pondering = unsafe {
+            (self.plugin.vtable.ponder_start)(
+                self.plugin.bot_ptr,
+                state as *const GameState,
+                &mut expected as *mut Move,
+            )
+        };
+        pondering.then_some(expected)
+    }
+
+    fn ponder_hit(&mut self) {
+        unsafe {
+            (self.plugin.vtable.ponder_hit)(self.plugin.bot_ptr);
+        }
+    }
+
+    fn ponder_miss(&mut self) {
+        unsafe {
+            (self.plugin.vtable.ponder_miss)(self.plugin.bot_ptr);
+        }
+    }
+
+    fn set_search_info_sender(&mut self, sender: SearchInfoSender) {
+        let sender = Box::new(sender);
+        let ctx = &*sender as *const SearchInfoSender as *mut ();
+        unsafe {
+            (self.plugin.vtable.set_info_callback)(self.plugin.bot_ptr, forward_search_info, ctx);
+        }
+        // Dropped only after the plugin was handed the new pointer
+        self.info_sender = Some(sender);
+    }
+
     fn game_start(&mut self, player: Player) {
         unsafe {
             (self.plugin.vtable.game_start)(self.plugin.bot_ptr, player);
@@ -125,6 +358,9 @@ macro_rules! export_bot {
         use std::ffi::CString;
         use std::os::raw::c_char;
 
+        #[unsafe(no_mangle)]
+        pub static HNEFATAFL_PLUGIN_ABI: u32 = $crate::plugin::PLUGIN_ABI_VERSION;
+
         #[unsafe(no_mangle)]
         pub unsafe extern "C" fn create_bot() -> *mut $crate::plugin::BotPlugin {
             let bot = Box::new(<$bot_type>::default());
@@ -137,6 +373,16 @@ macro_rules! export_bot {
                 notify_move: bot_notify_move,
                 game_end: bot_game_end,
                 drop: bot_drop,
+                options: bot_options,
+                set_option: bot_set_option,
+                free_string: bot_free_string,
+                set_info_callback: bot_set_info_callback,
+                get_action: bot_get_action,
+                notify_draw_offer: bot_notify_draw_offer,
+                ponder_start: bot_ponder_start,
+                ponder_hit: bot_ponder_hit,
+                ponder_miss: bot_ponder_miss,
+                version: bot_version,
             };
 
             Box::into_raw(Box::new($crate::plugin::BotPlugin { bot_ptr, vtable }))
@@ -149,6 +395,16 @@ macro_rules! export_bot {
             c_string.into_raw()
         }
 
+        /// Falls back to the plugin crate's version when the bot has none
+        unsafe extern "C" fn bot_version(ptr: *mut ()) -> *mut c_char {
+            let bot = &*(ptr as *const $bot_type);
+            let version = match bot.version() {
+                "" => env!("CARGO_PKG_VERSION"),
+                version => version,
+            };
+            CString::new(version).unwrap_or_default().into_raw()
+        }
+
         unsafe extern "C" fn bot_get_move(
             ptr: *mut (),
             state: *const $crate::game::GameState,
@@ -164,6 +420,45 @@ macro_rules! export_bot {
             }
         }
 
+        unsafe extern "C" fn bot_get_action(
+            ptr: *mut (),
+            state: *const $crate::game::GameState,
+            time: *const $crate::plugin::PluginTimeInfo,
+        ) -> $crate::bot::BotAction {
+            let bot = &mut *(ptr as *mut $bot_type);
+            bot.get_action(&*state, &$crate::clock::TimeInfo::from(*time))
+        }
+
+        unsafe extern "C" fn bot_notify_draw_offer(ptr: *mut ()) {
+            let bot = &mut *(ptr as *mut $bot_type);
+            bot.notify_draw_offer();
+        }
+
+        unsafe extern "C" fn bot_ponder_start(
+            ptr: *mut (),
+            state: *const $crate::game::GameState,
+            expected: *mut $crate::game::Move,
+        ) -> bool {
+            let bot = &mut *(ptr as *mut $bot_type);
+            match bot.ponder_start(&*state) {
+                Some(mv) => {
+                    *expected = mv;
+                    true
+                }
+                None => false,
+            }
+        }
+
+        unsafe extern "C" fn bot_ponder_hit(ptr: *mut ()) {
+            let bot = &mut *(ptr as *mut $bot_type);
+            bot.ponder_hit();
+        }
+
+        unsafe extern "C" fn bot_ponder_miss(ptr: *mut ()) {
+            let bot = &mut *(ptr as *mut $bot_type);
+            bot.ponder_miss();
+        }
+
         unsafe extern "C" fn bot_game_start(ptr: *mut (), player: $crate::game::Player) {
             let bot = &mut *(ptr as *mut $bot_type);
             bot.game_start(player);
@@ -182,5 +477,45 @@ macro_rules! export_bot {
         unsafe extern "C" fn bot_drop(ptr: *mut ()) {
             let _ = Box::from_raw(ptr as *mut $bot_type);
         }
+
+        unsafe extern "C" fn bot_options(ptr: *mut ()) -> *mut c_char {
+            let bot = &*(ptr as *const $bot_type);
+            let json = $crate::serde_json::to_string(&bot.options()).unwrap_or_default();
+            CString::new(json).unwrap_or_default().into_raw()
+        }
+
+        unsafe extern "C" fn bot_set_option(
+            ptr: *mut (),
+            name: *const c_char,
+            value: *const c_char,
+        ) -> *mut c_char {
+            let bot = &mut *(ptr as *mut $bot_type);
+            let name = std::ffi::CStr::from_ptr(name).to_string_lossy();
+            let value = std::ffi::CStr::from_ptr(value).to_string_lossy();
+
+            let result = $crate::serde_json::from_str::<$crate::bot::OptionValue>(&value)
+                .map_err(|e| e.to_string())
+                .and_then(|value| bot.set_option(&name, &value).map_err(|e| e.to_string()));
+
+            match result {
+                Ok(()) => std::ptr::null_mut(),
+                Err(e) => CString::new(e).unwrap_or_default().into_raw(),
+            }
+        }
+
+        unsafe extern "C" fn bot_set_info_callback(
+            ptr: *mut (),
+            callback: $crate::plugin::InfoCallbackFn,
+            ctx: *mut (),
+        ) {
+            let bot = &mut *(ptr as *mut $bot_type);
+            bot.set_search_info_sender($crate::plugin::info_sender_from_ffi(callback, ctx));
+        }
+
+        unsafe extern "C" fn bot_free_string(s: *mut c_char) {
+            if !s.is_null() {
+                let _ = CString::from_raw(s);
+            }
+        }
     };
 }
@@ -0,0 +1,356 @@
+use crate::bot::{Bot, BotAction, BotOption, OptionError, OptionValue};
+use crate::clock::TimeInfo;
+use crate::game::{GameState, Move, Player};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Extra time a process gets to answer on top of its move budget, so that
+/// pipe latency is not mistaken for thinking
+const RESPONSE_GRACE: Duration = Duration::from_secs(1);
+
+/// How long a process may take to list its options after starting
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages sent to a process bot, one JSON object per line
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request<'a> {
+    /// Answered with a JSON array of `BotOption`
+    Options,
+    SetOption {
+        name: &'a str,
+        value: &'a OptionValue,
+    },
+    GameStart {
+        player: Player,
+    },
+    /// Answered with a `BotAction`, e.g. `{"Move":{"from":...,"to":...}}` or `"Resign"`
+    Go {
+        state: &'a GameState,
+        time: &'a TimeInfo,
+        budget_ms: u64,
+    },
+    Move {
+        mv: Move,
+    },
+    DrawOffer,
+    GameEnd,
+}
+
+/// A bot running as a separate program that talks JSON lines over
+/// stdin/stdout, so bots can be written in any language
+/// See PLUGIN_GUIDE.md for the protocol
+pub struct ProcessBot {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    options: Vec<BotOption>,
+}
+
+impl ProcessBot {
+    /// Start `command`, a program followed by its arguments separated by
+    /// spaces; quote an argument that contains spaces, as in a shell
+    pub fn spawn(name: String, command: &str) -> Result<Self, String> {
+        let parts = split_command(command)?;
+        let (program, args) = parts
+            .split_first()
+            .ok_or_else(|| "Empty bot command".to_string())?;
+        let mut process = Command::new(program);
+        process.args(args);
+        Self::start(name, process, command)
+    }
+
+    /// Start the process and ask for its options; `command` names it in errors
+    fn start(name: String, mut process: Command, command: &str) -> Result<Self, String> {
+        let mut child = process
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", command, e))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut bot = ProcessBot {
+            name,
+            child,
+            stdin,
+            lines,
+            options: Vec::new(),
+        };
+        let reply = bot
+            .ask(&Request::Options, REPLY_TIMEOUT)
+            .map_err(|_| format!("{} did not answer the options request", command))?;
+        bot.options = serde_json::from_str(&reply)
+            .map_err(|e| format!("Invalid options from {}: {}", command, e))?;
+        Ok(bot)
+    }
+
+    fn send(&mut self, request: &Request) {
+        let line = serde_json::to_string(request).expect("requests serialize");
+        // A process that went away is noticed when its answer is missing
+        let _ = writeln!(self.stdin, "{}", line).and_then(|_| self.stdin.flush());
+    }
+
+    /// Send a request and wait for the next line of output
+    fn ask(&mut self, request: &Request, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        // Answers that arrived too late belong to earlier requests
+        while self.lines.try_recv().is_ok() {}
+        self.send(request);
+        self.lines.recv_timeout(timeout)
+    }
+}
+
+impl Bot for ProcessBot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn options(&self) -> Vec<BotOption> {
+        self.options.clone()
+    }
+
+    fn set_option(&mut self, name: &str, value: &OptionValue) -> Result<(), OptionError> {
+        // Checked here so callers get the same errors as from `apply_options`
+        // instead of a process that silently ignores the value
+        let option = self
+            .options
+            .iter()
+            .find(|o| o.name == name)
+            .ok_or_else(|| OptionError::Unknown(name.to_string()))?;
+        let value = option.check(value)?;
+        self.send(&Request::SetOption {
+            name,
+            value: &value,
+        });
+        Ok(())
+    }
+
+    fn get_move(&mut self, state: &GameState, time_limit: Duration) -> Option<Move> {
+        self.get_action(state, &TimeInfo::per_move(time_limit))
+            .played_move()
+    }
+
+    fn get_action(&mut self, state: &GameState, time: &TimeInfo) -> BotAction {
+        let budget = time.move_budget();
+        let request = Request::Go {
+            state,
+            time,
+            budget_ms: budget.as_millis() as u64,
+        };
+        // No answer in time gives up the game; the match notices the process
+        // took longer than it was allowed to and scores it as a timeout.
+        // A process that died or answered garbage forfeits, which the match
+        // scores as a crash
+        match self.ask(&request, time.move_limit + RESPONSE_GRACE) {
+            Ok(line) => serde_json::from_str(&line).unwrap_or(BotAction::Forfeit),
+            Err(RecvTimeoutError::Timeout) => BotAction::Resign,
+            Err(RecvTimeoutError::Disconnected) => BotAction::Forfeit,
+        }
+    }
+
+    fn notify_draw_offer(&mut self) {
+        self.send(&Request::DrawOffer);
+    }
+
+    fn game_start(&mut self, player: Player) {
+        self.send(&Request::GameStart { player });
+    }
+
+    fn notify_move(&mut self, mv: Move) {
+        self.send(&Request::Move { mv });
+    }
+
+    fn game_end(&mut self) {
+        self.send(&Request::GameEnd);
+    }
+}
+
+/// Split a command line into words like a shell does: single quotes keep
+/// everything literally, double quotes allow `\"` and `\\`, and a backslash
+/// outside quotes escapes the next character
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unclosed quote in {}", command)),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("Unclosed quote in {}", command)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unclosed quote in {}", command)),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.next());
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+impl Drop for ProcessBot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{Match, MatchConfig, Termination};
+    use crate::bot::GreedyBot;
+    use crate::game::Position;
+
+    const OPTIONS: &str = r#"[{"name":"depth","type":"int","default":3,"min":1,"max":8}]"#;
+
+    /// A bot whose process lists `OPTIONS` and runs the shell command `on_go`
+    /// for every `go` request
+    fn script_bot(on_go: &str) -> Result<ProcessBot, String> {
+        let script = format!(
+            r#"read line; echo '{}'
+while read line; do
+  case "$line" in *'"type":"go"'*) {} ;; esac
+done"#,
+            OPTIONS, on_go
+        );
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(script);
+        ProcessBot::start("Script".to_string(), process, "script")
+    }
+
+    fn play(bot: ProcessBot) -> Termination {
+        let config = MatchConfig {
+            time_per_move: Duration::from_millis(200),
+            max_moves: 20,
+            ..Default::default()
+        };
+        let defender = Box::new(GreedyBot::new("Greedy".to_string()));
+        Match::new(Box::new(bot), defender, config, false)
+            .play()
+            .termination
+    }
+
+    #[test]
+    fn test_options_handshake() {
+        let bot = script_bot("true").unwrap();
+        assert_eq!(bot.options(), vec![BotOption::int("depth", 3, 1, 8)]);
+
+        let mut process = Command::new("sh");
+        process.arg("-c").arg("read line; echo nonsense");
+        let err = ProcessBot::start("Script".to_string(), process, "script").err();
+        assert!(err.unwrap().starts_with("Invalid options from script"));
+    }
+
+    #[test]
+    fn test_set_option_is_checked() {
+        let mut bot = script_bot("true").unwrap();
+        assert_eq!(bot.set_option("depth", &OptionValue::Int(5)), Ok(()));
+        assert_eq!(
+            bot.set_option("depth", &OptionValue::String("4".to_string())),
+            Ok(())
+        );
+        assert!(matches!(
+            bot.set_option("depth", &OptionValue::Int(9)),
+            Err(OptionError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            bot.set_option("width", &OptionValue::Int(1)),
+            Err(OptionError::Unknown("width".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_move() {
+        let mut bot =
+            script_bot(r#"echo '{"Move":{"from":{"row":0,"col":3},"to":{"row":0,"col":2}}}'"#)
+                .unwrap();
+        let action = bot.get_action(
+            &GameState::new_brandubh(),
+            &TimeInfo::per_move(Duration::from_secs(1)),
+        );
+        let expected = Move::new(Position::new(0, 3), Position::new(0, 2));
+        assert_eq!(action, BotAction::Move(expected));
+    }
+
+    #[test]
+    fn test_late_reply_resigns() {
+        let mut bot = script_bot(r#"sleep 2; echo '"OfferDraw"'"#).unwrap();
+        let action = bot.get_action(
+            &GameState::new_brandubh(),
+            &TimeInfo::per_move(Duration::from_millis(100)),
+        );
+        assert_eq!(action, BotAction::Resign);
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"python3  "my bot.py" --name 'Big Bot' a\ b "say \"hi\"" ''"#),
+            Ok(vec![
+                "python3".to_string(),
+                "my bot.py".to_string(),
+                "--name".to_string(),
+                "Big Bot".to_string(),
+                "a b".to_string(),
+                r#"say "hi""#.to_string(),
+                String::new(),
+            ])
+        );
+        assert!(split_command("python3 'bot.py").is_err());
+        assert_eq!(split_command("  "), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_garbage_is_a_crash() {
+        let bot = script_bot("echo e2e4").unwrap();
+        assert_eq!(play(bot), Termination::Crash);
+    }
+
+    #[test]
+    fn test_exit_is_a_crash() {
+        let bot = script_bot("exit 0").unwrap();
+        assert_eq!(play(bot), Termination::Crash);
+    }
+}
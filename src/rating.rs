@@ -0,0 +1,486 @@
+use crate::arena::MatchResult;
+use crate::bot::Bot;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// K-factor used for Elo updates
+pub const ELO_K: f64 = 32.0;
+/// Rating new bots start with, on both scales
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// Glicko-2 rating deviation of a new bot
+const INITIAL_DEVIATION: f64 = 350.0;
+const INITIAL_VOLATILITY: f64 = 0.06;
+/// System constant limiting how fast the volatility changes
+const TAU: f64 = 0.5;
+/// Conversion factor between the Glicko and Glicko-2 scales
+const GLICKO2_SCALE: f64 = 173.7178;
+
+/// A bot as it is rated: two versions of the same bot are rated separately
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BotId {
+    pub name: String,
+    pub version: String,
+}
+
+impl BotId {
+    pub fn new(name: &str, version: &str) -> Self {
+        BotId {
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    pub fn of(bot: &dyn Bot) -> Self {
+        Self::new(bot.name(), bot.version())
+    }
+}
+
+impl fmt::Display for BotId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.version.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}@{}", self.name, self.version)
+        }
+    }
+}
+
+/// Outcome of one game between two rated bots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatedGame {
+    pub attacker: BotId,
+    pub defender: BotId,
+    /// 1.0 for an attacker win, 0.5 for a draw, 0.0 for a defender win
+    pub score: f64,
+}
+
+impl RatedGame {
+    pub fn new(attacker: BotId, defender: BotId, score: f64) -> Self {
+        RatedGame {
+            attacker,
+            defender,
+            score,
+        }
+    }
+
+    pub fn from_result(attacker: BotId, defender: BotId, result: &MatchResult) -> Self {
+        Self::new(attacker, defender, result.attacker_score())
+    }
+}
+
+/// Expected score against an opponent rated `diff` points lower
+pub fn expected_score(diff: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-diff / 400.0))
+}
+
+/// Elo difference that corresponds to an expected score
+pub fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Elo difference and half-width of its 95% confidence interval for a
+/// win/draw/loss record
+pub fn elo_estimate(wins: u32, draws: u32, losses: u32) -> (f64, f64) {
+    let n = (wins + draws + losses) as f64;
+    if n == 0.0 {
+        return (0.0, f64::INFINITY);
+    }
+
+    let score = (wins as f64 + 0.5 * draws as f64) / n;
+    let variance = (wins as f64 * (1.0 - score).powi(2)
+        + draws as f64 * (0.5 - score).powi(2)
+        + losses as f64 * score.powi(2))
+        / n;
+    let margin = 1.96 * (variance / n).sqrt();
+
+    let low = elo_difference(score - margin);
+    let high = elo_difference(score + margin);
+    (elo_difference(score), (high - low) / 2.0)
+}
+
+/// Glicko-2 rating, stored on the Glicko scale (1500 / 350)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Glicko2 {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Glicko2 {
+            rating: INITIAL_RATING,
+            deviation: INITIAL_DEVIATION,
+            volatility: INITIAL_VOLATILITY,
+        }
+    }
+}
+
+impl Glicko2 {
+    /// 95% confidence interval of the rating
+    pub fn interval(&self) -> (f64, f64) {
+        (
+            self.rating - 1.96 * self.deviation,
+            self.rating + 1.96 * self.deviation,
+        )
+    }
+
+    fn mu(&self) -> f64 {
+        (self.rating - INITIAL_RATING) / GLICKO2_SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / GLICKO2_SCALE
+    }
+
+    /// Rating after one rating period against `(opponent, score)` pairs
+    /// A period without games only increases the deviation
+    pub fn update(&self, games: &[(Glicko2, f64)]) -> Glicko2 {
+        let (mu, phi, sigma) = (self.mu(), self.phi(), self.volatility);
+
+        if games.is_empty() {
+            let phi = (phi * phi + sigma * sigma).sqrt();
+            return Glicko2 {
+                deviation: (phi * GLICKO2_SCALE).min(INITIAL_DEVIATION),
+                ..*self
+            };
+        }
+
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt();
+        let mut v_inv = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in games {
+            let g = g(opponent.phi());
+            let e = 1.0 / (1.0 + (-g * (mu - opponent.mu())).exp());
+            v_inv += g * g * e * (1.0 - e);
+            improvement += g * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * improvement;
+
+        let sigma = new_volatility(phi, sigma, v, delta);
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu = mu + phi * phi * improvement;
+
+        Glicko2 {
+            rating: mu * GLICKO2_SCALE + INITIAL_RATING,
+            deviation: phi * GLICKO2_SCALE,
+            volatility: sigma,
+        }
+    }
+}
+
+/// Step 5 of Glickman's Glicko-2 paper, solved with the Illinois algorithm
+fn new_volatility(phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let d = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d) - (x - a) / (TAU * TAU)
+    };
+
+    let mut lo = a;
+    let mut hi = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let (mut f_lo, mut f_hi) = (f(lo), f(hi));
+    while (hi - lo).abs() > 1e-6 {
+        let c = lo + (lo - hi) * f_lo / (f_hi - f_lo);
+        let f_c = f(c);
+        if f_c * f_hi <= 0.0 {
+            lo = hi;
+            f_lo = f_hi;
+        } else {
+            f_lo /= 2.0;
+        }
+        hi = c;
+        f_hi = f_c;
+    }
+
+    (lo / 2.0).exp()
+}
+
+/// Ratings and lifetime record of one bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotRating {
+    pub id: BotId,
+    pub elo: f64,
+    pub glicko: Glicko2,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Number of rating updates (tournaments) the bot took part in
+    pub tournaments: u32,
+}
+
+impl BotRating {
+    pub fn new(id: BotId) -> Self {
+        BotRating {
+            id,
+            elo: INITIAL_RATING,
+            glicko: Glicko2::default(),
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            tournaments: 0,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// 95% confidence interval of the Elo rating, from the bot's record
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let (_, margin) = elo_estimate(self.wins, self.draws, self.losses);
+        let margin = margin.min(INITIAL_DEVIATION * 1.96);
+        (self.elo - margin, self.elo + margin)
+    }
+
+    fn record(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+/// Persistent ratings of every bot seen so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RatingList {
+    #[serde(with = "rating_entries")]
+    ratings: BTreeMap<BotId, BotRating>,
+}
+
+/// Ratings are saved as a list, since JSON object keys must be strings and
+/// every rating holds its `BotId` anyway
+mod rating_entries {
+    use super::{BotId, BotRating};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        ratings: &BTreeMap<BotId, BotRating>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ratings.values())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<BotId, BotRating>, D::Error> {
+        let list = Vec::<BotRating>::deserialize(deserializer)?;
+        Ok(list
+            .into_iter()
+            .map(|rating| (rating.id.clone(), rating))
+            .collect())
+    }
+}
+
+impl RatingList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load ratings from a JSON file, starting empty if it does not exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn get(&self, id: &BotId) -> Option<&BotRating> {
+        self.ratings.get(id)
+    }
+
+    fn entry(&mut self, id: &BotId) -> &mut BotRating {
+        self.ratings
+            .entry(id.clone())
+            .or_insert_with(|| BotRating::new(id.clone()))
+    }
+
+    /// Rate the games of one tournament
+    /// All games count as a single rating period: every update uses the
+    /// ratings from before the tournament, so the order of games is irrelevant.
+    /// Bots that did not play only get a larger Glicko deviation
+    pub fn update(&mut self, games: &[RatedGame]) {
+        for game in games {
+            self.entry(&game.attacker);
+            self.entry(&game.defender);
+        }
+        let before = self.ratings.clone();
+
+        let mut elo_change: BTreeMap<&BotId, f64> = BTreeMap::new();
+        let mut glicko_games: BTreeMap<&BotId, Vec<(Glicko2, f64)>> = BTreeMap::new();
+        for game in games {
+            let attacker = &before[&game.attacker];
+            let defender = &before[&game.defender];
+            let expected = expected_score(attacker.elo - defender.elo);
+            let change = ELO_K * (game.score - expected);
+
+            *elo_change.entry(&attacker.id).or_default() += change;
+            *elo_change.entry(&defender.id).or_default() -= change;
+            glicko_games
+                .entry(&attacker.id)
+                .or_default()
+                .push((defender.glicko, game.score));
+            glicko_games
+                .entry(&defender.id)
+                .or_default()
+                .push((attacker.glicko, 1.0 - game.score));
+
+            self.entry(&game.attacker).record(game.score);
+            self.entry(&game.defender).record(1.0 - game.score);
+        }
+
+        for (key, rating) in &mut self.ratings {
+            let played = glicko_games.get(key).map(Vec::as_slice).unwrap_or(&[]);
+            rating.glicko = rating.glicko.update(played);
+            if !played.is_empty() {
+                rating.elo += elo_change[key];
+                rating.tournaments += 1;
+            }
+        }
+    }
+
+    /// All bots, best Glicko-2 rating first
+    pub fn leaderboard(&self) -> Vec<&BotRating> {
+        let mut list: Vec<_> = self.ratings.values().collect();
+        list.sort_by(|a, b| b.glicko.rating.total_cmp(&a.glicko.rating));
+        list
+    }
+
+    pub fn display(&self) {
+        println!("\nRatings:");
+        println!(
+            "{:<4} {:<28} {:>14} {:>14} {:>6}",
+            "#", "Bot", "Glicko-2", "Elo", "Games"
+        );
+        for (i, rating) in self.leaderboard().iter().enumerate() {
+            let (elo_low, elo_high) = rating.elo_interval();
+            println!(
+                "{:<4} {:<28} {:>7.0} ±{:<5.0} {:>7.0} ±{:<5.0} {:>6}",
+                i + 1,
+                rating.id.to_string(),
+                rating.glicko.rating,
+                1.96 * rating.glicko.deviation,
+                rating.elo,
+                (elo_high - elo_low) / 2.0,
+                rating.games()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() < eps
+    }
+
+    #[test]
+    fn test_glicko2_paper_example() {
+        // Example from Glickman's "Example of the Glicko-2 system"
+        let player = Glicko2 {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+        let opponent = |rating, deviation| Glicko2 {
+            rating,
+            deviation,
+            volatility: 0.06,
+        };
+        let updated = player.update(&[
+            (opponent(1400.0, 30.0), 1.0),
+            (opponent(1550.0, 100.0), 0.0),
+            (opponent(1700.0, 300.0), 0.0),
+        ]);
+
+        assert!(approx(updated.rating, 1464.06, 0.1));
+        assert!(approx(updated.deviation, 151.52, 0.1));
+        assert!(approx(updated.volatility, 0.05999, 0.0001));
+    }
+
+    #[test]
+    fn test_elo_estimate() {
+        assert!(approx(elo_difference(0.5), 0.0, 1e-9));
+        assert!(approx(elo_difference(expected_score(200.0)), 200.0, 1e-6));
+
+        let (elo, margin) = elo_estimate(60, 0, 40);
+        assert!(approx(elo, 70.4, 0.1));
+        assert!(margin > 50.0 && margin < 100.0);
+    }
+
+    #[test]
+    fn test_rating_list_update_and_persist() {
+        let strong = BotId::new("Strong", "1.0");
+        let weak = BotId::new("Weak", "");
+        let games: Vec<_> = (0..10)
+            .map(|i| {
+                if i % 2 == 0 {
+                    RatedGame::new(strong.clone(), weak.clone(), 1.0)
+                } else {
+                    RatedGame::new(weak.clone(), strong.clone(), 0.0)
+                }
+            })
+            .collect();
+
+        let mut list = RatingList::new();
+        list.update(&games);
+
+        let s = list.get(&strong).unwrap();
+        let w = list.get(&weak).unwrap();
+        assert_eq!((s.wins, s.losses, s.tournaments), (10, 0, 1));
+        assert!(approx(s.elo + w.elo, 2.0 * INITIAL_RATING, 1e-9));
+        assert!(s.glicko.rating > w.glicko.rating);
+        assert_eq!(list.leaderboard()[0].id, strong);
+
+        let path = std::env::temp_dir().join(format!("ratings-{}.json", std::process::id()));
+        list.save(&path).unwrap();
+        let loaded = RatingList::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.get(&strong), Some(s));
+    }
+
+    #[test]
+    fn test_names_with_at_signs_are_kept_apart() {
+        let plain = BotId::new("Bot", "v1@beta");
+        let tagged = BotId::new("Bot@v1", "beta");
+        let mut list = RatingList::new();
+        list.update(&[RatedGame::new(plain.clone(), tagged.clone(), 1.0)]);
+        assert_eq!(list.get(&plain).unwrap().wins, 1);
+        assert_eq!(list.get(&tagged).unwrap().losses, 1);
+
+        let json = serde_json::to_string(&list).unwrap();
+        let loaded: RatingList = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.leaderboard().len(), 2);
+    }
+}
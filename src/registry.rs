@@ -0,0 +1,302 @@
+use crate::bot::Bot;
+use crate::config::BotSource;
+use crate::plugin::PluginBot;
+use crate::process::ProcessBot;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Where `serve` keeps uploaded bots unless told otherwise
+pub const DEFAULT_BOT_DIR: &str = "bots";
+
+/// Lists the registered bots, next to the libraries themselves
+const MANIFEST: &str = "bots.json";
+
+/// Prefix that selects a registered bot, e.g. `bot:alphabeta`
+pub const REGISTERED_PREFIX: &str = "bot:";
+
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid bot list: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("Invalid bot ID {0:?}, use up to 40 letters, digits, - and _")]
+    InvalidId(String),
+    #[error("No registered bot {0}")]
+    NotFound(String),
+    #[error("Bot rejected: {0}")]
+    Rejected(String),
+}
+
+/// How a registered bot is started
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RegisteredSource {
+    /// Library in the bot directory, by file name
+    Plugin { file: String },
+    /// Program speaking the `ProcessBot` protocol
+    Command { command: String },
+}
+
+/// A bot added to the registry, as listed in its manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisteredBot {
+    pub id: String,
+    pub name: String,
+    /// Version the bot reported when it was added
+    #[serde(default)]
+    pub version: String,
+    #[serde(flatten)]
+    pub source: RegisteredSource,
+    /// Seconds since the Unix epoch
+    pub added: u64,
+}
+
+/// Bots uploaded at runtime, kept in one directory with a manifest
+///
+/// Every bot is checked before it is registered: a library must load with
+/// the current plugin interface and a command must start and answer the
+/// options request. Libraries are stored under a fresh file name each time,
+/// so a replaced bot never picks up a copy the process still has loaded
+pub struct BotRegistry {
+    dir: PathBuf,
+    // Held while the manifest is read, changed and written back
+    manifest: Mutex<()>,
+}
+
+impl BotRegistry {
+    /// Registry in `dir`, created when the first bot is added
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        BotRegistry {
+            dir: dir.as_ref().to_path_buf(),
+            manifest: Mutex::new(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// All registered bots in the order they were added
+    pub fn list(&self) -> Result<Vec<RegisteredBot>, RegistryError> {
+        let _guard = self.manifest.lock().unwrap();
+        self.read()
+    }
+
+    pub fn get(&self, id: &str) -> Result<RegisteredBot, RegistryError> {
+        self.list()?
+            .into_iter()
+            .find(|bot| bot.id == id)
+            .ok_or_else(|| RegistryError::NotFound(id.to_string()))
+    }
+
+    /// How to create the bot `id`, for matches and tournaments
+    pub fn source(&self, id: &str) -> Result<BotSource, RegistryError> {
+        Ok(match self.get(id)?.source {
+            RegisteredSource::Plugin { file } => BotSource::Plugin(self.dir.join(file)),
+            RegisteredSource::Command { command } => BotSource::Command(command),
+        })
+    }
+
+    /// Store `library`, a compiled plugin, as bot `id`
+    /// Replaces a bot with the same ID
+    pub fn add_plugin(&self, id: &str, library: &[u8]) -> Result<RegisteredBot, RegistryError> {
+        check_id(id)?;
+        fs::create_dir_all(&self.dir)?;
+        let file = format!(
+            "{}-{:08x}.{}",
+            id,
+            rand::random::<u32>(),
+            std::env::consts::DLL_EXTENSION
+        );
+        let path = self.dir.join(&file);
+        fs::write(&path, library)?;
+
+        let checked = PluginBot::load(&path).and_then(|bot| metadata(&bot));
+        let (name, version) = match checked {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                return Err(RegistryError::Rejected(e));
+            }
+        };
+        self.insert(RegisteredBot {
+            id: id.to_string(),
+            name,
+            version,
+            source: RegisteredSource::Plugin { file },
+            added: now(),
+        })
+    }
+
+    /// Register `command` as bot `id` called `name`
+    /// Replaces a bot with the same ID
+    pub fn add_command(
+        &self,
+        id: &str,
+        name: &str,
+        command: &str,
+    ) -> Result<RegisteredBot, RegistryError> {
+        check_id(id)?;
+        if name.trim().is_empty() {
+            return Err(RegistryError::Rejected("The bot needs a name".to_string()));
+        }
+        // Started once to see that it runs and speaks the protocol
+        ProcessBot::spawn(name.to_string(), command).map_err(RegistryError::Rejected)?;
+        fs::create_dir_all(&self.dir)?;
+        self.insert(RegisteredBot {
+            id: id.to_string(),
+            name: name.trim().to_string(),
+            version: String::new(),
+            source: RegisteredSource::Command {
+                command: command.to_string(),
+            },
+            added: now(),
+        })
+    }
+
+    /// Unregister bot `id` and delete its library
+    pub fn remove(&self, id: &str) -> Result<RegisteredBot, RegistryError> {
+        let _guard = self.manifest.lock().unwrap();
+        let mut bots = self.read()?;
+        let index = bots
+            .iter()
+            .position(|bot| bot.id == id)
+            .ok_or_else(|| RegistryError::NotFound(id.to_string()))?;
+        let removed = bots.remove(index);
+        self.write(&bots)?;
+        self.delete_library(&removed);
+        Ok(removed)
+    }
+
+    fn insert(&self, bot: RegisteredBot) -> Result<RegisteredBot, RegistryError> {
+        let _guard = self.manifest.lock().unwrap();
+        let mut bots = self.read()?;
+        if let Some(old) = bots.iter_mut().find(|old| old.id == bot.id) {
+            let old = std::mem::replace(old, bot.clone());
+            self.delete_library(&old);
+        } else {
+            bots.push(bot.clone());
+        }
+        self.write(&bots)?;
+        Ok(bot)
+    }
+
+    fn delete_library(&self, bot: &RegisteredBot) {
+        // Games still playing the bot keep their loaded copy
+        if let RegisteredSource::Plugin { file } = &bot.source {
+            let _ = fs::remove_file(self.dir.join(file));
+        }
+    }
+
+    fn read(&self) -> Result<Vec<RegisteredBot>, RegistryError> {
+        match fs::read_to_string(self.dir.join(MANIFEST)) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, bots: &[RegisteredBot]) -> Result<(), RegistryError> {
+        // Written next to the manifest and renamed, so readers never see half
+        let temp = self.dir.join(format!("{}.tmp", MANIFEST));
+        fs::write(&temp, serde_json::to_string_pretty(bots)?)?;
+        fs::rename(&temp, self.dir.join(MANIFEST))?;
+        Ok(())
+    }
+}
+
+/// IDs end up in file names and URLs, so only plain characters are allowed
+fn check_id(id: &str) -> Result<(), RegistryError> {
+    let valid = !id.is_empty()
+        && id.len() <= 40
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(RegistryError::InvalidId(id.to_string())),
+    }
+}
+
+/// Name and version a loaded bot reports
+fn metadata(bot: &dyn Bot) -> Result<(String, String), String> {
+    let name = bot.name().trim();
+    if name.is_empty() {
+        return Err("The bot reports no name".to_string());
+    }
+    Ok((name.to_string(), bot.version().to_string()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejected_bots_are_not_registered() {
+        let dir = std::env::temp_dir().join(format!("registry-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let registry = BotRegistry::new(&dir);
+        assert!(registry.list().unwrap().is_empty());
+
+        assert!(matches!(
+            registry.add_plugin("../evil", b"\x7fELF"),
+            Err(RegistryError::InvalidId(_))
+        ));
+        assert!(matches!(
+            registry.add_plugin("broken", b"not a library"),
+            Err(RegistryError::Rejected(_))
+        ));
+        assert!(matches!(
+            registry.add_command("missing", "Missing", "no-such-bot-program"),
+            Err(RegistryError::Rejected(_))
+        ));
+        assert!(matches!(
+            registry.source("broken"),
+            Err(RegistryError::NotFound(_))
+        ));
+
+        // Nothing but the directory is left behind
+        assert!(registry.list().unwrap().is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = std::env::temp_dir().join(format!("registry-list-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let registry = BotRegistry::new(&dir);
+        let bot = RegisteredBot {
+            id: "python".to_string(),
+            name: "Python Bot".to_string(),
+            version: String::new(),
+            source: RegisteredSource::Command {
+                command: "python3 bot.py".to_string(),
+            },
+            added: 0,
+        };
+        registry.insert(bot.clone()).unwrap();
+        assert_eq!(registry.list().unwrap(), vec![bot.clone()]);
+        assert_eq!(
+            registry.source("python").unwrap(),
+            BotSource::Command("python3 bot.py".to_string())
+        );
+
+        assert_eq!(registry.remove("python").unwrap(), bot);
+        assert!(registry.list().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+use crate::gauntlet::Record;
+use crate::tournament::TournamentResults;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// One bot's line in a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportRow {
+    pub name: String,
+    pub points: f64,
+    pub as_attacker: Record,
+    pub as_defender: Record,
+    pub byes: u32,
+    /// Sum of the points of every opponent met
+    pub buchholz: f64,
+    /// Points scored against each opponent times that opponent's points
+    pub sonneborn_berger: f64,
+    /// Average number of moves in this bot's games
+    pub average_moves: f64,
+    /// Thinking time over all games
+    pub total_time: Duration,
+    /// Thinking time per own move
+    pub average_move_time: Duration,
+}
+
+impl ReportRow {
+    pub fn total(&self) -> Record {
+        self.as_attacker.combined(&self.as_defender)
+    }
+}
+
+/// A crosstable cell: points of the row bot against the column bot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossCell {
+    pub points: f64,
+    pub games: u32,
+}
+
+/// Standings with role split, tiebreaks, crosstable and time usage, ready to
+/// be rendered as a terminal table, CSV, Markdown or HTML
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub title: String,
+    /// Rows in the order of the standings
+    pub rows: Vec<ReportRow>,
+    /// `crosstable[i][j]` is row `i` against row `j`, `None` if they never met
+    pub crosstable: Vec<Vec<Option<CrossCell>>>,
+    pub games: usize,
+}
+
+impl Report {
+    pub fn new(title: &str, results: &TournamentResults) -> Self {
+        let entries = results.standings().entries();
+        let index: HashMap<&str, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.name.as_str(), i))
+            .collect();
+        let count = entries.len();
+
+        let mut rows: Vec<ReportRow> = entries
+            .iter()
+            .map(|entry| ReportRow {
+                name: entry.name.clone(),
+                points: entry.points,
+                as_attacker: Record::default(),
+                as_defender: Record::default(),
+                byes: entry.byes,
+                buchholz: entry.buchholz,
+                sonneborn_berger: 0.0,
+                average_moves: 0.0,
+                total_time: Duration::ZERO,
+                average_move_time: Duration::ZERO,
+            })
+            .collect();
+        let mut crosstable = vec![vec![None; count]; count];
+        let mut add_cell = |row: usize, col: usize, points: f64| {
+            let cell: &mut Option<CrossCell> = &mut crosstable[row][col];
+            let cell = cell.get_or_insert(CrossCell {
+                points: 0.0,
+                games: 0,
+            });
+            cell.points += points;
+            cell.games += 1;
+        };
+
+        for game in results.games() {
+            let (Some(&attacker), Some(&defender)) = (
+                index.get(game.attacker.name.as_str()),
+                index.get(game.defender.name.as_str()),
+            ) else {
+                continue;
+            };
+            rows[attacker].as_attacker.add(game.score);
+            rows[defender].as_defender.add(1.0 - game.score);
+            add_cell(attacker, defender, game.score);
+            add_cell(defender, attacker, 1.0 - game.score);
+        }
+
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.sonneborn_berger = crosstable[i]
+                .iter()
+                .zip(entries)
+                .filter_map(|(cell, opponent)| cell.map(|cell| cell.points * opponent.points))
+                .sum();
+        }
+
+        // Game length and time usage, from games added with their outcome
+        let mut moves = vec![(0usize, 0usize); count];
+        let mut own_moves = vec![0u32; count];
+        for stats in results.stats() {
+            let sides = [
+                (&stats.attacker, stats.attacker_time, stats.attacker_moves),
+                (&stats.defender, stats.defender_time, stats.defender_moves),
+            ];
+            for (name, time, played) in sides {
+                if let Some(&i) = index.get(name.as_str()) {
+                    moves[i].0 += stats.moves();
+                    moves[i].1 += 1;
+                    own_moves[i] += played as u32;
+                    rows[i].total_time += time;
+                }
+            }
+        }
+        for (i, row) in rows.iter_mut().enumerate() {
+            let (total, games) = moves[i];
+            if games > 0 {
+                row.average_moves = total as f64 / games as f64;
+            }
+            if own_moves[i] > 0 {
+                row.average_move_time = row.total_time / own_moves[i];
+            }
+        }
+
+        Report {
+            title: title.to_string(),
+            rows,
+            crosstable,
+            games: results.games().len(),
+        }
+    }
+
+    /// Aligned plain-text tables for the terminal
+    pub fn to_text(&self) -> String {
+        let mut text = format!("\n{}\n{}\n", self.title, "=".repeat(self.title.len()));
+        text.push_str(&format!(
+            "{:<4} {:<20} {:>6} {:>9} {:>9} {:>9} {:>6} {:>6} {:>7} {:>8}\n",
+            "#",
+            "Bot",
+            "Points",
+            "W/D/L",
+            "Attacker",
+            "Defender",
+            "Buchh",
+            "S-B",
+            "Moves",
+            "ms/move"
+        ));
+        for (i, row) in self.rows.iter().enumerate() {
+            text.push_str(&format!(
+                "{:<4} {:<20} {:>6} {:>9} {:>9} {:>9} {:>6} {:>6} {:>7.1} {:>8.1}\n",
+                i + 1,
+                row.name,
+                number(row.points),
+                record(&row.total()),
+                record(&row.as_attacker),
+                record(&row.as_defender),
+                number(row.buchholz),
+                number(row.sonneborn_berger),
+                row.average_moves,
+                millis(row.average_move_time)
+            ));
+        }
+
+        text.push_str("\nCrosstable (row's points against column):\n");
+        text.push_str(&format!("{:<4} {:<20}", "#", "Bot"));
+        for i in 0..self.rows.len() {
+            text.push_str(&format!(" {:>5}", i + 1));
+        }
+        text.push('\n');
+        for (i, row) in self.rows.iter().enumerate() {
+            text.push_str(&format!("{:<4} {:<20}", i + 1, row.name));
+            for j in 0..self.rows.len() {
+                text.push_str(&format!(" {:>5}", self.cell(i, j)));
+            }
+            text.push('\n');
+        }
+        text.push_str(&format!("\n{} games\n", self.games));
+        text
+    }
+
+    /// Standings, one line per bot
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "rank,bot,points,wins,draws,losses,attacker_wins,attacker_draws,attacker_losses,\
+             defender_wins,defender_draws,defender_losses,byes,buchholz,sonneborn_berger,\
+             average_moves,total_time_ms,average_move_ms\n",
+        );
+        for (i, row) in self.rows.iter().enumerate() {
+            let total = row.total();
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.1},{},{:.1}\n",
+                i + 1,
+                csv_field(&row.name),
+                row.points,
+                total.wins,
+                total.draws,
+                total.losses,
+                row.as_attacker.wins,
+                row.as_attacker.draws,
+                row.as_attacker.losses,
+                row.as_defender.wins,
+                row.as_defender.draws,
+                row.as_defender.losses,
+                row.byes,
+                row.buchholz,
+                row.sonneborn_berger,
+                row.average_moves,
+                row.total_time.as_millis(),
+                millis(row.average_move_time)
+            ));
+        }
+        csv
+    }
+
+    /// Crosstable with a header row of bot names; cells are the row bot's
+    /// points against the column bot, empty if they never met
+    pub fn crosstable_csv(&self) -> String {
+        let mut csv = String::from("bot");
+        for row in &self.rows {
+            csv.push(',');
+            csv.push_str(&csv_field(&row.name));
+        }
+        csv.push('\n');
+        for (i, row) in self.rows.iter().enumerate() {
+            csv.push_str(&csv_field(&row.name));
+            for cell in &self.crosstable[i] {
+                csv.push(',');
+                if let Some(cell) = cell {
+                    csv.push_str(&cell.points.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {}\n\n## Standings\n\n", self.title);
+        md.push_str(
+            "| # | Bot | Points | W/D/L | As attacker | As defender | Buchholz | Sonneborn-Berger | Avg. moves | Avg. time/move |\n",
+        );
+        md.push_str("|--:|-----|-------:|:-----:|:-----------:|:-----------:|---------:|-----------------:|-----------:|---------------:|\n");
+        for (i, row) in self.rows.iter().enumerate() {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {:.1} | {:.1} ms |\n",
+                i + 1,
+                row.name.replace('|', "\\|"),
+                number(row.points),
+                record(&row.total()),
+                record(&row.as_attacker),
+                record(&row.as_defender),
+                number(row.buchholz),
+                number(row.sonneborn_berger),
+                row.average_moves,
+                millis(row.average_move_time)
+            ));
+        }
+
+        md.push_str(
+            "\n## Crosstable\n\nPoints of the row bot against the column bot.\n\n| # | Bot |",
+        );
+        for i in 0..self.rows.len() {
+            md.push_str(&format!(" {} |", i + 1));
+        }
+        md.push_str("\n|--:|-----|");
+        md.push_str(&":-:|".repeat(self.rows.len()));
+        md.push('\n');
+        for (i, row) in self.rows.iter().enumerate() {
+            md.push_str(&format!("| {} | {} |", i + 1, row.name.replace('|', "\\|")));
+            for j in 0..self.rows.len() {
+                md.push_str(&format!(" {} |", self.cell(i, j)));
+            }
+            md.push('\n');
+        }
+        md.push_str(&format!("\n{} games played.\n", self.games));
+        md
+    }
+
+    /// A single page with inline styles, no external files needed
+    pub fn to_html(&self) -> String {
+        let title = html_escape(&self.title);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n{HTML_STYLE}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<h2>Standings</h2>\n<table>\n<tr><th>#</th><th>Bot</th>\
+             <th>Points</th><th>W/D/L</th><th>As attacker</th><th>As defender</th>\
+             <th>Buchholz</th><th>Sonneborn-Berger</th><th>Avg. moves</th>\
+             <th>Avg. time/move</th></tr>\n"
+        );
+        for (i, row) in self.rows.iter().enumerate() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"name\">{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                 <td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{:.1} ms</td></tr>\n",
+                i + 1,
+                html_escape(&row.name),
+                number(row.points),
+                record(&row.total()),
+                record(&row.as_attacker),
+                record(&row.as_defender),
+                number(row.buchholz),
+                number(row.sonneborn_berger),
+                row.average_moves,
+                millis(row.average_move_time)
+            ));
+        }
+
+        html.push_str(
+            "</table>\n<h2>Crosstable</h2>\n<p>Points of the row bot against the column bot.</p>\n\
+             <table>\n<tr><th>#</th><th>Bot</th>",
+        );
+        for (i, row) in self.rows.iter().enumerate() {
+            html.push_str(&format!(
+                "<th title=\"{}\">{}</th>",
+                html_escape(&row.name),
+                i + 1
+            ));
+        }
+        html.push_str("</tr>\n");
+        for (i, row) in self.rows.iter().enumerate() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"name\">{}</td>",
+                i + 1,
+                html_escape(&row.name)
+            ));
+            for j in 0..self.rows.len() {
+                let class = match self.crosstable[i][j] {
+                    _ if i == j => "self",
+                    Some(cell) if cell.points * 2.0 > cell.games as f64 => "win",
+                    Some(cell) if cell.points * 2.0 < cell.games as f64 => "loss",
+                    Some(_) => "draw",
+                    None => "",
+                };
+                html.push_str(&format!("<td class=\"{}\">{}</td>", class, self.cell(i, j)));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str(&format!(
+            "</table>\n<p>{} games played.</p>\n</body>\n</html>\n",
+            self.games
+        ));
+        html
+    }
+
+    /// Write `standings.csv`, `crosstable.csv`, `report.md` and `report.html`
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("standings.csv"), self.to_csv())?;
+        fs::write(dir.join("crosstable.csv"), self.crosstable_csv())?;
+        fs::write(dir.join("report.md"), self.to_markdown())?;
+        fs::write(dir.join("report.html"), self.to_html())
+    }
+
+    /// Crosstable entry as text: `x` on the diagonal, empty if never met
+    fn cell(&self, row: usize, col: usize) -> String {
+        match self.crosstable[row][col] {
+            _ if row == col => "x".to_string(),
+            Some(cell) => number(cell.points),
+            None => String::new(),
+        }
+    }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.7em; text-align: center; }
+th { background: #eee; }
+td.name { text-align: left; font-weight: bold; }
+td.win { background: #d4f4d4; }
+td.loss { background: #f8d7d7; }
+td.draw { background: #f4f0d0; }
+td.self { background: #ddd; }
+";
+
+/// `1.5` stays `1.5`, `2.0` becomes `2`
+fn number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+fn record(record: &Record) -> String {
+    format!("{}/{}/{}", record.wins, record.draws, record.losses)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rating::{BotId, RatedGame};
+    use crate::tournament::Standings;
+
+    fn results() -> TournamentResults {
+        let game = |attacker: &str, defender: &str, score: f64| {
+            RatedGame::new(BotId::new(attacker, ""), BotId::new(defender, ""), score)
+        };
+        let mut results = TournamentResults::new();
+        for game in [
+            game("A", "B", 1.0),
+            game("B", "A", 0.5),
+            game("A", "<C>", 1.0),
+            game("<C>", "A", 0.0),
+            game("B", "<C>", 0.0),
+            game("<C>", "B", 0.0),
+        ] {
+            results.add_game(game);
+        }
+        let names = ["A", "B", "<C>"].map(String::from);
+        results.set_standings(Standings::new(&names, results.games(), &[], 0.0));
+        results
+    }
+
+    #[test]
+    fn test_report_rows_and_crosstable() {
+        let report = Report::new("Test", &results());
+        let names: Vec<&str> = report.rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "<C>"]);
+
+        let a = &report.rows[0];
+        assert_eq!(a.points, 3.5);
+        assert_eq!(a.as_attacker.wins, 2);
+        assert_eq!(a.as_defender.draws, 1);
+        // 1.5 against B (1.5 points) and 2 against <C> (1 point)
+        assert_eq!(a.sonneborn_berger, 1.5 * 1.5 + 2.0 * 1.0);
+
+        assert_eq!(report.crosstable[0][2].unwrap().points, 2.0);
+        assert_eq!(report.crosstable[1][0].unwrap().points, 0.5);
+        assert!(report.crosstable[1][1].is_none());
+        assert_eq!(report.games, 6);
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let report = Report::new("Spring <Cup>", &results());
+        assert_eq!(report.to_csv().lines().count(), 4);
+        assert!(report.to_csv().contains("\n1,A,3.5,3,1,0,"));
+        assert_eq!(report.crosstable_csv().lines().nth(1), Some("A,,1.5,2"));
+        assert!(report.to_markdown().contains("| 1 | A | 3.5 | 3/1/0 |"));
+        let html = report.to_html();
+        assert!(html.contains("<h1>Spring &lt;Cup&gt;</h1>"));
+        assert!(html.contains("&lt;C&gt;"));
+        assert!(!html.contains("<C>"));
+        assert!(report.to_text().contains("Crosstable"));
+    }
+}
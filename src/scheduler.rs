@@ -0,0 +1,392 @@
+use crate::arena::{BotFactory, Match, MatchConfig, MatchResult, create_bots};
+use crate::checkpoint::{Checkpoint, ScheduleEntry};
+use crate::game::{GameError, GameState, Move, Variant};
+use crate::rating::{BotId, RatedGame};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Settings of the worker pool
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Games played at the same time; 0 uses one worker per `cpus_per_game` cores
+    pub workers: usize,
+    /// Cores reserved for each game
+    pub cpus_per_game: usize,
+    /// Print a line whenever a game finishes
+    pub verbose: bool,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            workers: 0,
+            cpus_per_game: 1,
+            verbose: false,
+        }
+    }
+}
+
+/// A game waiting to be played, bots given by their index in the scheduler
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledGame {
+    pub attacker: usize,
+    pub defender: usize,
+    pub variant: Variant,
+    /// Forced moves before the bots take over
+    pub opening: Vec<Move>,
+}
+
+impl ScheduledGame {
+    pub fn new(attacker: usize, defender: usize, variant: Variant) -> Self {
+        ScheduledGame {
+            attacker,
+            defender,
+            variant,
+            opening: Vec::new(),
+        }
+    }
+
+    pub fn with_opening(mut self, opening: Vec<Move>) -> Self {
+        self.opening = opening;
+        self
+    }
+}
+
+/// A finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameOutcome {
+    pub game: ScheduledGame,
+    pub attacker: BotId,
+    pub defender: BotId,
+    pub result: MatchResult,
+    /// 1 if the attackers won, 0.5 for a draw, 0 if the defenders won
+    pub attacker_score: f64,
+    /// Wall time the whole game took
+    pub duration: Duration,
+    /// Why the game was forfeited before the first move, if a bot could not
+    /// be created
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl GameOutcome {
+    pub fn rated_game(&self) -> RatedGame {
+        RatedGame::new(
+            self.attacker.clone(),
+            self.defender.clone(),
+            self.attacker_score,
+        )
+    }
+}
+
+/// Plays many games concurrently on a pool of worker threads
+/// Bots are created by their factory on the worker thread for every game,
+/// so no state leaks between games
+pub struct MatchScheduler {
+    bots: Vec<(String, BotFactory)>,
+    /// Versions of the bots, learned when a checkpoint needs them
+    versions: Vec<OnceLock<String>>,
+    match_config: MatchConfig,
+    config: SchedulerConfig,
+    checkpoint: Option<Mutex<Checkpoint>>,
+}
+
+impl MatchScheduler {
+    pub fn new(match_config: MatchConfig, config: SchedulerConfig) -> Self {
+        MatchScheduler {
+            bots: Vec::new(),
+            versions: Vec::new(),
+            match_config,
+            config,
+            checkpoint: None,
+        }
+    }
+
+    /// Record every game in `checkpoint` and take games it already holds
+    /// from there instead of playing them
+    pub fn set_checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.checkpoint = Some(Mutex::new(checkpoint));
+    }
+
+    /// Register a bot and return its index for `ScheduledGame`
+    pub fn add_bot(&mut self, name: String, factory: BotFactory) -> usize {
+        self.bots.push((name, factory));
+        self.versions.push(OnceLock::new());
+        self.bots.len() - 1
+    }
+
+    pub fn bot_name(&self, index: usize) -> &str {
+        &self.bots[index].0
+    }
+
+    /// Version of the bot, from one created just to ask; empty if that fails
+    fn bot_version(&self, index: usize) -> &str {
+        self.versions[index].get_or_init(|| {
+            (self.bots[index].1)().map_or(String::new(), |bot| bot.version().to_string())
+        })
+    }
+
+    pub fn bot_count(&self) -> usize {
+        self.bots.len()
+    }
+
+    pub fn match_config(&self) -> &MatchConfig {
+        &self.match_config
+    }
+
+    /// Number of games that run at the same time
+    pub fn workers(&self) -> usize {
+        if self.config.workers > 0 {
+            return self.config.workers;
+        }
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        (cores / self.config.cpus_per_game.max(1)).max(1)
+    }
+
+    /// Play all games and return their outcomes in the order of `games`,
+    /// whatever order they finish in
+    /// Fails before playing anything if an opening contains an illegal move
+    pub fn run(&self, games: Vec<ScheduledGame>) -> Result<Vec<GameOutcome>, GameError> {
+        for game in &games {
+            let mut state = GameState::new(game.variant);
+            for &mv in &game.opening {
+                state.make_move(mv)?;
+            }
+        }
+
+        let total = games.len();
+        let mut outcomes: Vec<Option<GameOutcome>> = (0..total).map(|_| None).collect();
+        // Games still to play with their position in `games` and checkpoint index
+        let mut pending = Vec::new();
+        for (index, game) in games.into_iter().enumerate() {
+            match self.schedule(&game) {
+                (_, Some(outcome)) => outcomes[index] = Some(outcome),
+                (checkpoint_index, None) => pending.push((index, checkpoint_index, game)),
+            }
+        }
+
+        let to_play = pending.len();
+        let next = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(total - to_play);
+        let pending = Mutex::new(pending.into_iter().map(Some).collect::<Vec<_>>());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers().min(to_play) {
+                let sender = sender.clone();
+                let (next, finished, pending) = (&next, &finished, &pending);
+                scope.spawn(move || {
+                    loop {
+                        let slot = next.fetch_add(1, Ordering::SeqCst);
+                        if slot >= to_play {
+                            break;
+                        }
+                        let (index, checkpoint_index, game) =
+                            pending.lock().unwrap()[slot].take().unwrap();
+                        let outcome = self.play(game);
+
+                        let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                        if self.config.verbose {
+                            let error = match &outcome.error {
+                                Some(error) => format!(", could not start {}", error),
+                                None => String::new(),
+                            };
+                            println!(
+                                "[{}/{}] {} vs {}: {}{}",
+                                done,
+                                total,
+                                outcome.attacker,
+                                outcome.defender,
+                                describe(&outcome),
+                                error
+                            );
+                        }
+                        sender.send((index, checkpoint_index, outcome)).unwrap();
+                    }
+                });
+            }
+            drop(sender);
+
+            // Saved as they finish, so an interruption loses as little as possible
+            for (index, checkpoint_index, outcome) in receiver {
+                if let (Some(checkpoint), Some(checkpoint_index)) =
+                    (&self.checkpoint, checkpoint_index)
+                    && let Err(e) = checkpoint
+                        .lock()
+                        .unwrap()
+                        .record(checkpoint_index, &outcome)
+                {
+                    eprintln!("Could not save game {}: {}", checkpoint_index + 1, e);
+                }
+                outcomes[index] = Some(outcome);
+            }
+        });
+
+        Ok(outcomes.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// Index of `game` in the checkpoint, and its outcome if it was played before
+    fn schedule(&self, game: &ScheduledGame) -> (Option<usize>, Option<GameOutcome>) {
+        let Some(checkpoint) = &self.checkpoint else {
+            return (None, None);
+        };
+        let bot_id = |index| BotId::new(self.bot_name(index), self.bot_version(index));
+        let entry = ScheduleEntry {
+            attacker: bot_id(game.attacker),
+            defender: bot_id(game.defender),
+            variant: game.variant,
+            opening: game.opening.clone(),
+        };
+        match checkpoint.lock().unwrap().schedule(entry) {
+            Ok((index, outcome)) => (Some(index), outcome),
+            Err(e) => {
+                eprintln!("Could not save the schedule: {}", e);
+                (None, None)
+            }
+        }
+    }
+
+    fn play(&self, game: ScheduledGame) -> GameOutcome {
+        let (attacker_name, attacker_factory) = &self.bots[game.attacker];
+        let (defender_name, defender_factory) = &self.bots[game.defender];
+        let start = Instant::now();
+
+        let (attacker_bot, defender_bot) = match create_bots(attacker_factory, defender_factory) {
+            Ok(bots) => bots,
+            Err((winning_side, error)) => {
+                let result = MatchResult::not_started(
+                    attacker_name,
+                    defender_name,
+                    winning_side,
+                    &game.opening,
+                );
+                return GameOutcome {
+                    attacker_score: result.attacker_score(),
+                    duration: start.elapsed(),
+                    attacker: BotId::new(attacker_name, ""),
+                    defender: BotId::new(defender_name, ""),
+                    game,
+                    result,
+                    error: Some(error),
+                };
+            }
+        };
+        let attacker = BotId::new(attacker_name, attacker_bot.version());
+        let defender = BotId::new(defender_name, defender_bot.version());
+
+        let mut game_match = Match::with_variant(
+            attacker_bot,
+            defender_bot,
+            self.match_config.clone(),
+            false,
+            game.variant,
+        );
+        game_match
+            .set_opening(&game.opening)
+            .expect("openings are checked before playing");
+        let result = game_match.play();
+
+        GameOutcome {
+            attacker_score: result.attacker_score(),
+            duration: start.elapsed(),
+            game,
+            attacker,
+            defender,
+            result,
+            error: None,
+        }
+    }
+}
+
+fn describe(outcome: &GameOutcome) -> &'static str {
+    match outcome.attacker_score {
+        s if s > 0.5 => "attackers win",
+        s if s < 0.5 => "defenders win",
+        _ => "draw",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Termination;
+    use crate::bot::{Bot, GreedyBot, RandomBot};
+
+    fn scheduler(workers: usize) -> MatchScheduler {
+        let config = MatchConfig {
+            max_moves: 60,
+            ..Default::default()
+        };
+        let mut scheduler = MatchScheduler::new(
+            config,
+            SchedulerConfig {
+                workers,
+                ..Default::default()
+            },
+        );
+        scheduler.add_bot(
+            "Greedy".to_string(),
+            Box::new(|| Ok(Box::new(GreedyBot::new("Greedy".to_string())) as Box<dyn Bot>)),
+        );
+        scheduler.add_bot(
+            "Random".to_string(),
+            Box::new(|| Ok(Box::new(RandomBot::new("Random".to_string())) as Box<dyn Bot>)),
+        );
+        scheduler
+    }
+
+    fn games() -> Vec<ScheduledGame> {
+        (0..8)
+            .map(|i| ScheduledGame::new(i % 2, 1 - i % 2, Variant::Brandubh))
+            .collect()
+    }
+
+    #[test]
+    fn test_results_are_deterministic() {
+        let serial = scheduler(1).run(games()).unwrap();
+        let parallel = scheduler(4).run(games()).unwrap();
+
+        assert_eq!(serial.len(), 8);
+        for (a, b) in serial.iter().zip(&parallel) {
+            assert_eq!(a.game, b.game);
+            assert_eq!(a.attacker_score, b.attacker_score);
+            assert_eq!(a.result.record.len(), b.result.record.len());
+        }
+        assert_eq!(parallel[1].attacker.name, "Random");
+    }
+
+    #[test]
+    fn test_illegal_opening_is_rejected() {
+        let bad = ScheduledGame::new(0, 1, Variant::Brandubh).with_opening(vec![Move::new(
+            crate::game::Position::new(0, 0),
+            crate::game::Position::new(0, 1),
+        )]);
+        assert!(scheduler(2).run(vec![bad]).is_err());
+    }
+
+    #[test]
+    fn test_failed_creation_forfeits_the_game() {
+        let mut scheduler = scheduler(2);
+        scheduler.add_bot(
+            "Broken".to_string(),
+            Box::new(|| Err("process exited".to_string())),
+        );
+        let games = vec![
+            ScheduledGame::new(2, 0, Variant::Brandubh),
+            ScheduledGame::new(0, 2, Variant::Brandubh),
+        ];
+        let outcomes = scheduler.run(games).unwrap();
+
+        assert_eq!(outcomes[0].attacker_score, 0.0);
+        assert_eq!(outcomes[1].attacker_score, 1.0);
+        for outcome in &outcomes {
+            assert_eq!(outcome.result.termination, Termination::Crash);
+            assert_eq!(outcome.result.loser(), Some("Broken"));
+            assert!(outcome.error.as_ref().unwrap().contains("process exited"));
+        }
+    }
+}
@@ -0,0 +1,771 @@
+use crate::arena::{BotFactory, MatchConfig};
+use crate::checkpoint::Checkpoint;
+use crate::game::{Player, Variant};
+use crate::opening::OpeningSuite;
+use crate::rating::RatedGame;
+use crate::report::Report;
+use crate::scheduler::{GameOutcome, MatchScheduler, ScheduledGame, SchedulerConfig};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Extra two-game sets played when a knockout pairing ends level
+const KNOCKOUT_TIEBREAK_SETS: usize = 3;
+
+/// Indices of two bots playing each other
+type Pairing = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentFormat {
+    /// Everybody plays everybody
+    RoundRobin,
+    /// A fixed number of rounds, each pairing bots with similar scores
+    Swiss { rounds: usize },
+    /// Out after losing one pairing
+    SingleElimination,
+    /// Out after losing two pairings
+    DoubleElimination,
+}
+
+/// One line of the standings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StandingsEntry {
+    pub name: String,
+    pub points: f64,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Rounds without an opponent
+    pub byes: u32,
+    /// Sum of the points of every opponent met
+    pub buchholz: f64,
+}
+
+impl StandingsEntry {
+    fn new(name: &str) -> Self {
+        StandingsEntry {
+            name: name.to_string(),
+            points: 0.0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            byes: 0,
+            buchholz: 0.0,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// Final ranking of a tournament, the same for every format
+#[derive(Debug, Clone, Default)]
+pub struct Standings {
+    entries: Vec<StandingsEntry>,
+}
+
+impl Standings {
+    /// Standings ordered by points, then Buchholz, then wins
+    /// Each bye is worth `bye_points`
+    pub fn new(names: &[String], games: &[RatedGame], byes: &[String], bye_points: f64) -> Self {
+        let mut entries: HashMap<&str, StandingsEntry> = names
+            .iter()
+            .map(|name| (name.as_str(), StandingsEntry::new(name)))
+            .collect();
+        let mut opponents: HashMap<&str, HashSet<&str>> = HashMap::new();
+
+        for game in games {
+            let sides = [
+                (&game.attacker.name, &game.defender.name, game.score),
+                (&game.defender.name, &game.attacker.name, 1.0 - game.score),
+            ];
+            for (bot, opponent, score) in sides {
+                let entry = entries
+                    .entry(bot)
+                    .or_insert_with(|| StandingsEntry::new(bot));
+                entry.points += score;
+                if score > 0.5 {
+                    entry.wins += 1;
+                } else if score < 0.5 {
+                    entry.losses += 1;
+                } else {
+                    entry.draws += 1;
+                }
+                opponents.entry(bot).or_default().insert(opponent);
+            }
+        }
+        for name in byes {
+            let entry = entries
+                .entry(name)
+                .or_insert_with(|| StandingsEntry::new(name));
+            entry.byes += 1;
+            entry.points += bye_points;
+        }
+
+        let points: HashMap<&str, f64> = entries
+            .iter()
+            .map(|(name, entry)| (*name, entry.points))
+            .collect();
+        for (name, entry) in &mut entries {
+            entry.buchholz = opponents
+                .get(name)
+                .map_or(0.0, |met| met.iter().map(|opponent| points[opponent]).sum());
+        }
+
+        let mut entries: Vec<_> = entries.into_values().collect();
+        entries.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.buchholz.total_cmp(&a.buchholz))
+                .then(b.wins.cmp(&a.wins))
+                .then(a.name.cmp(&b.name))
+        });
+        Standings { entries }
+    }
+
+    /// Reorder to a given placement, as decided by an elimination bracket
+    fn ordered(mut self, placement: &[String]) -> Self {
+        self.entries.sort_by_key(|entry| {
+            placement
+                .iter()
+                .position(|name| *name == entry.name)
+                .unwrap_or(usize::MAX)
+        });
+        self
+    }
+
+    /// Entries from first to last place
+    pub fn entries(&self) -> &[StandingsEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StandingsEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn display(&self) {
+        println!(
+            "{:<4} {:<24} {:>7} {:>5} {:>5} {:>5} {:>9}",
+            "#", "Bot", "Points", "W", "D", "L", "Buchholz"
+        );
+        for (i, entry) in self.entries.iter().enumerate() {
+            println!(
+                "{:<4} {:<24} {:>7.1} {:>5} {:>5} {:>5} {:>9.1}",
+                i + 1,
+                entry.name,
+                entry.points,
+                entry.wins,
+                entry.draws,
+                entry.losses,
+                entry.buchholz
+            );
+        }
+    }
+}
+
+pub struct Tournament {
+    scheduler: MatchScheduler,
+    variant: Variant,
+    games_per_pairing: usize,
+    openings: Option<OpeningSuite>,
+    /// Suite index of the next pairing's first opening
+    next_opening: usize,
+}
+
+impl Tournament {
+    pub fn new(config: MatchConfig, verbose: bool) -> Self {
+        Self::with_scheduler(
+            config,
+            SchedulerConfig {
+                verbose,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Tournament whose games run in parallel as set up in `scheduler`
+    pub fn with_scheduler(config: MatchConfig, scheduler: SchedulerConfig) -> Self {
+        Tournament {
+            scheduler: MatchScheduler::new(config, scheduler),
+            variant: Variant::Brandubh,
+            games_per_pairing: 2,
+            openings: None,
+            next_opening: 0,
+        }
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Start games from the suite's openings instead of the standard setup
+    /// Each opening is played twice with colors swapped; the suite also sets
+    /// the variant
+    pub fn set_openings(&mut self, suite: OpeningSuite) {
+        self.variant = suite.variant;
+        self.openings = Some(suite);
+    }
+
+    /// Games two bots play whenever they are paired, alternating roles
+    /// An even number gives both the same number of attacker games
+    pub fn set_games_per_pairing(&mut self, games: usize) {
+        self.games_per_pairing = games.max(1);
+    }
+
+    pub fn add_bot(&mut self, name: String, factory: BotFactory) {
+        self.scheduler.add_bot(name, factory);
+    }
+
+    /// Save every game to `checkpoint` as it finishes
+    /// Running the same tournament with a resumed checkpoint skips the games
+    /// it holds and ends with the same standings
+    pub fn set_checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.scheduler.set_checkpoint(checkpoint);
+    }
+
+    pub fn run(&mut self, format: TournamentFormat) -> TournamentResults {
+        match format {
+            TournamentFormat::RoundRobin => self.run_round_robin(),
+            TournamentFormat::Swiss { rounds } => self.run_swiss(rounds),
+            TournamentFormat::SingleElimination => self.run_elimination(1),
+            TournamentFormat::DoubleElimination => self.run_elimination(2),
+        }
+    }
+
+    pub fn run_round_robin(&mut self) -> TournamentResults {
+        let mut results = TournamentResults::new();
+        let count = self.scheduler.bot_count();
+        let pairings: Vec<_> = (0..count)
+            .flat_map(|i| ((i + 1)..count).map(move |j| (i, j)))
+            .collect();
+
+        self.play_pairings(&pairings, &mut results);
+        self.finish(results, None)
+    }
+
+    /// Each round pairs bots in order of their score with the best placed
+    /// opponent they have not met yet. With an odd number of bots the lowest
+    /// placed bot without a bye sits out and gets the points of a full win
+    pub fn run_swiss(&mut self, rounds: usize) -> TournamentResults {
+        let mut results = TournamentResults::new();
+        let count = self.scheduler.bot_count();
+        let mut met: HashSet<Pairing> = HashSet::new();
+        let mut had_bye = vec![false; count];
+        // Attacker games minus defender games
+        let mut role_balance = vec![0i64; count];
+
+        // Nobody can meet more than everybody else (plus one bye)
+        for _ in 0..rounds.min((count + count % 2).saturating_sub(1)) {
+            let standings = self.standings(&results);
+            let mut order: Vec<usize> = (0..count).collect();
+            order.sort_by(|&a, &b| {
+                let points = |i: usize| standings.get(self.scheduler.bot_name(i)).unwrap().points;
+                points(b).total_cmp(&points(a)).then(a.cmp(&b))
+            });
+
+            let (pairs, bye) = swiss_pairings(&order, &met, &had_bye)
+                .or_else(|| swiss_pairings(&order, &HashSet::new(), &had_bye))
+                .expect("pairing without restrictions always succeeds");
+            if let Some(bye) = bye {
+                had_bye[bye] = true;
+                results.byes.push(self.scheduler.bot_name(bye).to_string());
+            }
+
+            // The bot that attacked less so far attacks first
+            let pairs: Vec<_> = pairs
+                .into_iter()
+                .map(|(a, b)| match role_balance[a] <= role_balance[b] {
+                    true => (a, b),
+                    false => (b, a),
+                })
+                .collect();
+            for &(a, b) in &pairs {
+                met.insert(pair_key(a, b));
+                let first_games = self.games_per_pairing.div_ceil(2) as i64;
+                let second_games = (self.games_per_pairing / 2) as i64;
+                role_balance[a] += first_games - second_games;
+                role_balance[b] -= first_games - second_games;
+            }
+
+            self.play_pairings(&pairs, &mut results);
+        }
+
+        self.finish(results, None)
+    }
+
+    /// Knockout where a bot is out after `lives` lost pairings
+    /// Bots with the same number of losses play each other, best seed (order
+    /// of `add_bot`) against worst. With two lives the last bot of the
+    /// winners' bracket meets the last of the losers' bracket in the final
+    fn run_elimination(&mut self, lives: u32) -> TournamentResults {
+        let mut results = TournamentResults::new();
+        let count = self.scheduler.bot_count();
+        let mut losses = vec![0u32; count];
+        let mut eliminated = Vec::new();
+
+        loop {
+            let alive: Vec<usize> = (0..count).filter(|&i| losses[i] < lives).collect();
+            if alive.len() <= 1 {
+                eliminated.extend(alive);
+                break;
+            }
+
+            let groups: Vec<Vec<usize>> = (0..lives)
+                .map(|l| alive.iter().copied().filter(|&i| losses[i] == l).collect())
+                .filter(|group: &Vec<usize>| !group.is_empty())
+                .collect();
+            let mut pairings = Vec::new();
+            if groups.iter().all(|group| group.len() == 1) {
+                pairings.push((groups[0][0], groups[1][0]));
+            } else {
+                for mut group in groups {
+                    // The best seed waits when the group is odd
+                    if group.len() % 2 == 1 {
+                        group.remove(0);
+                    }
+                    let half = group.len() / 2;
+                    for k in 0..half {
+                        pairings.push((group[k], group[group.len() - 1 - k]));
+                    }
+                }
+            }
+
+            for (first_wins, (a, b)) in self.play_knockout(&pairings, &mut results) {
+                let loser = if first_wins { b } else { a };
+                losses[loser] += 1;
+                if losses[loser] == lives {
+                    eliminated.push(loser);
+                }
+            }
+        }
+
+        let placement: Vec<String> = eliminated
+            .iter()
+            .rev()
+            .map(|&i| self.scheduler.bot_name(i).to_string())
+            .collect();
+        self.finish(results, Some(placement))
+    }
+
+    /// Play pairings until each has a winner
+    /// Returns for each pairing whether the first bot won, with the pairing
+    fn play_knockout(
+        &mut self,
+        pairings: &[Pairing],
+        results: &mut TournamentResults,
+    ) -> Vec<(bool, Pairing)> {
+        let mut scores = self.play_pairings(pairings, results);
+        let total = self.games_per_pairing as f64;
+
+        for _ in 0..KNOCKOUT_TIEBREAK_SETS {
+            let tied: Vec<usize> = (0..pairings.len())
+                .filter(|&i| scores[i] * 2.0 == total)
+                .collect();
+            if tied.is_empty() {
+                break;
+            }
+            let tied_pairings: Vec<_> = tied.iter().map(|&i| pairings[i]).collect();
+            let extra = self.play_sets(&tied_pairings, 2, results);
+            for (&i, score) in tied.iter().zip(extra) {
+                scores[i] += score - 1.0;
+            }
+        }
+
+        // Still level: the better seed goes through
+        pairings
+            .iter()
+            .zip(scores)
+            .map(|(&(a, b), score)| {
+                let first_wins = score * 2.0 > total || (score * 2.0 == total && a < b);
+                (first_wins, (a, b))
+            })
+            .collect()
+    }
+
+    /// Play `games_per_pairing` games for every pairing, all in parallel
+    /// Returns the points of the first bot of each pairing
+    fn play_pairings(&mut self, pairings: &[Pairing], results: &mut TournamentResults) -> Vec<f64> {
+        for &(a, b) in pairings {
+            results.add_matchup(
+                self.scheduler.bot_name(a).to_string(),
+                self.scheduler.bot_name(b).to_string(),
+            );
+        }
+        self.play_sets(pairings, self.games_per_pairing, results)
+    }
+
+    fn play_sets(
+        &mut self,
+        pairings: &[Pairing],
+        games: usize,
+        results: &mut TournamentResults,
+    ) -> Vec<f64> {
+        // With an opening suite both games of a color swap share an opening
+        let mut schedule = Vec::new();
+        for &(a, b) in pairings {
+            for g in 0..games {
+                let game = match g % 2 {
+                    0 => ScheduledGame::new(a, b, self.variant),
+                    _ => ScheduledGame::new(b, a, self.variant),
+                };
+                let game = match &self.openings {
+                    Some(suite) => game.with_opening(suite.get(self.next_opening + g / 2).to_vec()),
+                    None => game,
+                };
+                schedule.push(game);
+            }
+            self.next_opening += games.div_ceil(2);
+        }
+        let outcomes = self
+            .scheduler
+            .run(schedule)
+            .expect("openings are checked when the suite is loaded");
+
+        let mut scores = vec![0.0; pairings.len()];
+        for (i, outcome) in outcomes.iter().enumerate() {
+            let first_attacks = (i % games).is_multiple_of(2);
+            scores[i / games] += match first_attacks {
+                true => outcome.attacker_score,
+                false => 1.0 - outcome.attacker_score,
+            };
+            results.add_outcome(outcome);
+        }
+        scores
+    }
+
+    fn names(&self) -> Vec<String> {
+        (0..self.scheduler.bot_count())
+            .map(|i| self.scheduler.bot_name(i).to_string())
+            .collect()
+    }
+
+    fn standings(&self, results: &TournamentResults) -> Standings {
+        Standings::new(
+            &self.names(),
+            &results.games,
+            &results.byes,
+            self.games_per_pairing as f64,
+        )
+    }
+
+    fn finish(
+        &self,
+        mut results: TournamentResults,
+        placement: Option<Vec<String>>,
+    ) -> TournamentResults {
+        let standings = self.standings(&results);
+        results.standings = match placement {
+            Some(placement) => standings.ordered(&placement),
+            None => standings,
+        };
+        results
+    }
+}
+
+fn pair_key(a: usize, b: usize) -> Pairing {
+    (a.min(b), a.max(b))
+}
+
+/// Pair `order` (best first) without repeating a pairing from `met`
+/// With an odd count the lowest placed bot that had no bye sits out
+fn swiss_pairings(
+    order: &[usize],
+    met: &HashSet<Pairing>,
+    had_bye: &[bool],
+) -> Option<(Vec<Pairing>, Option<usize>)> {
+    if order.len().is_multiple_of(2) {
+        return pair_in_order(order, met).map(|pairs| (pairs, None));
+    }
+
+    // Anybody may get a second bye once everybody had one
+    let everybody_had_bye = order.iter().all(|&i| had_bye[i]);
+    order
+        .iter()
+        .rev()
+        .filter(|&&i| everybody_had_bye || !had_bye[i])
+        .find_map(|&bye| {
+            let rest: Vec<usize> = order.iter().copied().filter(|&i| i != bye).collect();
+            pair_in_order(&rest, met).map(|pairs| (pairs, Some(bye)))
+        })
+}
+
+/// Pair the first bot with the best placed possible opponent, backtracking
+/// when the remaining bots cannot be paired
+fn pair_in_order(order: &[usize], met: &HashSet<Pairing>) -> Option<Vec<Pairing>> {
+    let Some((&first, rest)) = order.split_first() else {
+        return Some(Vec::new());
+    };
+
+    for (i, &opponent) in rest.iter().enumerate() {
+        if met.contains(&pair_key(first, opponent)) {
+            continue;
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+        if let Some(mut pairs) = pair_in_order(&remaining, met) {
+            pairs.insert(0, (first, opponent));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// Length and time usage of one game, for reports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameStats {
+    pub attacker: String,
+    pub defender: String,
+    /// Moves played by each side, not counting the opening
+    pub attacker_moves: usize,
+    pub defender_moves: usize,
+    pub attacker_time: Duration,
+    pub defender_time: Duration,
+}
+
+impl GameStats {
+    pub fn from_outcome(outcome: &GameOutcome) -> Self {
+        let attacker_times = outcome.result.think_times(Player::Attackers);
+        let defender_times = outcome.result.think_times(Player::Defenders);
+        GameStats {
+            attacker: outcome.attacker.name.clone(),
+            defender: outcome.defender.name.clone(),
+            attacker_moves: attacker_times.len(),
+            defender_moves: defender_times.len(),
+            attacker_time: attacker_times.iter().sum(),
+            defender_time: defender_times.iter().sum(),
+        }
+    }
+
+    pub fn moves(&self) -> usize {
+        self.attacker_moves + self.defender_moves
+    }
+}
+
+#[derive(Debug)]
+pub struct TournamentResults {
+    matchups: Vec<(String, String)>,
+    games: Vec<RatedGame>,
+    stats: Vec<GameStats>,
+    byes: Vec<String>,
+    standings: Standings,
+}
+
+impl TournamentResults {
+    pub fn new() -> Self {
+        TournamentResults {
+            matchups: Vec::new(),
+            games: Vec::new(),
+            stats: Vec::new(),
+            byes: Vec::new(),
+            standings: Standings::default(),
+        }
+    }
+
+    pub fn add_matchup(&mut self, bot1: String, bot2: String) {
+        self.matchups.push((bot1, bot2));
+    }
+
+    pub fn add_game(&mut self, game: RatedGame) {
+        self.games.push(game);
+    }
+
+    /// Add a played game together with its length and time usage
+    pub fn add_outcome(&mut self, outcome: &GameOutcome) {
+        self.games.push(outcome.rated_game());
+        self.stats.push(GameStats::from_outcome(outcome));
+    }
+
+    /// Pairings in the order they were made
+    pub fn matchups(&self) -> &[(String, String)] {
+        &self.matchups
+    }
+
+    /// Length and time usage of the games added with `add_outcome`
+    pub fn stats(&self) -> &[GameStats] {
+        &self.stats
+    }
+
+    /// Played games, ready for `RatingList::update`
+    pub fn games(&self) -> &[RatedGame] {
+        &self.games
+    }
+
+    /// Bots that sat out a Swiss round, once per round
+    pub fn byes(&self) -> &[String] {
+        &self.byes
+    }
+
+    pub fn standings(&self) -> &Standings {
+        &self.standings
+    }
+
+    /// Replace the standings, e.g. after adding games by hand
+    pub fn set_standings(&mut self, standings: Standings) {
+        self.standings = standings;
+    }
+
+    /// Points of `bot` against `opponent`, counting games in both roles
+    pub fn score(&self, bot: &str, opponent: &str) -> f64 {
+        self.games
+            .iter()
+            .map(|game| {
+                if game.attacker.name == bot && game.defender.name == opponent {
+                    game.score
+                } else if game.defender.name == bot && game.attacker.name == opponent {
+                    1.0 - game.score
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    /// Print the standings and crosstable, see `Report` for other formats
+    pub fn display(&self) {
+        print!("{}", Report::new("Tournament Results", self).to_text());
+    }
+}
+
+impl Default for TournamentResults {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, BotAction, GreedyBot};
+    use crate::clock::TimeInfo;
+    use crate::game::{GameState, Move};
+    use crate::rating::BotId;
+
+    /// Resigns at once, so every game against it is a quick loss
+    struct Resigner(String);
+
+    impl Bot for Resigner {
+        fn name(&self) -> &str {
+            &self.0
+        }
+
+        fn get_move(&mut self, _state: &GameState, _time_limit: Duration) -> Option<Move> {
+            None
+        }
+
+        fn get_action(&mut self, _state: &GameState, _time: &TimeInfo) -> BotAction {
+            BotAction::Resign
+        }
+    }
+
+    /// `strong` bots beat the others; bots of the same kind draw or split
+    fn tournament(strong: usize, weak: usize) -> Tournament {
+        let config = MatchConfig {
+            max_moves: 40,
+            ..Default::default()
+        };
+        let mut tournament = Tournament::new(config, false);
+        for i in 0..strong {
+            let name = format!("Strong{}", i);
+            tournament.add_bot(
+                name.clone(),
+                Box::new(move || Ok(Box::new(GreedyBot::new(name.clone())))),
+            );
+        }
+        for i in 0..weak {
+            let name = format!("Weak{}", i);
+            tournament.add_bot(
+                name.clone(),
+                Box::new(move || Ok(Box::new(Resigner(name.clone())))),
+            );
+        }
+        tournament
+    }
+
+    fn game(attacker: &str, defender: &str, score: f64) -> RatedGame {
+        RatedGame::new(BotId::new(attacker, ""), BotId::new(defender, ""), score)
+    }
+
+    #[test]
+    fn test_standings_buchholz() {
+        let names: Vec<String> = ["A", "B", "C"].iter().map(|s| s.to_string()).collect();
+        let games = [game("A", "B", 1.0), game("C", "A", 0.5)];
+        let standings = Standings::new(&names, &games, &["B".to_string()], 1.0);
+
+        let a = standings.get("A").unwrap();
+        assert_eq!((a.points, a.wins, a.draws, a.losses), (1.5, 1, 1, 0));
+        // B: 1 from the bye, C: 0.5
+        assert_eq!(a.buchholz, 1.5);
+        assert_eq!(standings.entries()[0].name, "A");
+        assert_eq!(standings.get("B").unwrap().byes, 1);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let results = tournament(1, 2).run(TournamentFormat::RoundRobin);
+        assert_eq!(results.games().len(), 6);
+        let standings = results.standings().entries();
+        assert_eq!(standings[0].name, "Strong0");
+        assert_eq!(standings[0].points, 4.0);
+    }
+
+    #[test]
+    fn test_swiss_avoids_repeats_and_balances_roles() {
+        let mut tournament = tournament(2, 3);
+        tournament.set_games_per_pairing(1);
+        let results = tournament.run(TournamentFormat::Swiss { rounds: 3 });
+
+        let mut pairs = HashSet::new();
+        for (a, b) in &results.matchups {
+            let key = (a.min(b).clone(), a.max(b).clone());
+            assert!(pairs.insert(key), "{} and {} met twice", a, b);
+        }
+        assert_eq!(results.byes().len(), 3);
+        let unique_byes: HashSet<_> = results.byes().iter().collect();
+        assert_eq!(unique_byes.len(), 3);
+
+        for name in ["Strong0", "Strong1", "Weak0", "Weak1", "Weak2"] {
+            let attacks = results
+                .games()
+                .iter()
+                .filter(|g| g.attacker.name == name)
+                .count();
+            let defends = results
+                .games()
+                .iter()
+                .filter(|g| g.defender.name == name)
+                .count();
+            assert!(attacks.abs_diff(defends) <= 1, "{} is unbalanced", name);
+        }
+        let top: Vec<_> = results.standings().entries()[..2]
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert!(top.contains(&"Strong0") && top.contains(&"Strong1"));
+    }
+
+    #[test]
+    fn test_single_elimination() {
+        let results = tournament(1, 4).run(TournamentFormat::SingleElimination);
+        assert_eq!(results.standings().entries()[0].name, "Strong0");
+        // Five bots need four knockouts
+        assert_eq!(results.matchups.len(), 4);
+    }
+
+    #[test]
+    fn test_double_elimination() {
+        let results = tournament(1, 3).run(TournamentFormat::DoubleElimination);
+        let placement: Vec<_> = results
+            .standings()
+            .entries()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+
+        // Weak bots split their games, so the better seed goes through
+        assert_eq!(placement, ["Strong0", "Weak0", "Weak1", "Weak2"]);
+        // Two winners' bracket rounds, two losers' bracket rounds and the final
+        assert_eq!(results.matchups.len(), 6);
+        assert_eq!(results.standings().entries()[0].losses, 0);
+    }
+}
@@ -1,55 +1,391 @@
 use axum::{
     Json, Router,
-    extract::State,
-    http::StatusCode,
+    body::Bytes,
+    extract::{
+        DefaultBodyLimit, Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
-use crate::bot::{Bot, GreedyBot, RandomBot};
-use crate::game::{GameState, Move, Piece, Player, Position, Variant};
+use crate::arena::{Match, MatchConfig, MatchResult, MoveRecord};
+use crate::bot::{
+    Bot, BotAction, BotOption, GreedyBot, OptionValue, RandomBot, SearchInfo, SearchInfoSender,
+    apply_options,
+};
+use crate::clock::{Clock, TimeInfo};
+use crate::config::{BotSource, TimeControlConfig};
+use crate::game::{GameResult, GameState, Move, Piece, Player, Position, Variant};
+use crate::observer::MatchObserver;
 use crate::plugin::PluginBot;
+use crate::registry::{
+    BotRegistry, DEFAULT_BOT_DIR, REGISTERED_PREFIX, RegisteredBot, RegisteredSource, RegistryError,
+};
+
+const SESSION_COOKIE: &str = "hnefatafl_session";
+/// Games nobody touched for this long are dropped
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+/// Time the bot gets for each move when its side has no time control
+const BOT_MOVE_TIME: Duration = Duration::from_secs(5);
+/// Time the analysis bot gets for a hint unless the request picks one
+const HINT_TIME: Duration = Duration::from_secs(2);
+/// Longest a hint may take, so nobody ties up a thread for long
+const MAX_HINT_TIME: Duration = Duration::from_secs(30);
+/// Pause between moves of a bot match unless the request picks one
+const MATCH_DELAY: Duration = Duration::from_secs(1);
+/// How often sockets hear how long the bot has been thinking
+const CLOCK_TICK: Duration = Duration::from_secs(1);
+/// Events a slow socket may fall behind before it gets a fresh snapshot
+const EVENT_BUFFER: usize = 64;
+/// Largest plugin library that can be uploaded
+const MAX_UPLOAD: usize = 64 * 1024 * 1024;
+/// Bot matches played at the same time unless `set_max_matches` says otherwise
+const MAX_MATCHES: usize = 8;
 
 #[derive(Clone, Debug)]
 enum BotType {
     Greedy,
     Random,
     Plugin(String), // Plugin path
+    /// Bot from the registry, by name, resolved when it was chosen
+    Registered(String, BotSource),
 }
 
+/// Every game on the server, by game ID
 #[derive(Clone)]
 pub struct AppState {
+    games: Arc<Mutex<HashMap<String, GameEntry>>>,
+    idle_timeout: Duration,
+    /// Bots uploaded through the web interface
+    bots: Arc<BotRegistry>,
+    /// Needed to upload or remove bots; uploads are off without one
+    admin_token: Option<String>,
+    /// Bot matches whose thread is still running
+    running_matches: Arc<AtomicUsize>,
+    max_matches: usize,
+}
+
+/// One of the running bot matches counted by `AppState`, freed when dropped
+struct MatchSlot(Arc<AtomicUsize>);
+
+impl Drop for MatchSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct GameEntry {
+    /// Sessions that can see the game: its creator and whoever joined it
+    sessions: Vec<String>,
+    handle: GameHandle,
+    last_used: Instant,
+}
+
+impl GameEntry {
+    /// Whether nobody used the game for longer than `timeout`
+    /// Watching it on the WebSocket counts as using it
+    fn is_idle(&mut self, timeout: Duration) -> bool {
+        if self.handle.events.receiver_count() > 0 {
+            self.last_used = Instant::now();
+            return false;
+        }
+        self.last_used.elapsed() > timeout
+    }
+}
+
+#[derive(Clone)]
+struct GameHandle {
+    id: String,
     game: Arc<Mutex<WebGame>>,
+    // Kept outside the game mutex so it can be read while the bot is thinking
+    analysis: Arc<Mutex<Option<SearchInfo>>>,
+    /// Pushed to the game's WebSockets
+    events: broadcast::Sender<GameEvent>,
+}
+
+/// What a game's WebSocket sends, one JSON message per event
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum GameEvent {
+    /// The whole game; sent on connecting and after every change
+    State(Box<GameResponse>),
+    Move {
+        player: Player,
+        mv: Move,
+        captures: Vec<Position>,
+        /// How long a bot took for the move; `None` for humans
+        think_ms: Option<u64>,
+    },
+    SearchInfo {
+        info: SearchInfo,
+    },
+    /// How long the side to move has been thinking
+    Clock {
+        player: Player,
+        elapsed_ms: u64,
+    },
+    GameOver {
+        winner: Option<String>,
+        message: String,
+    },
 }
 
 struct WebGame {
     state: GameState,
-    player_side: Player,
-    bot_type: BotType,
+    /// Where the game started, and every move since; undo replays them
+    start: GameState,
+    moves: Vec<Move>,
+    /// Sessions of the humans playing and their sides; empty when two bots
+    /// play each other and everyone just watches
+    humans: HashMap<String, Player>,
+    /// Side kept free for a second human until they join
+    open_seat: Option<Player>,
+    attacker: SideInfo,
+    defender: SideInfo,
+    /// Taken out while the bot thinks on its own thread
     bot_instance: Option<Box<dyn Bot>>,
+    /// What `bot_instance` was loaded from, to load it again after an undo
+    bot_choice: Option<BotChoice>,
+    bot_thinking: bool,
     game_over: bool,
     winner: Option<Player>,
+    /// What happened last, e.g. the bot's move
+    message: String,
+    /// Controls the speed of a bot match
+    pacing: Option<Arc<Pacing>>,
+    attacker_time: SideTime,
+    defender_time: SideTime,
+    /// Both sides' time before each move in `moves`, given back on undo
+    times_before: Vec<(SideTime, SideTime)>,
+    /// When the side to move started thinking; the server's clocks are the
+    /// ones that count
+    turn_started: Instant,
+    /// Counts turns, so stale flag watchers and bot answers can tell they
+    /// are too late
+    turn: u64,
+}
+
+/// Time limit of one side in a web game
+#[derive(Clone)]
+enum SideTime {
+    /// Humans may think forever; bots get `BOT_MOVE_TIME` without losing on time
+    Unlimited,
+    PerMove(Duration),
+    Clock(Clock),
+}
+
+#[derive(Clone)]
+struct BotChoice {
+    bot_type: BotType,
+    options: HashMap<String, OptionValue>,
+}
+
+impl BotChoice {
+    /// Load the chosen bot on a blocking thread; plugins and processes can
+    /// take a while to start, which must not stall the runtime or a game
+    async fn load(&self) -> Result<Box<dyn Bot>, String> {
+        let choice = self.clone();
+        tokio::task::spawn_blocking(move || load_bot(&choice.bot_type, &choice.options))
+            .await
+            .unwrap_or_else(|_| Err("Loading the bot crashed".to_string()))
+    }
+}
+
+/// Who plays a side and how long they have been thinking
+#[derive(Serialize, Clone, Default)]
+struct SideInfo {
+    name: String,
+    /// Think time of the side's last move, if a bot made it
+    last_think_ms: Option<u64>,
+    total_think_ms: u64,
+}
+
+/// Delay, pause and single steps between the moves of a bot match
+/// The match thread waits in `wait_turn` before each bot starts thinking
+struct Pacing {
+    state: Mutex<PacingState>,
+    changed: Condvar,
+}
+
+struct PacingState {
+    delay: Duration,
+    paused: bool,
+    /// Turns allowed to start while paused
+    steps: usize,
+    /// Nobody watches any more; the match ends before the next move
+    stopped: bool,
+}
+
+#[derive(Serialize, Clone)]
+struct PacingResponse {
+    delay_ms: u64,
+    paused: bool,
+}
+
+/// Plays the moves of a `Match` into a web game
+struct WebObserver {
+    handle: GameHandle,
+    /// For the clock ticks; the match itself runs on its own thread
+    runtime: tokio::runtime::Handle,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct NewGameRequest {
     variant: String,
     player_side: String,
+    #[serde(default)]
     bot_type: String,
+    /// Leave the other side to a second human, who joins with the game ID
+    #[serde(default)]
+    vs_human: bool,
+    /// Start here instead of the variant's starting position
+    position: Option<PositionSetup>,
+    /// Time controls; a side without one has no time limit
+    attacker_time: Option<TimeControlConfig>,
+    defender_time: Option<TimeControlConfig>,
+    #[serde(default)]
+    bot_options: HashMap<String, OptionValue>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct GameResponse {
+    id: String,
     board: Vec<Vec<String>>,
     current_player: String,
     legal_moves: Vec<MoveResponse>,
+    /// The bot is choosing its move; poll until it is done
+    bot_thinking: bool,
     game_over: bool,
     winner: Option<String>,
     variant: String,
     message: String,
+    analysis: Option<SearchInfo>,
+    /// A human plays against a bot, so moves can be taken back
+    vs_bot: bool,
+    /// The creator waits for a second human to join
+    waiting_for_opponent: bool,
+    move_count: usize,
+    attacker: SideInfo,
+    defender: SideInfo,
+    /// Set when two bots play each other
+    pacing: Option<PacingResponse>,
+    /// Time left for each side's next move, `None` without a time limit
+    attacker_time_ms: Option<u64>,
+    defender_time_ms: Option<u64>,
+    /// The clock of `current_player` is running
+    clock_running: bool,
+}
+
+/// Two bots play each other while the session watches
+#[derive(Serialize, Deserialize)]
+pub struct NewMatchRequest {
+    variant: String,
+    attacker: String,
+    defender: String,
+    #[serde(default)]
+    attacker_options: HashMap<String, OptionValue>,
+    #[serde(default)]
+    defender_options: HashMap<String, OptionValue>,
+    /// Pause between moves; one second by default
+    delay_ms: Option<u64>,
+    /// Time limit for each move; five seconds by default
+    time_ms: Option<u64>,
+    /// Start paused, e.g. to step through the game
+    #[serde(default)]
+    paused: bool,
+}
+
+/// Ask an analysis bot for the best move without playing it
+#[derive(Serialize, Deserialize)]
+pub struct HintRequest {
+    bot_type: String,
+    #[serde(default)]
+    bot_options: HashMap<String, OptionValue>,
+    /// Thinking time; two seconds by default
+    time_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct HintResponse {
+    bot: String,
+    /// `None` if the bot would rather resign
+    mv: Option<Move>,
+    /// The last search info the bot reported
+    info: Option<SearchInfo>,
+    /// Position the hint is for; the game may have moved on since
+    move_count: usize,
+    think_ms: u64,
+}
+
+/// A board as sent by the editor: rows of `"A"`, `"D"`, `"K"` or `"."`
+#[derive(Serialize, Deserialize)]
+pub struct PositionSetup {
+    board: Vec<Vec<String>>,
+    to_move: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionRequest {
+    variant: String,
+    #[serde(flatten)]
+    position: PositionSetup,
+}
+
+#[derive(Deserialize)]
+struct VariantQuery {
+    variant: String,
+}
+
+/// A checked position, ready to start a game from
+#[derive(Serialize)]
+pub struct PositionResponse {
+    variant: String,
+    board: Vec<Vec<String>>,
+    to_move: String,
+    legal_moves: usize,
+}
+
+/// The game as seen by a human who joined it
+#[derive(Serialize)]
+pub struct JoinResponse {
+    player_side: String,
+    #[serde(flatten)]
+    game: GameResponse,
+}
+
+#[derive(Debug, Error)]
+enum JoinError {
+    #[error("Game not found")]
+    NotFound,
+    #[error("Both sides of this game are taken")]
+    Full,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ControlAction {
+    Pause,
+    Resume,
+    /// Let one more move be played while paused
+    Step,
+}
+
+#[derive(Deserialize)]
+pub struct ControlRequest {
+    action: Option<ControlAction>,
+    delay_ms: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -70,18 +406,169 @@ pub struct MoveResponse {
 
 impl AppState {
     pub fn new() -> Self {
-        let game = WebGame {
-            state: GameState::new(Variant::Brandubh),
-            player_side: Player::Defenders,
-            bot_type: BotType::Greedy,
-            bot_instance: None,
-            game_over: false,
-            winner: None,
-        };
+        Self::with_idle_timeout(IDLE_TIMEOUT)
+    }
+
+    pub fn with_idle_timeout(idle_timeout: Duration) -> Self {
         AppState {
-            game: Arc::new(Mutex::new(game)),
+            games: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout,
+            bots: Arc::new(BotRegistry::new(DEFAULT_BOT_DIR)),
+            admin_token: None,
+            running_matches: Arc::new(AtomicUsize::new(0)),
+            max_matches: MAX_MATCHES,
         }
     }
+
+    /// Limit the bot matches played at the same time; each takes a thread
+    /// and the CPU its bots think with
+    pub fn set_max_matches(&mut self, max_matches: usize) {
+        self.max_matches = max_matches;
+    }
+
+    /// A slot for a new bot match, unless `max_matches` are running
+    fn match_slot(&self) -> Option<MatchSlot> {
+        self.running_matches
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.max_matches).then_some(n + 1)
+            })
+            .ok()?;
+        Some(MatchSlot(self.running_matches.clone()))
+    }
+
+    pub fn set_bot_registry(&mut self, registry: BotRegistry) {
+        self.bots = Arc::new(registry);
+    }
+
+    /// Allow uploading bots with `Authorization: Bearer <token>`
+    pub fn set_admin_token(&mut self, token: impl Into<String>) {
+        self.admin_token = Some(token.into());
+    }
+
+    /// What a bot type string from a request stands for; `bot:<id>` is
+    /// looked up in the registry and `plugin:<path>` in `plugin_libraries`
+    fn bot_type(&self, bot_type: &str) -> Result<BotType, String> {
+        match bot_type.strip_prefix(REGISTERED_PREFIX) {
+            Some(id) => {
+                let bot = self.bots.get(id).map_err(|e| e.to_string())?;
+                let source = self.bots.source(id).map_err(|e| e.to_string())?;
+                Ok(BotType::Registered(bot.name, source))
+            }
+            None => create_bot(bot_type),
+        }
+    }
+
+    /// Load a bot by its type string and apply the chosen options
+    async fn load_bot(
+        &self,
+        bot_type: &str,
+        options: &HashMap<String, OptionValue>,
+    ) -> Result<Box<dyn Bot>, String> {
+        let choice = BotChoice {
+            bot_type: self.bot_type(bot_type)?,
+            options: options.clone(),
+        };
+        choice.load().await
+    }
+
+    /// Check the request's admin token before a change to the registry
+    /// Returns the error to answer with if it is missing or wrong
+    fn unauthorized(&self, headers: &HeaderMap) -> Option<Response> {
+        let Some(expected) = &self.admin_token else {
+            let message = "Uploading bots is disabled on this server";
+            return Some(error_response(StatusCode::FORBIDDEN, message));
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match given {
+            Some(given) if same_token(given, expected) => None,
+            _ => Some(error_response(
+                StatusCode::UNAUTHORIZED,
+                "A valid admin token is required",
+            )),
+        }
+    }
+
+    /// Games currently kept, expired ones included until `expire_idle` runs
+    pub fn game_count(&self) -> usize {
+        self.games.lock().unwrap().len()
+    }
+
+    /// Drop games that were idle for longer than the timeout
+    /// Returns how many were dropped
+    pub fn expire_idle(&self) -> usize {
+        let mut games = self.games.lock().unwrap();
+        let before = games.len();
+        games.retain(|_, entry| {
+            let keep = !entry.is_idle(self.idle_timeout);
+            if !keep {
+                entry.handle.close();
+            }
+            keep
+        });
+        before - games.len()
+    }
+
+    fn insert(&self, session: &str, handle: GameHandle) {
+        let entry = GameEntry {
+            sessions: vec![session.to_string()],
+            handle: handle.clone(),
+            last_used: Instant::now(),
+        };
+        self.games.lock().unwrap().insert(handle.id, entry);
+    }
+
+    /// The game `id` if it belongs to `session` and has not expired
+    fn get(&self, id: &str, session: Option<&str>) -> Option<GameHandle> {
+        let mut games = self.games.lock().unwrap();
+        let entry = games.get_mut(id)?;
+        if entry.is_idle(self.idle_timeout) {
+            entry.handle.close();
+            games.remove(id);
+            return None;
+        }
+        if !entry.sessions.iter().any(|s| Some(s.as_str()) == session) {
+            return None;
+        }
+        entry.last_used = Instant::now();
+        Some(entry.handle.clone())
+    }
+
+    /// Take the open side of game `id` for `session`
+    /// A session that already plays in the game gets its side back
+    fn join(&self, id: &str, session: &str) -> Result<(GameHandle, Player), JoinError> {
+        let mut games = self.games.lock().unwrap();
+        let entry = games.get_mut(id).ok_or(JoinError::NotFound)?;
+        if entry.is_idle(self.idle_timeout) {
+            entry.handle.close();
+            games.remove(id);
+            return Err(JoinError::NotFound);
+        }
+
+        let handle = entry.handle.clone();
+        let mut game = handle.game.lock().unwrap();
+        let side = match game.humans.get(session) {
+            Some(&side) => side,
+            None => {
+                let side = game.open_seat.take().ok_or(JoinError::Full)?;
+                game.humans.insert(session.to_string(), side);
+                *game.side_mut(side) = SideInfo::new("Guest");
+                game.message = format!(
+                    "Opponent joined. {} to move",
+                    player_to_string(game.state.current_player())
+                );
+                start_turn(&handle, &mut game);
+                handle.publish_state(&game);
+                entry.sessions.push(session.to_string());
+                side
+            }
+        };
+        drop(game);
+        entry.last_used = Instant::now();
+        Ok((handle, side))
+    }
 }
 
 impl Default for AppState {
@@ -90,6 +577,414 @@ impl Default for AppState {
     }
 }
 
+impl GameHandle {
+    fn new(game: WebGame) -> Self {
+        GameHandle {
+            id: random_id(),
+            game: Arc::new(Mutex::new(game)),
+            analysis: Arc::new(Mutex::new(None)),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    fn publish(&self, event: GameEvent) {
+        // Nobody watching is fine
+        let _ = self.events.send(event);
+    }
+
+    /// Tell the sockets about the game as it is now, and about its end
+    fn publish_state(&self, game: &WebGame) {
+        self.publish(GameEvent::State(Box::new(game_response(self, game))));
+        if game.game_over {
+            self.publish(GameEvent::GameOver {
+                winner: game.winner.map(player_to_string),
+                message: game.message.clone(),
+            });
+        }
+    }
+
+    fn response(&self) -> GameResponse {
+        game_response(self, &self.game.lock().unwrap())
+    }
+
+    /// The game was dropped; end its bot match instead of playing on
+    fn close(&self) {
+        if let Some(pacing) = &self.game.lock().unwrap().pacing {
+            pacing.stop();
+        }
+    }
+}
+
+impl WebGame {
+    fn new(state: GameState) -> Self {
+        WebGame {
+            start: state.clone(),
+            state,
+            moves: Vec::new(),
+            humans: HashMap::new(),
+            open_seat: None,
+            attacker: SideInfo::default(),
+            defender: SideInfo::default(),
+            bot_instance: None,
+            bot_choice: None,
+            bot_thinking: false,
+            game_over: false,
+            winner: None,
+            message: String::new(),
+            pacing: None,
+            attacker_time: SideTime::Unlimited,
+            defender_time: SideTime::Unlimited,
+            times_before: Vec::new(),
+            turn_started: Instant::now(),
+            turn: 0,
+        }
+    }
+
+    fn side(&self, player: Player) -> &SideInfo {
+        match player {
+            Player::Attackers => &self.attacker,
+            Player::Defenders => &self.defender,
+        }
+    }
+
+    fn side_mut(&mut self, player: Player) -> &mut SideInfo {
+        match player {
+            Player::Attackers => &mut self.attacker,
+            Player::Defenders => &mut self.defender,
+        }
+    }
+
+    fn time_mut(&mut self, player: Player) -> &mut SideTime {
+        match player {
+            Player::Attackers => &mut self.attacker_time,
+            Player::Defenders => &mut self.defender_time,
+        }
+    }
+
+    fn clock_running(&self) -> bool {
+        !self.game_over && self.open_seat.is_none()
+    }
+
+    /// How long `player` may still take for their next move
+    /// Only the side to move uses up time
+    fn time_left(&self, player: Player) -> Option<Duration> {
+        let elapsed = if self.clock_running() && self.state.current_player() == player {
+            self.turn_started.elapsed()
+        } else {
+            Duration::ZERO
+        };
+        let limit = match player {
+            Player::Attackers => &self.attacker_time,
+            Player::Defenders => &self.defender_time,
+        }
+        .limit()?;
+        Some(limit.saturating_sub(elapsed))
+    }
+
+    /// What the bot playing `player` is told about the clocks
+    fn time_info(&self, player: Player) -> TimeInfo {
+        let (own, opponent) = match player {
+            Player::Attackers => (&self.attacker_time, &self.defender_time),
+            Player::Defenders => (&self.defender_time, &self.attacker_time),
+        };
+        match own {
+            SideTime::Unlimited => TimeInfo::per_move(BOT_MOVE_TIME),
+            SideTime::PerMove(limit) => TimeInfo::per_move(*limit),
+            SideTime::Clock(clock) => TimeInfo {
+                opponent_remaining: opponent.limit().unwrap_or(clock.remaining()),
+                ..TimeInfo::from_clocks(clock, clock)
+            },
+        }
+    }
+
+    /// Stop the clock of the side to move, who just moved
+    /// Returns false if their time had already run out; otherwise the time
+    /// from before is kept for an undo
+    fn stop_clock(&mut self) -> bool {
+        let before = (self.attacker_time.clone(), self.defender_time.clone());
+        let elapsed = self.turn_started.elapsed();
+        let in_time = match self.time_mut(self.state.current_player()) {
+            SideTime::Unlimited => true,
+            SideTime::PerMove(limit) => elapsed <= *limit,
+            SideTime::Clock(clock) => clock.charge(elapsed),
+        };
+        if in_time {
+            self.times_before.push(before);
+        }
+        in_time
+    }
+
+    /// The side to move lost on time
+    fn flag(&mut self) {
+        let player = self.state.current_player();
+        self.game_over = true;
+        self.winner = Some(player.opponent());
+        self.message = format!("{} lost on time", player_to_string(player));
+    }
+
+    /// End the game if the last move decided it, with the winner the rules
+    /// name; after a threefold repetition that is the attackers, whoever
+    /// made the last move. Returns whether the game is over
+    fn end_if_decided(&mut self) -> bool {
+        let Some(result) = self.state.result() else {
+            return false;
+        };
+        self.winner = match result {
+            GameResult::AttackersWin => Some(Player::Attackers),
+            GameResult::DefendersWin => Some(Player::Defenders),
+            GameResult::Draw => None,
+        };
+        self.game_over = true;
+        true
+    }
+
+    /// The side to move loses for breaking the rules
+    /// Returns the message, naming the reason and the winner
+    fn forfeit(&mut self, reason: &str) -> String {
+        let winner = self.state.current_player().opponent();
+        self.game_over = true;
+        self.winner = Some(winner);
+        format!("{}, {} win", reason, player_to_string(winner))
+    }
+
+    /// Side that played move number `index`, counting from 0
+    fn mover(&self, index: usize) -> Player {
+        if index.is_multiple_of(2) {
+            self.start.current_player()
+        } else {
+            self.start.current_player().opponent()
+        }
+    }
+
+    /// Side played by the human of `session`, if any
+    fn side_of(&self, session: Option<&str>) -> Option<Player> {
+        session.and_then(|session| self.humans.get(session).copied())
+    }
+
+    /// Is it a human's turn to move, with both players present?
+    fn human_to_move(&self) -> bool {
+        let to_move = self.state.current_player();
+        !self.game_over
+            && !self.bot_thinking
+            && self.open_seat.is_none()
+            && self.humans.values().any(|&side| side == to_move)
+    }
+}
+
+impl SideTime {
+    fn from_config(config: Option<&TimeControlConfig>) -> Self {
+        let Some(config) = config else {
+            return SideTime::Unlimited;
+        };
+        match (config.time_control(), config.per_move_ms) {
+            (Some(time_control), _) => SideTime::Clock(Clock::new(time_control)),
+            (None, Some(per_move)) => SideTime::PerMove(Duration::from_millis(per_move)),
+            (None, None) => SideTime::Unlimited,
+        }
+    }
+
+    /// Longest the next move may take
+    fn limit(&self) -> Option<Duration> {
+        match self {
+            SideTime::Unlimited => None,
+            SideTime::PerMove(limit) => Some(*limit),
+            SideTime::Clock(clock) => Some(clock.available()),
+        }
+    }
+}
+
+impl SideInfo {
+    fn new(name: &str) -> Self {
+        SideInfo {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn add_think_time(&mut self, think_time: Duration) {
+        let ms = think_time.as_millis() as u64;
+        self.last_think_ms = Some(ms);
+        self.total_think_ms += ms;
+    }
+}
+
+impl Pacing {
+    fn new(delay: Duration, paused: bool) -> Self {
+        Pacing {
+            state: Mutex::new(PacingState {
+                delay,
+                paused,
+                steps: 0,
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Block until the next turn may start: after the delay, or after a
+    /// step or resume while paused
+    fn wait_turn(&self) {
+        let start = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.stopped {
+                return;
+            }
+            if state.steps > 0 {
+                state.steps -= 1;
+                return;
+            }
+            if state.paused {
+                state = self.changed.wait(state).unwrap();
+                continue;
+            }
+            let left = state.delay.saturating_sub(start.elapsed());
+            if left.is_zero() {
+                return;
+            }
+            state = self.changed.wait_timeout(state, left).unwrap().0;
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut PacingState)) {
+        change(&mut self.state.lock().unwrap());
+        self.changed.notify_all();
+    }
+
+    fn control(&self, action: Option<ControlAction>, delay: Option<Duration>) {
+        self.update(|state| {
+            match action {
+                Some(ControlAction::Pause) => state.paused = true,
+                Some(ControlAction::Resume) => state.paused = false,
+                Some(ControlAction::Step) => state.steps += 1,
+                None => {}
+            }
+            if let Some(delay) = delay {
+                state.delay = delay;
+            }
+        });
+    }
+
+    fn stop(&self) {
+        self.update(|state| state.stopped = true);
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.state.lock().unwrap().stopped
+    }
+
+    fn response(&self) -> PacingResponse {
+        let state = self.state.lock().unwrap();
+        PacingResponse {
+            delay_ms: state.delay.as_millis() as u64,
+            paused: state.paused,
+        }
+    }
+}
+
+impl MatchObserver for WebObserver {
+    fn game_start(
+        &mut self,
+        attacker: &str,
+        defender: &str,
+        _opening: &[Move],
+        _state: &GameState,
+    ) {
+        let mut game = self.handle.game.lock().unwrap();
+        game.message = format!("{} vs {}", attacker, defender);
+        self.handle.publish_state(&game);
+    }
+
+    fn turn_start(&mut self, player: Player, state: &GameState, _time: &TimeInfo) {
+        let pacing = self.handle.game.lock().unwrap().pacing.clone();
+        if let Some(pacing) = pacing {
+            pacing.wait_turn();
+        }
+
+        let mut game = self.handle.game.lock().unwrap();
+        game.bot_thinking = true;
+        game.message = format!("{} is thinking...", game.side(player).name);
+        self.handle.publish_state(&game);
+        self.runtime
+            .spawn(tick_clock(self.handle.clone(), state.move_count()));
+    }
+
+    fn should_stop(&mut self) -> bool {
+        let game = self.handle.game.lock().unwrap();
+        game.pacing
+            .as_ref()
+            .is_some_and(|pacing| pacing.is_stopped())
+    }
+
+    fn search_info(&mut self, _player: Player, info: &SearchInfo) {
+        *self.handle.analysis.lock().unwrap() = Some(info.clone());
+        self.handle
+            .publish(GameEvent::SearchInfo { info: info.clone() });
+    }
+
+    fn move_played(&mut self, record: &MoveRecord, captures: &[Position], state: &GameState) {
+        let mut game = self.handle.game.lock().unwrap();
+        game.state = state.clone();
+        game.moves.push(record.mv);
+        game.bot_thinking = false;
+        game.side_mut(record.player)
+            .add_think_time(record.think_time);
+        game.message = format!("{} played {}", game.side(record.player).name, record.mv);
+        self.handle.publish(GameEvent::Move {
+            player: record.player,
+            mv: record.mv,
+            captures: captures.to_vec(),
+            think_ms: Some(record.think_time.as_millis() as u64),
+        });
+        self.handle.publish_state(&game);
+    }
+
+    fn game_end(&mut self, result: &MatchResult, state: &GameState) {
+        let mut game = self.handle.game.lock().unwrap();
+        game.state = state.clone();
+        game.bot_thinking = false;
+        game.game_over = true;
+        game.winner = result.winning_side;
+        game.message = result.to_string();
+        self.handle.publish_state(&game);
+    }
+}
+
+/// 128 random bits as hex, for game and session IDs
+fn random_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// Compare tokens in time that does not depend on where they differ
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The session ID from the request's cookie, if it has one
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+fn game_not_found() -> Response {
+    error_response(StatusCode::NOT_FOUND, "Game not found")
+}
+
 fn piece_to_string(piece: Option<Piece>) -> String {
     match piece {
         None => ".".to_string(),
@@ -99,130 +994,663 @@ fn piece_to_string(piece: Option<Piece>) -> String {
     }
 }
 
-fn player_to_string(player: Player) -> String {
-    match player {
-        Player::Attackers => "Attackers".to_string(),
-        Player::Defenders => "Defenders".to_string(),
+fn player_to_string(player: Player) -> String {
+    match player {
+        Player::Attackers => "Attackers".to_string(),
+        Player::Defenders => "Defenders".to_string(),
+    }
+}
+
+fn string_to_player(s: &str) -> Player {
+    match s.to_lowercase().as_str() {
+        "attackers" => Player::Attackers,
+        "defenders" => Player::Defenders,
+        _ => Player::Defenders,
+    }
+}
+
+fn string_to_variant(s: &str) -> Variant {
+    match s.to_lowercase().as_str() {
+        "copenhagen" => Variant::Copenhagen,
+        "brandubh" => Variant::Brandubh,
+        _ => Variant::Brandubh,
+    }
+}
+
+fn create_bot(bot_type: &str) -> Result<BotType, String> {
+    if let Some(path) = bot_type.strip_prefix("plugin:") {
+        if !plugin_libraries().iter().any(|plugin| plugin.path == path) {
+            return Err(format!(
+                "Unknown plugin {}; pick one from the bot list",
+                path
+            ));
+        }
+        return Ok(BotType::Plugin(path.to_string()));
+    }
+    match bot_type.to_lowercase().as_str() {
+        "greedy" => Ok(BotType::Greedy),
+        "random" => Ok(BotType::Random),
+        _ => Ok(BotType::Greedy),
+    }
+}
+
+fn get_bot_instance(bot_type: &BotType) -> Result<Box<dyn Bot>, String> {
+    match bot_type {
+        BotType::Greedy => Ok(Box::new(GreedyBot::new("Greedy Bot".to_string()))),
+        BotType::Random => Ok(Box::new(RandomBot::new("Random Bot".to_string()))),
+        BotType::Plugin(path) => PluginBot::load(path).map(|bot| Box::new(bot) as Box<dyn Bot>),
+        BotType::Registered(name, source) => source.create(name),
+    }
+}
+
+/// Let the bot choose its move on a blocking thread, so neither the async
+/// runtime nor the game mutex is held while it thinks
+/// The move endpoint returns right away; clients hear about the bot's move
+/// on the game's WebSocket, or poll until `bot_thinking` clears
+fn start_bot_turn(handle: &GameHandle, game: &mut WebGame) {
+    let Some(mut bot) = game.bot_instance.take() else {
+        game.message = "Bot instance not found".to_string();
+        return;
+    };
+    let state = game.state.clone();
+    let time = game.time_info(state.current_player());
+    let turn = game.turn;
+    game.bot_thinking = true;
+    game.message = "Bot is thinking...".to_string();
+
+    let handle = handle.clone();
+    tokio::spawn(tick_clock(handle.clone(), state.move_count()));
+    tokio::task::spawn_blocking(move || {
+        let start = Instant::now();
+        let action = panic::catch_unwind(AssertUnwindSafe(|| bot.get_action(&state, &time)));
+        let think_time = start.elapsed();
+
+        let mut game = handle.game.lock().unwrap();
+        game.bot_thinking = false;
+        if game.turn != turn || game.game_over {
+            // Flagged or otherwise ended while thinking; the answer comes too late
+            game.bot_instance = Some(bot);
+            return;
+        }
+        match action {
+            Ok(action) => {
+                game.bot_instance = Some(bot);
+                game.side_mut(state.current_player())
+                    .add_think_time(think_time);
+                game.message = apply_bot_action(&handle, &mut game, action, think_time);
+            }
+            Err(_) => {
+                game.game_over = true;
+                game.winner = Some(state.current_player().opponent());
+                game.message = "Bot crashed".to_string();
+            }
+        }
+        handle.publish_state(&game);
+    });
+}
+
+/// Start the clock of the side to move
+/// Without a move in time a watcher flags the side, even if nobody asks
+fn start_turn(handle: &GameHandle, game: &mut WebGame) {
+    game.turn_started = Instant::now();
+    game.turn += 1;
+    if !game.clock_running() {
+        return;
+    }
+    if let Some(left) = game.time_left(game.state.current_player()) {
+        tokio::spawn(watch_flag(handle.clone(), game.turn, left));
+    }
+}
+
+async fn watch_flag(handle: GameHandle, turn: u64, mut left: Duration) {
+    loop {
+        tokio::time::sleep(left).await;
+        let mut game = handle.game.lock().unwrap();
+        if game.turn != turn || !game.clock_running() {
+            return;
+        }
+        match game.time_left(game.state.current_player()) {
+            Some(Duration::ZERO) => {
+                game.flag();
+                handle.publish_state(&game);
+                return;
+            }
+            Some(still_left) => left = still_left,
+            None => return,
+        }
+    }
+}
+
+/// Send `Clock` events while the bot thinks about move `move_count + 1`
+async fn tick_clock(handle: GameHandle, move_count: usize) {
+    let start = Instant::now();
+    loop {
+        tokio::time::sleep(CLOCK_TICK).await;
+        let player = {
+            let game = handle.game.lock().unwrap();
+            if !game.bot_thinking || game.state.move_count() != move_count {
+                return;
+            }
+            game.state.current_player()
+        };
+        handle.publish(GameEvent::Clock {
+            player,
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        });
+    }
+}
+
+/// Apply the bot's answer to the game
+fn apply_bot_action(
+    handle: &GameHandle,
+    game: &mut WebGame,
+    action: BotAction,
+    think_time: Duration,
+) -> String {
+    match action {
+        // Web games have no way to accept a draw, so an offer is just a move
+        BotAction::Move(bot_move) | BotAction::OfferDraw(bot_move) => {
+            let player = game.state.current_player();
+            if !game.state.legal_moves(player).contains(&bot_move) {
+                return game.forfeit(&format!("Bot played the illegal move {}", bot_move));
+            }
+            if !game.stop_clock() {
+                game.flag();
+                return game.message.clone();
+            }
+            let captures = game
+                .state
+                .make_move_with_captures(bot_move)
+                .expect("the move is legal");
+            game.moves.push(bot_move);
+            handle.publish(GameEvent::Move {
+                player,
+                mv: bot_move,
+                captures,
+                think_ms: Some(think_time.as_millis() as u64),
+            });
+
+            // Notify bot of its own move
+            if let Some(bot) = game.bot_instance.as_mut() {
+                bot.notify_move(bot_move);
+            }
+
+            game.end_if_decided();
+            start_turn(handle, game);
+            format!("Bot played: {} -> {}", bot_move.from, bot_move.to)
+        }
+        BotAction::Resign => {
+            game.game_over = true;
+            game.winner = Some(game.state.current_player().opponent());
+            "Bot resigned".to_string()
+        }
+        // The human never offers a draw in web games
+        BotAction::AcceptDraw => game.forfeit("Bot accepted a draw that was not offered"),
+        BotAction::Forfeit => {
+            game.game_over = true;
+            game.winner = Some(game.state.current_player().opponent());
+            "Bot crashed".to_string()
+        }
+    }
+}
+
+/// Load a bot and apply the chosen options
+fn load_bot(
+    bot_type: &BotType,
+    options: &HashMap<String, OptionValue>,
+) -> Result<Box<dyn Bot>, String> {
+    let mut bot = get_bot_instance(bot_type)?;
+    apply_options(bot.as_mut(), options).map_err(|e| e.to_string())?;
+    Ok(bot)
+}
+
+/// The caller's session, or a new one if the request has no cookie
+fn session_or_new(headers: &HeaderMap) -> (String, bool) {
+    match session_id(headers) {
+        Some(session) => (session, false),
+        None => (random_id(), true),
+    }
+}
+
+/// Register the game under the session and answer with its state, setting
+/// the cookie for a new session
+fn game_created(
+    app_state: &AppState,
+    handle: &GameHandle,
+    session: &str,
+    new_session: bool,
+) -> Response {
+    app_state.insert(session, handle.clone());
+    with_session_cookie(
+        Json(handle.response()).into_response(),
+        session,
+        new_session,
+    )
+}
+
+fn with_session_cookie(mut response: Response, session: &str, new_session: bool) -> Response {
+    if new_session {
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax",
+            SESSION_COOKIE, session
+        );
+        let cookie = HeaderValue::from_str(&cookie).expect("IDs are valid header values");
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+/// Make a loaded bot the game's bot for `bot_side` and tell it the moves
+/// so far
+fn attach_bot(handle: &GameHandle, game: &mut WebGame, mut bot: Box<dyn Bot>, bot_side: Player) {
+    let analysis = handle.analysis.clone();
+    let events = handle.events.clone();
+    bot.set_search_info_sender(SearchInfoSender::new(move |info| {
+        *analysis.lock().unwrap() = Some(info.clone());
+        let _ = events.send(GameEvent::SearchInfo { info });
+    }));
+
+    // Initialize the bot with game_start
+    bot.game_start(bot_side);
+    for &mv in &game.moves {
+        bot.notify_move(mv);
+    }
+    game.side_mut(bot_side).name = bot.name().to_string();
+    game.bot_instance = Some(bot);
+}
+
+/// Start a game for the caller's session, creating the session if needed
+#[axum::debug_handler]
+async fn create_game(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<NewGameRequest>,
+) -> Response {
+    let variant = string_to_variant(&req.variant);
+    let player_side = string_to_player(&req.player_side);
+    let state = match &req.position {
+        Some(position) => match setup_position(variant, position) {
+            Ok(state) => state,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+        },
+        None => GameState::new(variant),
+    };
+    for (side, time) in [
+        ("Attacker", &req.attacker_time),
+        ("Defender", &req.defender_time),
+    ] {
+        if let Some(Err(e)) = time.as_ref().map(TimeControlConfig::validate) {
+            let message = format!("{} time: {}", side, e);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    }
+    let (session, new_session) = session_or_new(&headers);
+
+    let handle = GameHandle::new(WebGame::new(state));
+    {
+        let mut game = handle.game.lock().unwrap();
+        game.humans.insert(session.clone(), player_side);
+        game.attacker_time = SideTime::from_config(req.attacker_time.as_ref());
+        game.defender_time = SideTime::from_config(req.defender_time.as_ref());
+
+        if req.vs_human {
+            *game.side_mut(player_side) = SideInfo::new("Host");
+            game.open_seat = Some(player_side.opponent());
+            game.message = "Waiting for an opponent to join".to_string();
+            drop(game);
+            return game_created(&app_state, &handle, &session, new_session);
+        }
+        *game.side_mut(player_side) = SideInfo::new("You");
+    }
+
+    // Create the bot without holding the game's lock, then initialize it
+    let choice = app_state.bot_type(&req.bot_type).map(|bot_type| BotChoice {
+        bot_type,
+        options: req.bot_options,
+    });
+    let bot = match &choice {
+        Ok(choice) => choice.load().await,
+        Err(e) => Err(e.clone()),
+    };
+    {
+        let mut game = handle.game.lock().unwrap();
+        game.bot_choice = choice.ok();
+        match bot {
+            Ok(bot) => {
+                attach_bot(&handle, &mut game, bot, player_side.opponent());
+                start_turn(&handle, &mut game);
+
+                // If bot goes first, make its move
+                if game.state.current_player() != player_side {
+                    start_bot_turn(&handle, &mut game);
+                } else {
+                    game.message = "Your turn!".to_string();
+                }
+            }
+            Err(e) => game.message = format!("Failed to load bot: {}", e),
+        }
+    } // MutexGuard dropped here
+
+    game_created(&app_state, &handle, &session, new_session)
+}
+
+/// Start a match between two bots for the session to watch
+/// The `Match` runs on its own thread and reports through a `WebObserver`
+async fn create_match(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<NewMatchRequest>,
+) -> Response {
+    let Some(slot) = app_state.match_slot() else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many bot matches are running, try again later",
+        );
+    };
+    let attacker = match app_state
+        .load_bot(&req.attacker, &req.attacker_options)
+        .await
+    {
+        Ok(bot) => bot,
+        Err(e) => {
+            let message = format!("Failed to load attacker: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    };
+    let defender = match app_state
+        .load_bot(&req.defender, &req.defender_options)
+        .await
+    {
+        Ok(bot) => bot,
+        Err(e) => {
+            let message = format!("Failed to load defender: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    };
+    let variant = string_to_variant(&req.variant);
+    let delay = req.delay_ms.map_or(MATCH_DELAY, Duration::from_millis);
+    let (session, new_session) = session_or_new(&headers);
+
+    let mut game = WebGame::new(GameState::new(variant));
+    game.attacker = SideInfo::new(attacker.name());
+    game.defender = SideInfo::new(defender.name());
+    game.pacing = Some(Arc::new(Pacing::new(delay, req.paused)));
+    let handle = GameHandle::new(game);
+
+    let config = MatchConfig {
+        time_per_move: req.time_ms.map_or(BOT_MOVE_TIME, Duration::from_millis),
+        ..Default::default()
+    };
+    let mut bot_match = Match::with_variant(attacker, defender, config, false, variant);
+    bot_match.add_observer(Box::new(WebObserver {
+        handle: handle.clone(),
+        runtime: tokio::runtime::Handle::current(),
+    }));
+    std::thread::spawn(move || {
+        let _slot = slot;
+        bot_match.play()
+    });
+
+    game_created(&app_state, &handle, &session, new_session)
+}
+
+/// The side the session plays and the index of its last move, for undo
+fn undo_target(game: &WebGame, session: Option<&str>) -> Result<(Player, usize), &'static str> {
+    let Some(human) = game.side_of(session) else {
+        return Err("You are not playing this game");
+    };
+    if game.bot_choice.is_none() {
+        return Err("Only moves against a bot can be undone");
+    }
+    if game.bot_thinking {
+        return Err("Wait for the bot to move");
+    }
+    (0..game.moves.len())
+        .rev()
+        .find(|&i| game.mover(i) == human)
+        .map(|last| (human, last))
+        .ok_or("No move to take back")
+}
+
+/// Take back the human's last move and the bot's reply to it
+/// The bot is loaded afresh, so bots that track the game see only the
+/// moves that remain
+async fn undo_move(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let session = session_id(&headers);
+    let Some(handle) = app_state.get(&id, session.as_deref()) else {
+        return game_not_found();
+    };
+    let choice = {
+        let game = handle.game.lock().unwrap();
+        if let Err(e) = undo_target(&game, session.as_deref()) {
+            return error_response(StatusCode::BAD_REQUEST, e);
+        }
+        game.bot_choice.clone().expect("checked by undo_target")
+    };
+    // Load the new bot without holding the lock, then check the game again
+    // as it may have moved on meanwhile
+    let bot = match choice.load().await {
+        Ok(bot) => bot,
+        Err(e) => {
+            let message = format!("Failed to load bot: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    };
+    let mut game = handle.game.lock().unwrap();
+    let (human, last) = match undo_target(&game, session.as_deref()) {
+        Ok(target) => target,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let mut state = game.start.clone();
+    for &mv in &game.moves[..last] {
+        state.make_move(mv).expect("moves already played are legal");
+    }
+    game.state = state;
+    game.moves.truncate(last);
+    if let Some((attacker_time, defender_time)) = game.times_before.get(last).cloned() {
+        game.attacker_time = attacker_time;
+        game.defender_time = defender_time;
+    }
+    game.times_before.truncate(last);
+    game.game_over = false;
+    game.winner = None;
+    *handle.analysis.lock().unwrap() = None;
+    attach_bot(&handle, &mut game, bot, human.opponent());
+    game.message = "Move taken back. Your turn!".to_string();
+    start_turn(&handle, &mut game);
+    handle.publish_state(&game);
+    Json(game_response(&handle, &game)).into_response()
+}
+
+/// Let a separate analysis bot suggest a move for the side to move
+/// The bot is loaded just for this and thinks on a blocking thread, so the
+/// game's own bot keeps its state and the game stays playable meanwhile
+async fn get_hint(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<HintRequest>,
+) -> Response {
+    let Some(handle) = app_state.get(&id, session_id(&headers).as_deref()) else {
+        return game_not_found();
+    };
+    let (state, moves) = {
+        let game = handle.game.lock().unwrap();
+        if game.game_over {
+            return error_response(StatusCode::BAD_REQUEST, "Game is over");
+        }
+        (game.state.clone(), game.moves.clone())
+    };
+    let mut bot = match app_state.load_bot(&req.bot_type, &req.bot_options).await {
+        Ok(bot) => bot,
+        Err(e) => {
+            let message = format!("Failed to load bot: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    };
+    let time = req
+        .time_ms
+        .map_or(HINT_TIME, Duration::from_millis)
+        .min(MAX_HINT_TIME);
+
+    let hint = tokio::task::spawn_blocking(move || {
+        let last_info = Arc::new(Mutex::new(None));
+        let sink = last_info.clone();
+        bot.set_search_info_sender(SearchInfoSender::new(move |info| {
+            *sink.lock().unwrap() = Some(info);
+        }));
+        bot.game_start(state.current_player());
+        for mv in moves {
+            bot.notify_move(mv);
+        }
+
+        let start = Instant::now();
+        let action = panic::catch_unwind(AssertUnwindSafe(|| {
+            bot.get_action(&state, &TimeInfo::per_move(time))
+        }))
+        .ok()?;
+        let info = last_info.lock().unwrap().take();
+        Some(HintResponse {
+            bot: bot.name().to_string(),
+            mv: action.played_move(),
+            info,
+            move_count: state.move_count(),
+            think_ms: start.elapsed().as_millis() as u64,
+        })
+    })
+    .await;
+
+    match hint {
+        Ok(Some(hint)) => Json(hint).into_response(),
+        _ => error_response(StatusCode::INTERNAL_SERVER_ERROR, "Analysis bot crashed"),
+    }
+}
+
+/// Parse the editor's board into a game
+fn setup_position(variant: Variant, setup: &PositionSetup) -> Result<GameState, String> {
+    let size = variant.board_size();
+    if setup.board.len() != size || setup.board.iter().any(|row| row.len() != size) {
+        return Err(format!(
+            "The board must have {} rows of {} squares",
+            size, size
+        ));
+    }
+
+    let mut pieces = Vec::new();
+    for (row_idx, row) in setup.board.iter().enumerate() {
+        for (col_idx, square) in row.iter().enumerate() {
+            let piece = match square.as_str() {
+                "" | "." => continue,
+                "A" => Piece::Attacker,
+                "D" => Piece::Defender,
+                "K" => Piece::King,
+                other => return Err(format!("Unknown piece '{}'", other)),
+            };
+            pieces.push((Position::new(row_idx, col_idx), piece));
+        }
+    }
+    let to_move = string_to_player(&setup.to_move);
+    GameState::from_position(variant, &pieces, to_move).map_err(|e| e.to_string())
+}
+
+fn position_response(state: &GameState) -> PositionResponse {
+    PositionResponse {
+        variant: format!("{:?}", state.variant()),
+        board: board_strings(state),
+        to_move: player_to_string(state.current_player()),
+        legal_moves: state.legal_moves(state.current_player()).len(),
     }
 }
 
-fn string_to_player(s: &str) -> Player {
-    match s.to_lowercase().as_str() {
-        "attackers" => Player::Attackers,
-        "defenders" => Player::Defenders,
-        _ => Player::Defenders,
-    }
+/// The variant's starting position, for the board editor to begin with
+async fn start_position(Query(query): Query<VariantQuery>) -> Json<PositionResponse> {
+    Json(position_response(&GameState::new(string_to_variant(
+        &query.variant,
+    ))))
 }
 
-fn string_to_variant(s: &str) -> Variant {
-    match s.to_lowercase().as_str() {
-        "copenhagen" => Variant::Copenhagen,
-        "brandubh" => Variant::Brandubh,
-        _ => Variant::Brandubh,
+/// Check a position from the board editor before starting a game from it
+async fn check_position(Json(req): Json<PositionRequest>) -> Response {
+    match setup_position(string_to_variant(&req.variant), &req.position) {
+        Ok(state) => Json(position_response(&state)).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, &e),
     }
 }
 
-fn create_bot(bot_type: &str) -> BotType {
-    if bot_type.starts_with("plugin:") {
-        let path = bot_type.strip_prefix("plugin:").unwrap().to_string();
-        BotType::Plugin(path)
-    } else {
-        match bot_type.to_lowercase().as_str() {
-            "greedy" => BotType::Greedy,
-            "random" => BotType::Random,
-            _ => BotType::Greedy,
+/// Take the free side of a game someone else created to play against a human
+async fn join_game(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let (session, new_session) = session_or_new(&headers);
+    match app_state.join(&id, &session) {
+        Ok((handle, side)) => {
+            let response = Json(JoinResponse {
+                player_side: player_to_string(side),
+                game: handle.response(),
+            });
+            with_session_cookie(response.into_response(), &session, new_session)
         }
+        Err(e @ JoinError::NotFound) => error_response(StatusCode::NOT_FOUND, &e.to_string()),
+        Err(e @ JoinError::Full) => error_response(StatusCode::CONFLICT, &e.to_string()),
     }
 }
 
-fn get_bot_instance(bot_type: &BotType) -> Result<Box<dyn Bot>, String> {
-    match bot_type {
-        BotType::Greedy => Ok(Box::new(GreedyBot::new("Greedy Bot".to_string()))),
-        BotType::Random => Ok(Box::new(RandomBot::new("Random Bot".to_string()))),
-        BotType::Plugin(path) => PluginBot::load(path).map(|bot| Box::new(bot) as Box<dyn Bot>),
-    }
+/// Pause, resume or step a bot match, or change its delay
+async fn control_match(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ControlRequest>,
+) -> Response {
+    let Some(handle) = app_state.get(&id, session_id(&headers).as_deref()) else {
+        return game_not_found();
+    };
+    let game = handle.game.lock().unwrap();
+    let Some(pacing) = &game.pacing else {
+        return error_response(StatusCode::BAD_REQUEST, "Not a bot match");
+    };
+    pacing.control(req.action, req.delay_ms.map(Duration::from_millis));
+    handle.publish_state(&game);
+    Json(game_response(&handle, &game)).into_response()
 }
 
 #[axum::debug_handler]
-async fn new_game(State(app_state): State<AppState>, Json(req): Json<NewGameRequest>) -> Response {
-    let variant = string_to_variant(&req.variant);
-    let player_side = string_to_player(&req.player_side);
-    let bot_type = create_bot(&req.bot_type);
-
-    let message = {
-        let mut game = app_state.game.lock().unwrap();
-        game.state = GameState::new(variant);
-        game.player_side = player_side;
-        game.bot_type = bot_type.clone();
-        game.game_over = false;
-        game.winner = None;
-
-        // Create and initialize the bot
-        match get_bot_instance(&bot_type) {
-            Ok(mut bot) => {
-                // Initialize the bot with game_start
-                let bot_side = player_side.opponent();
-                bot.game_start(bot_side);
+async fn make_move(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<MoveRequest>,
+) -> Response {
+    let session = session_id(&headers);
+    let Some(handle) = app_state.get(&id, session.as_deref()) else {
+        return game_not_found();
+    };
+    {
+        let mut game = handle.game.lock().unwrap();
 
-                // If bot goes first, make its move
-                let message = if game.state.current_player() != player_side {
-                    let state_clone = game.state.clone();
-                    if let Some(bot_move) =
-                        bot.get_move(&state_clone, std::time::Duration::from_secs(5))
-                    {
-                        let _ = game.state.make_move(bot_move);
-                        bot.notify_move(bot_move);
-                        if let Some(_result) = game.state.result() {
-                            game.game_over = true;
-                            game.winner = Some(game.state.current_player().opponent());
-                        }
-                        format!("Bot played: {} -> {}", bot_move.from, bot_move.to)
-                    } else {
-                        "Bot failed to make a move".to_string()
-                    }
-                } else {
-                    "Your turn!".to_string()
-                };
-
-                game.bot_instance = Some(bot);
-                message
-            }
-            Err(e) => {
-                game.bot_instance = None;
-                format!("Failed to load bot: {}", e)
-            }
+        if game.game_over {
+            return error_response(StatusCode::BAD_REQUEST, "Game is over");
         }
-    }; // MutexGuard dropped here
 
-    let Json(mut game_response) = get_game_state(State(app_state)).await;
-    game_response.message = message;
-    Json(game_response).into_response()
-}
+        if game.humans.is_empty() {
+            return error_response(StatusCode::BAD_REQUEST, "Bots are playing this game");
+        }
 
-#[axum::debug_handler]
-async fn make_move(State(app_state): State<AppState>, Json(req): Json<MoveRequest>) -> Response {
-    let bot_message = {
-        let mut game = app_state.game.lock().unwrap();
+        if game.open_seat.is_some() {
+            return error_response(StatusCode::BAD_REQUEST, "Waiting for an opponent to join");
+        }
 
-        if game.game_over {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "Game is over"
-                })),
-            )
-                .into_response();
-        }
-
-        // Check if it's the player's turn
-        if game.state.current_player() != game.player_side {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "Not your turn"
-                })),
-            )
-                .into_response();
+        // Check if it's the player's turn, and only move their own pieces
+        let to_move = game.state.current_player();
+        if game.bot_thinking || game.side_of(session.as_deref()) != Some(to_move) {
+            return error_response(StatusCode::BAD_REQUEST, "Not your turn");
         }
 
         // Apply player's move
@@ -231,112 +1659,170 @@ async fn make_move(State(app_state): State<AppState>, Json(req): Json<MoveReques
             Position::new(req.to_row, req.to_col),
         );
 
-        if let Err(e) = game.state.make_move(player_move) {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": format!("Invalid move: {}", e)
-                })),
-            )
-                .into_response();
+        let player = game.state.current_player();
+        if !game.state.legal_moves(player).contains(&player_move) {
+            let message = format!("Invalid move: Move {} is not legal", player_move);
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+        if !game.stop_clock() {
+            game.flag();
+            handle.publish_state(&game);
+            return error_response(StatusCode::BAD_REQUEST, "Out of time");
         }
+        let captures = game
+            .state
+            .make_move_with_captures(player_move)
+            .expect("the move is legal");
+        game.moves.push(player_move);
+        start_turn(&handle, &mut game);
+        handle.publish(GameEvent::Move {
+            player,
+            mv: player_move,
+            captures,
+            think_ms: None,
+        });
 
         // Check if game is over after player's move
-        if let Some(_result) = game.state.result() {
-            let winner = game.state.current_player().opponent();
-            game.game_over = true;
-            game.winner = Some(winner);
-            return (
-                StatusCode::OK,
-                Json(serde_json::json!({
-                    "message": format!("{} wins!", player_to_string(winner))
-                })),
-            )
-                .into_response();
-        }
-
-        // Bot's turn
-        if !game.game_over {
+        if game.end_if_decided() {
+            game.message = match game.winner {
+                Some(winner) => format!("{} wins!", player_to_string(winner)),
+                None => "Draw!".to_string(),
+            };
+        } else if game.humans.len() > 1 {
+            // The other human moves next
+            game.message = format!("{} played {}", game.side(player).name, player_move);
+        } else {
             // Notify bot of player's move
             if let Some(ref mut bot) = game.bot_instance {
                 bot.notify_move(player_move);
             }
 
-            let state_clone = game.state.clone();
+            start_bot_turn(&handle, &mut game);
+        }
+        handle.publish_state(&game);
+    } // Guard dropped here
 
-            // Get the bot's move (separate borrow scope)
-            let bot_move_opt = if let Some(ref mut bot) = game.bot_instance {
-                bot.get_move(&state_clone, std::time::Duration::from_secs(5))
-            } else {
-                None
-            };
+    Json(handle.response()).into_response()
+}
 
-            // Now apply the move and update game state
-            if let Some(bot_move) = bot_move_opt {
-                let _ = game.state.make_move(bot_move);
+async fn get_game(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    match app_state.get(&id, session_id(&headers).as_deref()) {
+        Some(handle) => Json(handle.response()).into_response(),
+        None => game_not_found(),
+    }
+}
 
-                // Notify bot of its own move
-                if let Some(ref mut bot) = game.bot_instance {
-                    bot.notify_move(bot_move);
-                }
+/// Stream the game's events; the first message is the current state
+async fn game_socket(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    match app_state.get(&id, session_id(&headers).as_deref()) {
+        Some(handle) => ws.on_upgrade(move |socket| stream_game(socket, handle)),
+        None => game_not_found(),
+    }
+}
 
-                if let Some(_result) = game.state.result() {
-                    game.game_over = true;
-                    game.winner = Some(game.state.current_player().opponent());
-                }
-                format!("Bot played: {} -> {}", bot_move.from, bot_move.to)
-            } else if game.bot_instance.is_none() {
-                "Bot instance not found".to_string()
-            } else {
-                "Bot failed to make a move".to_string()
+async fn stream_game(mut socket: WebSocket, handle: GameHandle) {
+    let mut events = handle.events.subscribe();
+    let mut next = Some(GameEvent::State(Box::new(handle.response())));
+    loop {
+        if let Some(event) = next.take() {
+            let text = serde_json::to_string(&event).expect("events serialize");
+            if socket.send(Message::Text(text)).await.is_err() {
+                return;
             }
-        } else {
-            String::new()
         }
-    }; // Guard dropped here
-
-    let Json(mut game_response) = get_game_state(State(app_state)).await;
-    game_response.message = bot_message;
-    Json(game_response).into_response()
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => next = Some(event),
+                // Too slow to keep up; start over from a snapshot
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    next = Some(GameEvent::State(Box::new(handle.response())));
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Clients have nothing to say; pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+        }
+    }
 }
 
-async fn get_game_state(State(app_state): State<AppState>) -> Json<GameResponse> {
-    let game = app_state.game.lock().unwrap();
+fn millis(duration: Option<Duration>) -> Option<u64> {
+    duration.map(|duration| duration.as_millis() as u64)
+}
 
-    let size = game.state.variant().board_size();
+fn board_strings(state: &GameState) -> Vec<Vec<String>> {
+    let size = state.variant().board_size();
     let mut board = vec![vec![String::new(); size]; size];
 
     for (row_idx, row) in board.iter_mut().enumerate() {
         for (col_idx, field) in row.iter_mut().enumerate() {
-            *field = piece_to_string(game.state.get_piece(Position::new(row_idx, col_idx)));
+            *field = piece_to_string(state.get_piece(Position::new(row_idx, col_idx)));
         }
     }
+    board
+}
 
-    let legal_moves: Vec<MoveResponse> =
-        if !game.game_over && game.state.current_player() == game.player_side {
-            game.state
-                .legal_moves(game.state.current_player())
-                .iter()
-                .map(|m| MoveResponse {
-                    from_row: m.from.row,
-                    from_col: m.from.col,
-                    to_row: m.to.row,
-                    to_col: m.to.col,
-                })
-                .collect()
-        } else {
-            Vec::new()
-        };
+fn game_response(handle: &GameHandle, game: &WebGame) -> GameResponse {
+    let board = board_strings(&game.state);
 
-    Json(GameResponse {
+    let legal_moves: Vec<MoveResponse> = if game.human_to_move() {
+        game.state
+            .legal_moves(game.state.current_player())
+            .iter()
+            .map(|m| MoveResponse {
+                from_row: m.from.row,
+                from_col: m.from.col,
+                to_row: m.to.row,
+                to_col: m.to.col,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    GameResponse {
+        id: handle.id.clone(),
         board,
         current_player: player_to_string(game.state.current_player()),
         legal_moves,
+        bot_thinking: game.bot_thinking,
         game_over: game.game_over,
         winner: game.winner.map(player_to_string),
         variant: format!("{:?}", game.state.variant()),
-        message: String::new(),
-    })
+        message: game.message.clone(),
+        analysis: handle.analysis.lock().unwrap().clone(),
+        vs_bot: game.bot_choice.is_some(),
+        waiting_for_opponent: game.open_seat.is_some(),
+        move_count: game.state.move_count(),
+        attacker: game.attacker.clone(),
+        defender: game.defender.clone(),
+        pacing: game.pacing.as_ref().map(|pacing| pacing.response()),
+        attacker_time_ms: millis(game.time_left(Player::Attackers)),
+        defender_time_ms: millis(game.time_left(Player::Defenders)),
+        clock_running: game.clock_running(),
+    }
+}
+
+async fn get_analysis(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    match app_state.get(&id, session_id(&headers).as_deref()) {
+        Some(handle) => Json(handle.analysis.lock().unwrap().clone()).into_response(),
+        None => game_not_found(),
+    }
 }
 
 #[derive(Serialize)]
@@ -346,13 +1832,78 @@ struct PluginInfo {
     path: String,
 }
 
+/// A bot from the registry, selected with its `id`
+#[derive(Serialize)]
+struct RegisteredInfo {
+    id: String,
+    name: String,
+    version: String,
+    /// `plugin` or `command`
+    kind: String,
+    added: u64,
+}
+
+impl From<RegisteredBot> for RegisteredInfo {
+    fn from(bot: RegisteredBot) -> Self {
+        let kind = match bot.source {
+            RegisteredSource::Plugin { .. } => "plugin",
+            RegisteredSource::Command { .. } => "command",
+        };
+        RegisteredInfo {
+            id: format!("{}{}", REGISTERED_PREFIX, bot.id),
+            name: bot.name,
+            version: bot.version,
+            kind: kind.to_string(),
+            added: bot.added,
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct AvailableBotsResponse {
     built_in: Vec<String>,
     plugins: Vec<PluginInfo>,
+    registered: Vec<RegisteredInfo>,
+    /// The server has an admin token, so bots can be uploaded
+    uploads_enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    id: String,
 }
 
-async fn list_bots() -> Json<AvailableBotsResponse> {
+#[derive(Deserialize)]
+struct RegisterCommandRequest {
+    id: String,
+    name: String,
+    command: String,
+}
+
+#[derive(Deserialize)]
+struct BotOptionsQuery {
+    bot_type: String,
+}
+
+async fn bot_options(
+    State(app_state): State<AppState>,
+    Query(query): Query<BotOptionsQuery>,
+) -> Response {
+    match app_state.load_bot(&query.bot_type, &HashMap::new()).await {
+        Ok(bot) => Json::<Vec<BotOption>>(bot.options()).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Failed to load bot: {}", e)
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Plugin libraries built under `plugins/`; `plugin:<path>` may only name
+/// one of these, so clients cannot make the server load any library
+fn plugin_libraries() -> Vec<PluginInfo> {
     use std::fs;
 
     let mut plugins = Vec::new();
@@ -394,27 +1945,994 @@ async fn list_bots() -> Json<AvailableBotsResponse> {
         }
     }
 
+    plugins
+}
+
+async fn list_bots(State(app_state): State<AppState>) -> Json<AvailableBotsResponse> {
+    let plugins = plugin_libraries();
+
+    // A broken manifest only hides the registered bots
+    let registered = app_state.bots.list().unwrap_or_default();
+
     Json(AvailableBotsResponse {
         built_in: vec!["Greedy".to_string(), "Random".to_string()],
         plugins,
+        registered: registered.into_iter().map(RegisteredInfo::from).collect(),
+        uploads_enabled: app_state.admin_token.is_some(),
     })
 }
 
-pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
-    let app_state = AppState::new();
+fn registry_error(error: RegistryError) -> Response {
+    let status = match error {
+        RegistryError::InvalidId(_) | RegistryError::Rejected(_) => StatusCode::BAD_REQUEST,
+        RegistryError::NotFound(_) => StatusCode::NOT_FOUND,
+        RegistryError::Io(_) | RegistryError::Manifest(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_response(status, &error.to_string())
+}
 
-    let app = Router::new()
-        .route("/api/new-game", post(new_game))
-        .route("/api/move", post(make_move))
-        .route("/api/game-state", get(get_game_state))
+/// Change the registry on a blocking thread, since checking a bot loads the
+/// library or starts the program
+async fn update_registry(
+    app_state: &AppState,
+    update: impl FnOnce(&BotRegistry) -> Result<RegisteredBot, RegistryError> + Send + 'static,
+) -> Response {
+    let bots = app_state.bots.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        // A plugin may panic while it is created
+        panic::catch_unwind(AssertUnwindSafe(|| update(&bots)))
+    })
+    .await;
+    match result {
+        Ok(Ok(Ok(bot))) => Json(RegisteredInfo::from(bot)).into_response(),
+        Ok(Ok(Err(e))) => registry_error(e),
+        _ => error_response(
+            StatusCode::BAD_REQUEST,
+            "Bot rejected: it crashed on loading",
+        ),
+    }
+}
+
+/// Register the uploaded plugin library in the body as bot `id`
+async fn upload_plugin(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UploadQuery>,
+    library: Bytes,
+) -> Response {
+    if let Some(response) = app_state.unauthorized(&headers) {
+        return response;
+    }
+    update_registry(&app_state, move |bots| bots.add_plugin(&query.id, &library)).await
+}
+
+/// Register a process bot; the command runs on the server, so this is for
+/// admins only like uploads
+async fn register_command(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RegisterCommandRequest>,
+) -> Response {
+    if let Some(response) = app_state.unauthorized(&headers) {
+        return response;
+    }
+    update_registry(&app_state, move |bots| {
+        bots.add_command(&req.id, &req.name, &req.command)
+    })
+    .await
+}
+
+async fn remove_bot(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(response) = app_state.unauthorized(&headers) {
+        return response;
+    }
+    update_registry(&app_state, move |bots| bots.remove(&id)).await
+}
+
+/// All routes of the web app, the static files included
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/api/games", post(create_game))
+        .route("/api/games/:id", get(get_game))
+        .route("/api/games/:id/move", post(make_move))
+        .route("/api/games/:id/join", post(join_game))
+        .route("/api/games/:id/undo", post(undo_move))
+        .route("/api/games/:id/hint", post(get_hint))
+        .route("/api/positions", get(start_position).post(check_position))
+        .route("/api/games/:id/analysis", get(get_analysis))
+        .route("/api/games/:id/ws", get(game_socket))
+        .route("/api/matches", post(create_match))
+        .route("/api/games/:id/control", post(control_match))
         .route("/api/bots", get(list_bots))
+        .route(
+            "/api/bots/plugins",
+            post(upload_plugin).layer(DefaultBodyLimit::max(MAX_UPLOAD)),
+        )
+        .route("/api/bots/commands", post(register_command))
+        .route("/api/bots/:id", delete(remove_bot))
+        .route("/api/bot-options", get(bot_options))
         .nest_service("/", ServeDir::new("static"))
-        .with_state(app_state);
+        .with_state(app_state)
+}
+
+pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
+    serve("127.0.0.1:3000").await
+}
+
+/// Serve the web app on `addr`, e.g. `127.0.0.1:3000`
+pub async fn serve(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    serve_with_state(addr, AppState::new()).await
+}
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
-    println!("🌐 Web server running at http://127.0.0.1:3000");
+/// Serve the web app with a registry or admin token set up by the caller
+pub async fn serve_with_state(
+    addr: &str,
+    app_state: AppState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let expiring = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            expiring.expire_idle();
+        }
+    });
+
+    let app = router(app_state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("🌐 Web server running at http://{}", addr);
     println!("   Open your browser and start playing!");
 
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::Service;
+
+    async fn send(
+        app: &AppState,
+        request: Request<Body>,
+    ) -> (StatusCode, HeaderMap, serde_json::Value) {
+        let response = router(app.clone()).call(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn new_game_request(cookie: Option<&str>) -> Request<Body> {
+        let mut request =
+            Request::post("/api/games").header(header::CONTENT_TYPE, "application/json");
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        let body = r#"{"variant":"brandubh","player_side":"attackers","bot_type":"random"}"#;
+        request.body(Body::from(body)).unwrap()
+    }
+
+    fn get_request(uri: &str, cookie: Option<&str>) -> Request<Body> {
+        let mut request = Request::get(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_games_belong_to_sessions() {
+        let app = AppState::new();
+
+        let (status, headers, game) = send(&app, new_game_request(None)).await;
+        assert_eq!(status, StatusCode::OK);
+        let set_cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = set_cookie.split(';').next().unwrap().to_string();
+        let uri = format!("/api/games/{}", game["id"].as_str().unwrap());
+
+        // A second game in the same session keeps the cookie
+        let (_, headers, other) = send(&app, new_game_request(Some(&cookie))).await;
+        assert!(!headers.contains_key(header::SET_COOKIE));
+        assert_ne!(other["id"], game["id"]);
+        assert_eq!(app.game_count(), 2);
+
+        let (status, _, state) = send(&app, get_request(&uri, Some(&cookie))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["current_player"], "Attackers");
+
+        let (status, _, _) = send(&app, get_request(&uri, None)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let stranger = format!("{}=someone-else", SESSION_COOKIE);
+        let (status, _, _) = send(&app, get_request(&uri, Some(&stranger))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_bot_thinks_in_background() {
+        let app = AppState::new();
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"defenders","bot_type":"greedy"}"#,
+            ))
+            .unwrap();
+        let (_, headers, game) = send(&app, request).await;
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let uri = format!("/api/games/{}", game["id"].as_str().unwrap());
+
+        // The attacking bot moves first; wait for its answer
+        let mut state = game;
+        for _ in 0..100 {
+            if state["bot_thinking"] == false {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        }
+        assert_eq!(state["bot_thinking"], false);
+        assert_eq!(state["current_player"], "Defenders");
+        assert!(state["message"].as_str().unwrap().starts_with("Bot played"));
+        assert!(!state["legal_moves"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_socket_streams_moves() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let app = AppState::new();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = router(app.clone());
+        tokio::spawn(async move { axum::serve(listener, server).await });
+
+        let (_, headers, game) = send(&app, new_game_request(None)).await;
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+
+        let mut request = format!("ws://{}/api/games/{}/ws", addr, id)
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert(header::COOKIE, cookie.parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        let mut next_event = async || -> serde_json::Value {
+            let message = tokio::time::timeout(Duration::from_secs(10), socket.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            serde_json::from_str(message.to_text().unwrap()).unwrap()
+        };
+
+        let first = next_event().await;
+        assert_eq!(first["type"], "state");
+        assert_eq!(first["id"], id);
+
+        let mv = &first["legal_moves"][0];
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(mv.to_string()))
+            .unwrap();
+        let (status, _, _) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Our move, then the bot's, then the position after it
+        let mut movers = Vec::new();
+        loop {
+            let event = next_event().await;
+            match event["type"].as_str().unwrap() {
+                "move" => {
+                    assert!(event["captures"].is_array());
+                    movers.push(event["player"].as_str().unwrap().to_string());
+                }
+                "state" if event["bot_thinking"] == false => break,
+                _ => {}
+            }
+        }
+        assert_eq!(movers, ["Attackers", "Defenders"]);
+    }
+
+    #[tokio::test]
+    async fn test_bot_matches_are_capped_and_stop_when_closed() {
+        let mut app = AppState::new();
+        app.set_max_matches(1);
+        let new_match = || {
+            Request::post("/api/matches")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"{"variant":"brandubh","attacker":"greedy","defender":"random","paused":true}"#,
+                ))
+                .unwrap()
+        };
+
+        let (status, _, _) = send(&app, new_match()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, body) = send(&app, new_match()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body["error"].as_str().unwrap().contains("Too many"));
+
+        // Closing the paused match ends it rather than playing it out,
+        // which frees its slot
+        let handle = app
+            .games
+            .lock()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .handle
+            .clone();
+        handle.close();
+        let mut status = StatusCode::SERVICE_UNAVAILABLE;
+        for _ in 0..100 {
+            if app.running_matches.load(Ordering::SeqCst) == 0 {
+                status = send(&app, new_match()).await.0;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(status, StatusCode::OK);
+        let game = handle.game.lock().unwrap();
+        assert!(game.moves.is_empty());
+        assert_eq!(game.message, "Stopped after 0 moves");
+    }
+
+    #[tokio::test]
+    async fn test_bot_match_steps_while_paused() {
+        let app = AppState::new();
+        let request = Request::post("/api/matches")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","attacker":"greedy","defender":"random","delay_ms":0,"paused":true}"#,
+            ))
+            .unwrap();
+        let (status, headers, game) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(game["pacing"]["paused"], true);
+        assert_eq!(game["attacker"]["name"], "Greedy Bot");
+        assert!(game["legal_moves"].as_array().unwrap().is_empty());
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let uri = format!("/api/games/{}", id);
+        let control = |body: &'static str| {
+            Request::post(format!("/api/games/{}/control", id))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::COOKIE, &cookie)
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        assert_eq!(state["move_count"], 0);
+
+        // One step plays exactly one move
+        send(&app, control(r#"{"action":"step"}"#)).await;
+        let mut state = state;
+        for _ in 0..100 {
+            if state["move_count"] == 1 && state["bot_thinking"] == false {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        }
+        assert_eq!(state["move_count"], 1);
+        assert!(state["attacker"]["last_think_ms"].is_u64());
+        assert!(state["defender"]["last_think_ms"].is_null());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        assert_eq!(state["move_count"], 1);
+
+        let (_, _, state) = send(&app, control(r#"{"action":"resume"}"#)).await;
+        assert_eq!(state["pacing"]["paused"], false);
+        let mut state = state;
+        for _ in 0..500 {
+            if state["game_over"] == true {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        }
+        assert_eq!(state["game_over"], true);
+        assert!(state["move_count"].as_u64().unwrap() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_second_human_joins_with_game_id() {
+        let app = AppState::new();
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"attackers","vs_human":true}"#,
+            ))
+            .unwrap();
+        let (_, headers, game) = send(&app, request).await;
+        assert_eq!(game["waiting_for_opponent"], true);
+        assert!(game["legal_moves"].as_array().unwrap().is_empty());
+        let host = headers[header::SET_COOKIE].to_str().unwrap();
+        let host = host.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let join = |cookie: Option<&str>| {
+            let mut request = Request::post(format!("/api/games/{}/join", id));
+            if let Some(cookie) = cookie {
+                request = request.header(header::COOKIE, cookie);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let play = |cookie: &str, mv: &serde_json::Value| {
+            Request::post(format!("/api/games/{}/move", id))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::COOKIE, cookie)
+                .body(Body::from(mv.to_string()))
+                .unwrap()
+        };
+
+        let (status, headers, joined) = send(&app, join(None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(joined["player_side"], "Defenders");
+        assert_eq!(joined["waiting_for_opponent"], false);
+        let guest = headers[header::SET_COOKIE].to_str().unwrap();
+        let guest = guest.split(';').next().unwrap().to_string();
+
+        // The seat is taken; the host rejoining keeps their side
+        let (status, _, _) = send(&app, join(None)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, _, rejoined) = send(&app, join(Some(&host))).await;
+        assert_eq!(rejoined["player_side"], "Attackers");
+
+        let mv = &joined["legal_moves"][0];
+        let (status, _, _) = send(&app, play(&guest, mv)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, state) = send(&app, play(&host, mv)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["current_player"], "Defenders");
+        assert_eq!(state["bot_thinking"], false);
+
+        let uri = format!("/api/games/{}", id);
+        let (status, _, state) = send(&app, get_request(&uri, Some(&guest))).await;
+        assert_eq!(status, StatusCode::OK);
+        let reply = &state["legal_moves"][0];
+        let (status, _, _) = send(&app, play(&host, reply)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, state) = send(&app, play(&guest, reply)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["move_count"], 2);
+    }
+
+    /// Poll the game until the bot has answered
+    async fn wait_for_bot(app: &AppState, uri: &str, cookie: &str) -> serde_json::Value {
+        for _ in 0..100 {
+            let state = send(app, get_request(uri, Some(cookie))).await.2;
+            if state["bot_thinking"] == false {
+                return state;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("the bot did not move in time");
+    }
+
+    #[test]
+    fn test_bot_forfeits_for_breaking_rules() {
+        let actions = [
+            BotAction::AcceptDraw,
+            BotAction::Move(Move {
+                from: Position::new(0, 0),
+                to: Position::new(0, 1),
+            }),
+        ];
+        for action in actions {
+            let handle = GameHandle::new(WebGame::new(GameState::new(Variant::Brandubh)));
+            let mut game = handle.game.lock().unwrap();
+            let bot_side = game.state.current_player();
+            let message = apply_bot_action(&handle, &mut game, action, Duration::ZERO);
+            assert!(game.game_over);
+            assert_eq!(game.winner, Some(bot_side.opponent()));
+            assert!(message.ends_with(&format!("{} win", player_to_string(bot_side.opponent()))));
+        }
+    }
+
+    /// Attacker and defender shuttle back and forth from the start, so the
+    /// defenders' last move repeats the starting position a third time
+    fn repeating_moves() -> Vec<Move> {
+        let shuttles = [
+            Move::new(Position::new(0, 3), Position::new(0, 2)),
+            Move::new(Position::new(2, 3), Position::new(2, 2)),
+            Move::new(Position::new(0, 2), Position::new(0, 3)),
+            Move::new(Position::new(2, 2), Position::new(2, 3)),
+        ];
+        shuttles.iter().chain(&shuttles).copied().collect()
+    }
+
+    /// Thinks for a while, then plays its first legal move
+    struct SlowBot;
+
+    impl Bot for SlowBot {
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn get_move(&mut self, state: &GameState, _time_limit: Duration) -> Option<Move> {
+            std::thread::sleep(Duration::from_millis(100));
+            state.legal_moves(state.current_player()).first().copied()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_bot_answer_after_the_flag_is_ignored() {
+        let handle = GameHandle::new(WebGame::new(GameState::new(Variant::Brandubh)));
+        {
+            let mut game = handle.game.lock().unwrap();
+            game.bot_instance = Some(Box::new(SlowBot));
+            start_bot_turn(&handle, &mut game);
+            game.flag();
+        }
+        while handle.game.lock().unwrap().bot_thinking {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let game = handle.game.lock().unwrap();
+        assert!(game.moves.is_empty());
+        assert_eq!(game.winner, Some(Player::Defenders));
+        assert_eq!(game.message, "Attackers lost on time");
+        assert!(game.bot_instance.is_some());
+    }
+
+    #[test]
+    fn test_bot_repetition_is_won_by_the_attackers() {
+        let mut moves = repeating_moves();
+        let last = moves.pop().unwrap();
+        let mut state = GameState::new(Variant::Brandubh);
+        for mv in moves {
+            state.make_move(mv).unwrap();
+        }
+        let handle = GameHandle::new(WebGame::new(state));
+        let mut game = handle.game.lock().unwrap();
+        apply_bot_action(&handle, &mut game, BotAction::Move(last), Duration::ZERO);
+        assert!(game.game_over);
+        assert_eq!(game.winner, Some(Player::Attackers));
+    }
+
+    #[tokio::test]
+    async fn test_human_repetition_is_won_by_the_attackers() {
+        let app = AppState::new();
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"attackers","vs_human":true}"#,
+            ))
+            .unwrap();
+        let (_, headers, game) = send(&app, request).await;
+        let host = headers[header::SET_COOKIE].to_str().unwrap();
+        let host = host.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let request = Request::post(format!("/api/games/{}/join", id))
+            .body(Body::empty())
+            .unwrap();
+        let (_, headers, _) = send(&app, request).await;
+        let guest = headers[header::SET_COOKIE].to_str().unwrap();
+        let guest = guest.split(';').next().unwrap().to_string();
+
+        let mut state = serde_json::Value::Null;
+        for (i, mv) in repeating_moves().into_iter().enumerate() {
+            let cookie = if i % 2 == 0 { &host } else { &guest };
+            let body = serde_json::json!({
+                "from_row": mv.from.row,
+                "from_col": mv.from.col,
+                "to_row": mv.to.row,
+                "to_col": mv.to.col,
+            });
+            let request = Request::post(format!("/api/games/{}/move", id))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::COOKIE, cookie)
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let (status, _, body) = send(&app, request).await;
+            assert_eq!(status, StatusCode::OK);
+            state = body;
+        }
+        assert_eq!(state["game_over"], true);
+        assert_eq!(state["winner"], "Attackers");
+        assert_eq!(state["message"], "Attackers wins!");
+    }
+
+    #[tokio::test]
+    async fn test_undo_takes_back_move_and_reply() {
+        let app = AppState::new();
+        let (_, headers, game) = send(&app, new_game_request(None)).await;
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let uri = format!("/api/games/{}", id);
+        let undo = || {
+            Request::post(format!("/api/games/{}/undo", id))
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (status, _, _) = send(&app, undo()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(game["legal_moves"][0].to_string()))
+            .unwrap();
+        send(&app, request).await;
+        let state = wait_for_bot(&app, &uri, &cookie).await;
+        assert_eq!(state["move_count"], 2);
+
+        let (status, _, state) = send(&app, undo()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["move_count"], 0);
+        assert_eq!(state["current_player"], "Attackers");
+        assert_eq!(state["board"], game["board"]);
+        assert_eq!(state["legal_moves"], game["legal_moves"]);
+    }
+
+    #[tokio::test]
+    async fn test_undo_gives_back_time_and_needs_the_bot() {
+        let app = AppState::new();
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"attackers","bot_type":"random",
+                    "attacker_time":{"initial_ms":60000}}"#,
+            ))
+            .unwrap();
+        let (_, headers, game) = send(&app, request).await;
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let uri = format!("/api/games/{}", id);
+        let undo = || {
+            Request::post(format!("/api/games/{}/undo", id))
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(game["legal_moves"][0].to_string()))
+            .unwrap();
+        send(&app, request).await;
+        let state = wait_for_bot(&app, &uri, &cookie).await;
+        assert!(state["attacker_time_ms"].as_u64().unwrap() <= 59900);
+
+        // A bot that no longer loads leaves the game as it is
+        let handle = app.games.lock().unwrap()[id].handle.clone();
+        let choice = handle.game.lock().unwrap().bot_choice.clone().unwrap();
+        handle.game.lock().unwrap().bot_choice = Some(BotChoice {
+            bot_type: BotType::Plugin("missing.so".to_string()),
+            options: HashMap::new(),
+        });
+        let (status, _, error) = send(&app, undo()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            error["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Failed to load bot")
+        );
+        let (_, _, state) = send(&app, get_request(&uri, Some(&cookie))).await;
+        assert_eq!(state["move_count"], 2);
+
+        handle.game.lock().unwrap().bot_choice = Some(choice);
+        let (status, _, state) = send(&app, undo()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["move_count"], 0);
+        assert!(state["attacker_time_ms"].as_u64().unwrap() > 59900);
+    }
+
+    #[tokio::test]
+    async fn test_game_from_position() {
+        let app = AppState::new();
+        let mut board = vec![vec!["."; 7]; 7];
+        board[1][2] = "K";
+        board[5][5] = "A";
+        let position = serde_json::json!({
+            "variant": "brandubh",
+            "board": board,
+            "to_move": "defenders",
+        });
+        let request = Request::post("/api/positions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(position.to_string()))
+            .unwrap();
+        let (status, _, checked) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(checked["to_move"], "Defenders");
+
+        board[3][3] = "A";
+        let request = Request::post("/api/positions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({"variant": "brandubh", "board": board, "to_move": "defenders"})
+                    .to_string(),
+            ))
+            .unwrap();
+        let (status, _, error) = send(&app, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["error"].as_str().unwrap().contains("throne"));
+
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "variant": "brandubh",
+                    "player_side": "defenders",
+                    "bot_type": "greedy",
+                    "position": {"board": checked["board"], "to_move": "defenders"},
+                })
+                .to_string(),
+            ))
+            .unwrap();
+        let (status, _, game) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(game["board"], checked["board"]);
+        assert_eq!(game["current_player"], "Defenders");
+        assert_eq!(game["bot_thinking"], false);
+        assert!(!game["legal_moves"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hint_suggests_legal_move() {
+        let app = AppState::new();
+        let (_, headers, game) = send(&app, new_game_request(None)).await;
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+
+        let request = Request::post(format!("/api/games/{}/hint", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(r#"{"bot_type":"greedy","time_ms":500}"#))
+            .unwrap();
+        let (status, _, hint) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(hint["bot"], "Greedy Bot");
+        assert_eq!(hint["move_count"], 0);
+        let mv = &hint["mv"];
+        let suggested = serde_json::json!({
+            "from_row": mv["from"]["row"],
+            "from_col": mv["from"]["col"],
+            "to_row": mv["to"]["row"],
+            "to_col": mv["to"]["col"],
+        });
+        let legal = game["legal_moves"].as_array().unwrap();
+        assert!(legal.contains(&suggested));
+
+        // Nothing was played, and the opponent bot still answers moves
+        let uri = format!("/api/games/{}", id);
+        let state = send(&app, get_request(&uri, Some(&cookie))).await.2;
+        assert_eq!(state["move_count"], 0);
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(suggested.to_string()))
+            .unwrap();
+        send(&app, request).await;
+        let state = wait_for_bot(&app, &uri, &cookie).await;
+        assert_eq!(state["move_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_side_loses_on_time() {
+        let app = AppState::new();
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"attackers","vs_human":true,
+                    "attacker_time":{"initial_ms":60000,"increment_ms":1000},
+                    "defender_time":{"per_move_ms":100}}"#,
+            ))
+            .unwrap();
+        let (_, headers, game) = send(&app, request).await;
+        assert_eq!(game["attacker_time_ms"], 60000);
+        assert_eq!(game["defender_time_ms"], 100);
+        // Nobody's clock runs before the opponent is there
+        assert_eq!(game["clock_running"], false);
+        let host = headers[header::SET_COOKIE].to_str().unwrap();
+        let host = host.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+
+        let request = Request::post(format!("/api/games/{}/join", id))
+            .body(Body::empty())
+            .unwrap();
+        let (_, _, joined) = send(&app, request).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &host)
+            .body(Body::from(joined["legal_moves"][0].to_string()))
+            .unwrap();
+        let (status, _, state) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state["clock_running"], true);
+        let attacker_left = state["attacker_time_ms"].as_u64().unwrap();
+        assert!(attacker_left > 60000 && attacker_left <= 61000);
+        let defender_left = state["defender_time_ms"].as_u64().unwrap();
+        assert!(defender_left <= 100);
+
+        // The defenders never move; the server flags them by itself
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let uri = format!("/api/games/{}", id);
+        let state = send(&app, get_request(&uri, Some(&host))).await.2;
+        assert_eq!(state["game_over"], true);
+        assert_eq!(state["winner"], "Attackers");
+        assert_eq!(state["message"], "Defenders lost on time");
+        assert_eq!(state["clock_running"], false);
+    }
+
+    #[tokio::test]
+    async fn test_bot_plays_on_a_clock_with_periods() {
+        let app = AppState::new();
+        let new_game = |defender_time: &str| {
+            let body = format!(
+                r#"{{"variant":"brandubh","player_side":"attackers","bot_type":"random",
+                    "defender_time":{}}}"#,
+                defender_time
+            );
+            Request::post("/api/games")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let (status, _, body) = send(
+            &app,
+            new_game(r#"{"initial_ms":1000,"moves_per_period":0}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"],
+            "Defender time: moves_per_period must be at least 1"
+        );
+        let (status, _, _) = send(&app, new_game(r#"{"per_move_ms":100000000}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // A new period after every move: the bot's clock is refilled
+        let (status, headers, game) = send(
+            &app,
+            new_game(r#"{"initial_ms":10000,"moves_per_period":1}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let cookie = headers[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let id = game["id"].as_str().unwrap();
+        let request = Request::post(format!("/api/games/{}/move", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::COOKIE, &cookie)
+            .body(Body::from(game["legal_moves"][0].to_string()))
+            .unwrap();
+        send(&app, request).await;
+        let state = wait_for_bot(&app, &format!("/api/games/{}", id), &cookie).await;
+        assert_eq!(state["move_count"], 2);
+        assert_eq!(state["game_over"], false);
+        assert!(state["defender_time_ms"].as_u64().unwrap() > 10000);
+    }
+
+    #[tokio::test]
+    async fn test_only_listed_plugins_load() {
+        let app = AppState::new();
+        for path in ["/tmp/libevil.so", "plugins/../libevil.so"] {
+            let uri = format!("/api/bot-options?bot_type=plugin:{}", path);
+            let (status, _, body) = send(&app, get_request(&uri, None)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body["error"].as_str().unwrap().contains("Unknown plugin"));
+        }
+
+        let request = Request::post("/api/matches")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","attacker":"plugin:/tmp/libevil.so","defender":"greedy"}"#,
+            ))
+            .unwrap();
+        let (status, _, _) = send(&app, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_idle_games_expire() {
+        let app = AppState::with_idle_timeout(Duration::ZERO);
+        let (_, headers, game) = send(&app, new_game_request(None)).await;
+        let cookie = headers[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let uri = format!("/api/games/{}", game["id"].as_str().unwrap());
+
+        std::thread::sleep(Duration::from_millis(5));
+        let (status, _, _) = send(&app, get_request(&uri, Some(&cookie))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        send(&app, new_game_request(Some(&cookie))).await;
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(app.expire_idle(), 1);
+        assert_eq!(app.game_count(), 0);
+
+        // Games watched on the WebSocket stay
+        let (_, _, game) = send(&app, new_game_request(Some(&cookie))).await;
+        let uri = format!("/api/games/{}", game["id"].as_str().unwrap());
+        let watcher = app.games.lock().unwrap()[game["id"].as_str().unwrap()]
+            .handle
+            .events
+            .subscribe();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(app.expire_idle(), 0);
+        let (status, _, _) = send(&app, get_request(&uri, Some(&cookie))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        drop(watcher);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(app.expire_idle(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bot_uploads_need_admin_token() {
+        let dir = std::env::temp_dir().join(format!("web-bots-{}", std::process::id()));
+        let mut app = AppState::new();
+        app.set_bot_registry(BotRegistry::new(&dir));
+        let upload = |token: Option<&str>| {
+            let mut request = Request::post("/api/bots/plugins?id=mine");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(Body::from("not a library")).unwrap()
+        };
+
+        let (status, _, _) = send(&app, upload(Some("secret"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        app.set_admin_token("secret");
+        let (status, _, _) = send(&app, upload(None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, _) = send(&app, upload(Some("guess"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, body) = send(&app, upload(Some("secret"))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().starts_with("Bot rejected"));
+
+        let (status, _, bots) = send(&app, get_request("/api/bots", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(bots["uploads_enabled"], true);
+        assert_eq!(bots["registered"], serde_json::json!([]));
+
+        let request = Request::post("/api/games")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"variant":"brandubh","player_side":"attackers","bot_type":"bot:mine"}"#,
+            ))
+            .unwrap();
+        let (_, _, game) = send(&app, request).await;
+        assert!(
+            game["message"]
+                .as_str()
+                .unwrap()
+                .contains("No registered bot mine")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
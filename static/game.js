@@ -1,15 +1,55 @@
 let gameState = null;
+let gameId = null;
 let selectedSquare = null;
 let highlightedMoves = [];
 let playerSide = null;
+let socket = null;
+// Waiting to reconnect a dropped socket, backing off up to MAX_RECONNECT_DELAY
+let reconnectTimer = null;
+let reconnectDelay = 1000;
+let lastMove = null;
+// Set up in the board editor: { variant, board, to_move }
+let customPosition = null;
+let editorBoard = null;
+// The analysis bot's suggestion, drawn as an arrow while the position lasts
+let hint = null;
+// When the current game state arrived, for running its clock locally
+let clockState = null;
+let clockSyncedAt = 0;
 
 const API_BASE = '/api';
+const MAX_RECONNECT_DELAY = 30000;
 
 // Initialize
 document.addEventListener('DOMContentLoaded', () => {
     document.getElementById('newGameBtn').addEventListener('click', startNewGame);
     document.getElementById('backToSetup').addEventListener('click', showSetup);
+    document.getElementById('botType').addEventListener('change', loadBotOptions);
+    document.getElementById('mode').addEventListener('change', updateMode);
+    document.getElementById('pauseBtn').addEventListener('click', togglePause);
+    document.getElementById('stepBtn').addEventListener('click', () => controlMatch({ action: 'step' }));
+    document.getElementById('copyJoinLink').addEventListener('click', copyJoinLink);
+    document.getElementById('undoBtn').addEventListener('click', undoMove);
+    document.getElementById('hintBtn').addEventListener('click', requestHint);
+    document.getElementById('variant').addEventListener('change', () => setCustomPosition(null));
+    document.getElementById('editPositionBtn').addEventListener('click', openEditor);
+    document.getElementById('clearPositionBtn').addEventListener('click', () => setCustomPosition(null));
+    document.getElementById('editorStartBtn').addEventListener('click', loadStartPosition);
+    document.getElementById('editorClearBtn').addEventListener('click', clearEditorBoard);
+    document.getElementById('editorCancelBtn').addEventListener('click', closeEditor);
+    document.getElementById('editorUseBtn').addEventListener('click', useEditorPosition);
+    document.getElementById('uploadKind').addEventListener('change', updateUploadKind);
+    document.getElementById('uploadBtn').addEventListener('click', addBot);
+    updateMode();
     loadAvailableBots();
+    setInterval(renderClocks, 200);
+
+    // Opened through a friend's join link
+    const joinId = new URLSearchParams(location.search).get('join');
+    if (joinId) {
+        history.replaceState(null, '', location.pathname);
+        joinGame(joinId);
+    }
 });
 
 async function loadAvailableBots() {
@@ -17,37 +57,235 @@ async function loadAvailableBots() {
         const response = await fetch(`${API_BASE}/bots`);
         const data = await response.json();
 
-        const botSelect = document.getElementById('botType');
-        botSelect.innerHTML = ''; // Clear existing options
+        document.querySelectorAll('.bot-select').forEach(select => fillBotSelect(select, data));
+        loadBotOptions();
+        renderRegisteredBots(data);
+    } catch (error) {
+        console.error('Error loading bots:', error);
+    }
+}
 
-        // Add built-in bots
-        data.built_in.forEach(bot => {
+function fillBotSelect(botSelect, data) {
+    botSelect.innerHTML = ''; // Clear existing options
+
+    // Add built-in bots
+    data.built_in.forEach(bot => {
+        const option = document.createElement('option');
+        option.value = bot.toLowerCase();
+        option.textContent = `${bot} Bot`;
+        botSelect.appendChild(option);
+    });
+
+    // Add plugin bots
+    if (data.plugins && data.plugins.length > 0) {
+        const separator = document.createElement('option');
+        separator.disabled = true;
+        separator.textContent = '──── Plugin Bots ────';
+        botSelect.appendChild(separator);
+
+        data.plugins.forEach(plugin => {
             const option = document.createElement('option');
-            option.value = bot.toLowerCase();
-            option.textContent = `${bot} Bot`;
+            option.value = plugin.id;
+            option.textContent = plugin.name;
             botSelect.appendChild(option);
         });
+    }
 
-        // Add plugin bots
-        if (data.plugins && data.plugins.length > 0) {
-            const separator = document.createElement('option');
-            separator.disabled = true;
-            separator.textContent = '──── Plugin Bots ────';
-            botSelect.appendChild(separator);
-
-            data.plugins.forEach(plugin => {
-                const option = document.createElement('option');
-                option.value = plugin.id;
-                option.textContent = plugin.name;
-                botSelect.appendChild(option);
-            });
+    // Add bots uploaded through the web interface
+    if (data.registered && data.registered.length > 0) {
+        const separator = document.createElement('option');
+        separator.disabled = true;
+        separator.textContent = '──── Uploaded Bots ────';
+        botSelect.appendChild(separator);
+
+        data.registered.forEach(bot => {
+            const option = document.createElement('option');
+            option.value = bot.id;
+            option.textContent = bot.version ? `${bot.name} ${bot.version}` : bot.name;
+            botSelect.appendChild(option);
+        });
+    }
+}
+
+// The bot admin panel; only shown when the server has an admin token
+function renderRegisteredBots(data) {
+    document.getElementById('botAdmin').style.display = data.uploads_enabled ? '' : 'none';
+    const list = document.getElementById('registeredBots');
+    list.innerHTML = '';
+    (data.registered || []).forEach(bot => {
+        const item = document.createElement('li');
+        const label = document.createElement('span');
+        const name = bot.version ? `${bot.name} ${bot.version}` : bot.name;
+        label.textContent = `${name} (${bot.id}, ${bot.kind})`;
+        const remove = document.createElement('button');
+        remove.className = 'btn btn-secondary';
+        remove.textContent = 'Remove';
+        remove.addEventListener('click', () => removeBot(bot.id));
+        item.appendChild(label);
+        item.appendChild(remove);
+        list.appendChild(item);
+    });
+}
+
+function updateUploadKind() {
+    const kind = document.getElementById('uploadKind').value;
+    document.querySelectorAll('[data-kinds]').forEach(element => {
+        element.style.display = element.dataset.kinds === kind ? '' : 'none';
+    });
+}
+
+function showUploadStatus(text, isError) {
+    const status = document.getElementById('uploadStatus');
+    status.textContent = text;
+    status.classList.toggle('error', isError);
+}
+
+function adminHeaders() {
+    return { 'Authorization': `Bearer ${document.getElementById('adminToken').value}` };
+}
+
+// Upload a plugin library or register a command; the server checks the bot
+// before anyone can pick it
+async function addBot() {
+    const id = document.getElementById('uploadId').value.trim();
+    const kind = document.getElementById('uploadKind').value;
+    let request;
+    if (kind === 'plugin') {
+        const file = document.getElementById('uploadFile').files[0];
+        if (!file) {
+            showUploadStatus('Choose a library to upload', true);
+            return;
+        }
+        request = fetch(`${API_BASE}/bots/plugins?id=${encodeURIComponent(id)}`, {
+            method: 'POST',
+            headers: { ...adminHeaders(), 'Content-Type': 'application/octet-stream' },
+            body: file,
+        });
+    } else {
+        request = fetch(`${API_BASE}/bots/commands`, {
+            method: 'POST',
+            headers: { ...adminHeaders(), 'Content-Type': 'application/json' },
+            body: JSON.stringify({
+                id,
+                name: document.getElementById('uploadName').value,
+                command: document.getElementById('uploadCommand').value,
+            }),
+        });
+    }
+
+    showUploadStatus('Checking the bot...', false);
+    try {
+        const response = await request;
+        const data = await response.json();
+        if (!response.ok) {
+            showUploadStatus(data.error || 'Upload failed', true);
+            return;
         }
+        showUploadStatus(`Added ${data.name}`, false);
+        loadAvailableBots();
     } catch (error) {
-        console.error('Error loading bots:', error);
+        showUploadStatus('Upload failed', true);
+    }
+}
+
+async function removeBot(id) {
+    const name = id.replace(/^bot:/, '');
+    try {
+        const response = await fetch(`${API_BASE}/bots/${encodeURIComponent(name)}`, {
+            method: 'DELETE',
+            headers: adminHeaders(),
+        });
+        const data = await response.json();
+        if (!response.ok) {
+            showUploadStatus(data.error || 'Removing the bot failed', true);
+            return;
+        }
+        showUploadStatus(`Removed ${data.name}`, false);
+        loadAvailableBots();
+    } catch (error) {
+        showUploadStatus('Removing the bot failed', true);
+    }
+}
+
+// Show the setup fields of the chosen mode only
+function updateMode() {
+    const mode = document.getElementById('mode').value;
+    document.querySelectorAll('[data-modes]').forEach(element => {
+        element.style.display = element.dataset.modes.split(' ').includes(mode) ? '' : 'none';
+    });
+}
+
+async function loadBotOptions() {
+    const botType = document.getElementById('botType').value;
+    const container = document.getElementById('botOptions');
+    container.innerHTML = '';
+
+    try {
+        const response = await fetch(
+            `${API_BASE}/bot-options?bot_type=${encodeURIComponent(botType)}`
+        );
+        if (!response.ok) return;
+        const options = await response.json();
+
+        options.forEach(opt => {
+            const group = document.createElement('div');
+            group.className = 'control-group';
+
+            const label = document.createElement('label');
+            label.textContent = `${opt.name}:`;
+
+            const input = document.createElement('input');
+            input.dataset.option = opt.name;
+            input.dataset.type = opt.type;
+            if (opt.type === 'bool') {
+                input.type = 'checkbox';
+                input.checked = opt.default;
+            } else if (opt.type === 'int') {
+                input.type = 'number';
+                input.min = opt.min;
+                input.max = opt.max;
+                input.value = opt.default;
+            } else {
+                input.type = 'text';
+                input.value = opt.default;
+            }
+
+            group.appendChild(label);
+            group.appendChild(input);
+            container.appendChild(group);
+        });
+    } catch (error) {
+        console.error('Error loading bot options:', error);
+    }
+}
+
+// 'move:5000' is a limit per move, '180000+2000' a game clock with increment
+function timeControl(selectId) {
+    const value = document.getElementById(selectId).value;
+    if (!value) return null;
+    if (value.startsWith('move:')) {
+        return { per_move_ms: parseInt(value.slice(5), 10) };
     }
+    const [initial, increment] = value.split('+').map(n => parseInt(n, 10));
+    return { initial_ms: initial, increment_ms: increment };
+}
+
+function collectBotOptions() {
+    const options = {};
+    document.querySelectorAll('#botOptions input').forEach(input => {
+        if (input.dataset.type === 'bool') {
+            options[input.dataset.option] = input.checked;
+        } else if (input.dataset.type === 'int') {
+            options[input.dataset.option] = parseInt(input.value, 10);
+        } else {
+            options[input.dataset.option] = input.value;
+        }
+    });
+    return options;
 }
 
 function showSetup() {
+    closeSocket();
     document.getElementById('setupPanel').style.display = 'block';
     document.getElementById('gameContainer').style.display = 'none';
     selectedSquare = null;
@@ -63,27 +301,54 @@ async function startNewGame() {
     const variant = document.getElementById('variant').value;
     const playerSideValue = document.getElementById('playerSide').value;
     const botType = document.getElementById('botType').value;
+    const mode = document.getElementById('mode').value;
+    const watching = mode === 'watch';
 
-    playerSide = playerSideValue;
+    playerSide = watching ? null : playerSideValue;
 
+    closeSocket();
     try {
-        const response = await fetch(`${API_BASE}/new-game`, {
-            method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({
-                variant: variant,
-                player_side: playerSideValue,
-                bot_type: botType
+        const response = watching
+            ? await fetch(`${API_BASE}/matches`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    variant: variant,
+                    attacker: document.getElementById('attackerBot').value,
+                    defender: document.getElementById('defenderBot').value,
+                    delay_ms: parseInt(document.getElementById('moveDelay').value, 10) || 0
+                })
             })
-        });
+            : await fetch(`${API_BASE}/games`, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    variant: variant,
+                    player_side: playerSideValue,
+                    bot_type: botType,
+                    bot_options: collectBotOptions(),
+                    vs_human: mode === 'friend',
+                    position: customPosition
+                        ? { board: customPosition.board, to_move: customPosition.to_move }
+                        : null,
+                    attacker_time: timeControl('attackerTime'),
+                    defender_time: timeControl('defenderTime')
+                })
+            });
 
         if (!response.ok) {
-            throw new Error('Failed to start game');
+            const error = await response.json().catch(() => ({}));
+            throw new Error(error.error || 'Failed to start game');
         }
 
         gameState = await response.json();
+        gameId = gameState.id;
+        lastMove = null;
+        clearHint();
+        renderAnalysis(null);
         showGame();
         updateUI();
+        connectSocket();
 
         if (gameState.message) {
             showMessage(gameState.message);
@@ -93,9 +358,215 @@ async function startNewGame() {
     }
 }
 
+async function joinGame(id) {
+    try {
+        const response = await fetch(`${API_BASE}/games/${id}/join`, { method: 'POST' });
+        const result = await response.json();
+        if (!response.ok) {
+            throw new Error(result.error || 'Failed to join game');
+        }
+
+        gameState = result;
+        gameId = id;
+        clearHint();
+        playerSide = result.player_side;
+        lastMove = null;
+        showGame();
+        updateUI();
+        connectSocket();
+    } catch (error) {
+        alert('Error joining game: ' + error.message);
+    }
+}
+
+function copyJoinLink() {
+    const input = document.getElementById('joinLink');
+    input.select();
+    navigator.clipboard.writeText(input.value).catch(() => document.execCommand('copy'));
+}
+
+// Ask the chosen analysis bot for a move; the game itself is not touched
+async function requestHint() {
+    const button = document.getElementById('hintBtn');
+    const text = document.getElementById('hintText');
+    const seconds = parseFloat(document.getElementById('hintTime').value) || 2;
+    button.disabled = true;
+    text.textContent = 'Thinking...';
+    try {
+        const response = await fetch(`${API_BASE}/games/${gameId}/hint`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({
+                bot_type: document.getElementById('hintBot').value,
+                time_ms: Math.round(seconds * 1000)
+            })
+        });
+        const result = await response.json();
+        if (!response.ok) {
+            text.textContent = 'Error: ' + (result.error || 'No hint');
+            return;
+        }
+
+        hint = result;
+        const lines = [result.mv
+            ? `${result.bot} suggests ${formatMove(result.mv)} (${formatThinkTime(result.think_ms)})`
+            : `${result.bot} has nothing to suggest`];
+        if (result.info) {
+            if (result.info.score !== null && result.info.score !== undefined) lines.push(`Score: ${result.info.score}`);
+            if (result.info.depth !== null && result.info.depth !== undefined) lines.push(`Depth: ${result.info.depth}`);
+            if (result.info.pv && result.info.pv.length > 0) lines.push(`PV: ${result.info.pv.map(formatMove).join(' ')}`);
+            if (result.info.text) lines.push(result.info.text);
+        }
+        text.textContent = lines.join('\n');
+        renderBoard();
+    } catch (error) {
+        text.textContent = 'Error getting hint: ' + error.message;
+    } finally {
+        button.disabled = false;
+    }
+}
+
+// Arrow from the hint's start square to its target, over the board
+function drawHintArrow(boardElement) {
+    if (!hint || !hint.mv || hint.move_count !== gameState.move_count) return;
+
+    const step = 52; // 50px squares with a 2px gap
+    const center = index => 2 + index * step + 25;
+    const { from, to } = hint.mv;
+    const size = gameState.board.length * step + 2;
+    const svgNs = 'http://www.w3.org/2000/svg';
+    const svg = document.createElementNS(svgNs, 'svg');
+    svg.setAttribute('class', 'hint-arrow');
+    svg.setAttribute('width', size);
+    svg.setAttribute('height', size);
+    svg.innerHTML = `
+        <defs>
+            <marker id="hintHead" markerWidth="4" markerHeight="4" refX="2" refY="2" orient="auto">
+                <path d="M0,0 L4,2 L0,4 z" fill="rgba(255, 87, 34, 0.85)"></path>
+            </marker>
+        </defs>
+        <line x1="${center(from.col)}" y1="${center(from.row)}"
+              x2="${center(to.col)}" y2="${center(to.row)}"
+              stroke="rgba(255, 87, 34, 0.85)" stroke-width="8" stroke-linecap="round"
+              marker-end="url(#hintHead)"></line>`;
+    boardElement.appendChild(svg);
+}
+
+async function undoMove() {
+    try {
+        const response = await fetch(`${API_BASE}/games/${gameId}/undo`, { method: 'POST' });
+        const result = await response.json();
+        if (!response.ok) {
+            showMessage('Error: ' + (result.error || 'Cannot undo'), 'error');
+            return;
+        }
+        gameState = result;
+        lastMove = null;
+        clearSelection();
+    } catch (error) {
+        showMessage('Error undoing move: ' + error.message, 'error');
+    }
+}
+
+function setCustomPosition(position) {
+    customPosition = position;
+    document.getElementById('positionLabel').textContent = position
+        ? `Custom, ${position.to_move} to move`
+        : 'Standard';
+    document.getElementById('clearPositionBtn').style.display = position ? '' : 'none';
+}
+
+async function openEditor() {
+    const variant = document.getElementById('variant').value;
+    document.getElementById('setupPanel').style.display = 'none';
+    document.getElementById('editorPanel').style.display = 'block';
+    document.getElementById('editorError').textContent = '';
+
+    if (customPosition && customPosition.variant === variant) {
+        editorBoard = customPosition.board.map(row => row.slice());
+        document.getElementById('editorToMove').value = customPosition.to_move;
+        renderEditor();
+    } else {
+        await loadStartPosition();
+    }
+}
+
+function closeEditor() {
+    document.getElementById('editorPanel').style.display = 'none';
+    document.getElementById('setupPanel').style.display = 'block';
+}
+
+async function loadStartPosition() {
+    const variant = document.getElementById('variant').value;
+    try {
+        const response = await fetch(`${API_BASE}/positions?variant=${encodeURIComponent(variant)}`);
+        const position = await response.json();
+        editorBoard = position.board;
+        document.getElementById('editorToMove').value = position.to_move;
+        renderEditor();
+    } catch (error) {
+        document.getElementById('editorError').textContent = 'Error loading position: ' + error.message;
+    }
+}
+
+function clearEditorBoard() {
+    editorBoard = editorBoard.map(row => row.map(() => '.'));
+    renderEditor();
+}
+
+function renderEditor() {
+    const boardElement = document.getElementById('editorBoard');
+    const size = editorBoard.length;
+    boardElement.innerHTML = '';
+    boardElement.style.gridTemplateColumns = `repeat(${size}, 50px)`;
+    boardElement.style.gridTemplateRows = `repeat(${size}, 50px)`;
+
+    for (let row = 0; row < size; row++) {
+        for (let col = 0; col < size; col++) {
+            const square = document.createElement('div');
+            square.className = `square ${squareType(row, col, size)}`;
+            const piece = editorBoard[row][col];
+            if (piece !== '.') {
+                square.textContent = piece;
+                square.classList.add(`piece-${piece}`);
+            }
+            square.addEventListener('click', () => {
+                const chosen = document.querySelector('input[name="editorPiece"]:checked').value;
+                // Clicking the same piece again removes it
+                editorBoard[row][col] = editorBoard[row][col] === chosen ? '.' : chosen;
+                document.getElementById('editorError').textContent = '';
+                renderEditor();
+            });
+            boardElement.appendChild(square);
+        }
+    }
+}
+
+// Have the server check the position before games start from it
+async function useEditorPosition() {
+    const variant = document.getElementById('variant').value;
+    const toMove = document.getElementById('editorToMove').value;
+    try {
+        const response = await fetch(`${API_BASE}/positions`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify({ variant: variant, board: editorBoard, to_move: toMove })
+        });
+        const result = await response.json();
+        if (!response.ok) {
+            document.getElementById('editorError').textContent = result.error;
+            return;
+        }
+        setCustomPosition({ variant: variant, board: result.board, to_move: result.to_move });
+        closeEditor();
+    } catch (error) {
+        document.getElementById('editorError').textContent = 'Error checking position: ' + error.message;
+    }
+}
+
 async function makeMove(fromRow, fromCol, toRow, toCol) {
     try {
-        const response = await fetch(`${API_BASE}/move`, {
+        const response = await fetch(`${API_BASE}/games/${gameId}/move`, {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
             body: JSON.stringify({
@@ -108,13 +579,18 @@ async function makeMove(fromRow, fromCol, toRow, toCol) {
 
         const result = await response.json();
 
+        if (response.status === 404) {
+            showMessage('This game has expired. Start a new one!', 'error');
+            return false;
+        }
         if (!response.ok) {
             showMessage('Error: ' + (result.error || 'Invalid move'), 'error');
             return false;
         }
 
-        // Update game state
-        await refreshGameState();
+        // The response is the new game state
+        gameState = result;
+        updateUI();
 
         if (result.message) {
             showMessage(result.message);
@@ -127,23 +603,148 @@ async function makeMove(fromRow, fromCol, toRow, toCol) {
     }
 }
 
-async function refreshGameState() {
+// Pause, resume or step a match between two bots
+async function controlMatch(request) {
     try {
-        const response = await fetch(`${API_BASE}/game-state`);
+        const response = await fetch(`${API_BASE}/games/${gameId}/control`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify(request)
+        });
+        if (!response.ok) {
+            showMessage('This game has expired. Start a new one!', 'error');
+            return;
+        }
         gameState = await response.json();
         updateUI();
     } catch (error) {
-        console.error('Error refreshing game state:', error);
+        showMessage('Error controlling match: ' + error.message, 'error');
+    }
+}
+
+function togglePause() {
+    const paused = gameState && gameState.pacing && gameState.pacing.paused;
+    controlMatch({ action: paused ? 'resume' : 'pause' });
+}
+
+// The server pushes everything that happens in the game, including the
+// bot's reply to our move
+function connectSocket() {
+    const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+    const ws = new WebSocket(`${protocol}//${location.host}${API_BASE}/games/${gameId}/ws`);
+    socket = ws;
+    ws.addEventListener('open', () => {
+        reconnectDelay = 1000;
+    });
+    ws.addEventListener('message', event => handleEvent(JSON.parse(event.data)));
+    ws.addEventListener('close', () => {
+        // Closed by closeSocket, e.g. for a new game
+        if (socket !== ws) return;
+        socket = null;
+        scheduleReconnect(gameId);
+    });
+}
+
+// Reconnect a dropped socket; the server starts with the full state, so
+// nothing that happened meanwhile is lost
+function scheduleReconnect(id) {
+    reconnectTimer = setTimeout(async () => {
+        reconnectTimer = null;
+        if (gameId !== id) return;
+        const response = await fetch(`${API_BASE}/games/${id}`).catch(() => null);
+        if (gameId !== id || socket) return;
+        if (response && response.status === 404) {
+            showMessage('This game has expired. Start a new one!', 'error');
+            return;
+        }
+        connectSocket();
+    }, reconnectDelay);
+    reconnectDelay = Math.min(reconnectDelay * 2, MAX_RECONNECT_DELAY);
+}
+
+function closeSocket() {
+    clearTimeout(reconnectTimer);
+    reconnectTimer = null;
+    reconnectDelay = 1000;
+    if (socket) {
+        const ws = socket;
+        socket = null;
+        ws.close();
+    }
+}
+
+function handleEvent(event) {
+    switch (event.type) {
+        case 'state':
+            // After an undo the last move highlight no longer applies
+            if (gameState && event.move_count < gameState.move_count) {
+                lastMove = null;
+            }
+            gameState = event;
+            updateUI();
+            if (gameState.message && !gameState.game_over && !gameState.bot_thinking) {
+                showMessage(gameState.message);
+            }
+            break;
+        case 'move':
+            lastMove = event;
+            break;
+        case 'search_info':
+            renderAnalysis(event.info);
+            break;
+        case 'clock': {
+            const side = event.player === 'Attackers' ? gameState.attacker : gameState.defender;
+            showMessage(`${side.name} is thinking... ${Math.round(event.elapsed_ms / 1000)}s`, 'info');
+            break;
+        }
+        case 'game_over':
+            showMessage(`🎉 Game Over! Winner: ${event.winner || 'Draw'}. ${event.message}`, 'success');
+            break;
     }
 }
 
+function formatMove(mv) {
+    return `(${mv.from.row},${mv.from.col})→(${mv.to.row},${mv.to.col})`;
+}
+
+function renderAnalysis(info) {
+    const element = document.getElementById('analysis');
+    if (!info) {
+        element.textContent = 'No analysis yet';
+        return;
+    }
+
+    const lines = [];
+    if (info.depth !== null && info.depth !== undefined) lines.push(`Depth: ${info.depth}`);
+    if (info.score !== null && info.score !== undefined) lines.push(`Score: ${info.score}`);
+    if (info.nodes !== null && info.nodes !== undefined) lines.push(`Nodes: ${info.nodes}`);
+    if (info.nps !== null && info.nps !== undefined) lines.push(`NPS: ${info.nps}`);
+    if (info.pv && info.pv.length > 0) lines.push(`PV: ${info.pv.map(formatMove).join(' ')}`);
+    if (info.text) lines.push(info.text);
+    element.textContent = lines.length > 0 ? lines.join('\n') : 'No analysis yet';
+}
+
 function updateUI() {
     if (!gameState) return;
 
     // Update info panel
     document.getElementById('currentVariant').textContent = gameState.variant;
-    document.getElementById('playerRole').textContent = playerSide;
+    document.getElementById('playerRole').textContent = playerSide || 'Spectator';
     document.getElementById('currentTurn').textContent = gameState.current_player;
+    renderPlayers();
+    renderClocks();
+
+    // Takebacks are for games against a bot
+    document.getElementById('undoBtn').style.display = gameState.vs_bot ? '' : 'none';
+
+    // The creator of a game against a human shares the link until they join
+    const joinPanel = document.getElementById('joinPanel');
+    if (gameState.waiting_for_opponent) {
+        document.getElementById('joinLink').value = `${location.origin}/?join=${gameId}`;
+        joinPanel.style.display = 'block';
+    } else {
+        joinPanel.style.display = 'none';
+    }
 
     // Update selected piece display
     if (selectedSquare) {
@@ -155,11 +756,18 @@ function updateUI() {
 
     // Render board
     renderBoard();
+    renderAnalysis(gameState.analysis);
 
     // Check for game over
     if (gameState.game_over) {
         const winner = gameState.winner || 'Draw';
         showMessage(`🎉 Game Over! Winner: ${winner}`, 'success');
+    } else if (gameState.pacing) {
+        showMessage(gameState.message, 'info');
+    } else if (gameState.waiting_for_opponent) {
+        showMessage('Waiting for an opponent to join...', 'info');
+    } else if (gameState.bot_thinking) {
+        showMessage('Bot is thinking...', 'info');
     } else if (gameState.current_player === playerSide) {
         showMessage('Your turn! Select a piece to move.', 'info');
     } else {
@@ -167,6 +775,64 @@ function updateUI() {
     }
 }
 
+function clearHint() {
+    hint = null;
+    document.getElementById('hintText').textContent = '';
+}
+
+function formatThinkTime(ms) {
+    return ms < 1000 ? `${ms}ms` : `${(ms / 1000).toFixed(1)}s`;
+}
+
+// Names and think times of both sides, plus the controls of a bot match
+function renderPlayers() {
+    [['attacker', gameState.attacker], ['defender', gameState.defender]].forEach(([id, side]) => {
+        document.getElementById(`${id}Name`).textContent = side.name || '-';
+        document.getElementById(`${id}Think`).textContent = side.last_think_ms === null
+            ? ''
+            : `last ${formatThinkTime(side.last_think_ms)}, total ${formatThinkTime(side.total_think_ms)}`;
+    });
+
+    const controls = document.getElementById('matchControls');
+    controls.style.display = gameState.pacing && !gameState.game_over ? 'flex' : 'none';
+    if (gameState.pacing) {
+        document.getElementById('pauseBtn').textContent = gameState.pacing.paused ? 'Resume' : 'Pause';
+        document.getElementById('stepBtn').disabled = !gameState.pacing.paused;
+    }
+}
+
+function formatClock(ms) {
+    const totalSeconds = Math.ceil(ms / 1000);
+    const minutes = Math.floor(totalSeconds / 60);
+    const seconds = totalSeconds % 60;
+    return `${minutes}:${seconds.toString().padStart(2, '0')}`;
+}
+
+// The server's clocks are authoritative; between updates the running one
+// counts down locally
+function renderClocks() {
+    if (!gameState) return;
+    if (clockState !== gameState) {
+        clockState = gameState;
+        clockSyncedAt = performance.now();
+    }
+    const elapsed = performance.now() - clockSyncedAt;
+
+    [['attacker', 'Attackers'], ['defender', 'Defenders']].forEach(([id, side]) => {
+        const element = document.getElementById(`${id}Clock`);
+        const left = gameState[`${id}_time_ms`];
+        if (left === null || left === undefined) {
+            element.textContent = '';
+            return;
+        }
+        const running = gameState.clock_running && gameState.current_player === side;
+        const shown = running ? Math.max(0, left - elapsed) : left;
+        element.textContent = formatClock(shown);
+        element.classList.toggle('running', running);
+        element.classList.toggle('low', shown < 10000);
+    });
+}
+
 function renderBoard() {
     const boardElement = document.getElementById('board');
     const size = gameState.board.length;
@@ -181,6 +847,19 @@ function renderBoard() {
             boardElement.appendChild(square);
         }
     }
+    drawHintArrow(boardElement);
+}
+
+// 'corner', 'throne' or 'normal'
+function squareType(row, col, size) {
+    const isCorner = (row === 0 || row === size - 1) && (col === 0 || col === size - 1);
+    const isThrone = row === Math.floor(size / 2) && col === Math.floor(size / 2);
+    if (isCorner) {
+        return 'corner';
+    } else if (isThrone) {
+        return 'throne';
+    }
+    return 'normal';
 }
 
 function createSquare(row, col) {
@@ -189,21 +868,7 @@ function createSquare(row, col) {
     square.dataset.row = row;
     square.dataset.col = col;
 
-    // Determine square type
-    const size = gameState.board.length;
-    const isCorner = (row === 0 && col === 0) ||
-        (row === 0 && col === size - 1) ||
-        (row === size - 1 && col === 0) ||
-        (row === size - 1 && col === size - 1);
-    const isThrone = row === Math.floor(size / 2) && col === Math.floor(size / 2);
-
-    if (isCorner) {
-        square.classList.add('corner');
-    } else if (isThrone) {
-        square.classList.add('throne');
-    } else {
-        square.classList.add('normal');
-    }
+    square.classList.add(squareType(row, col, gameState.board.length));
 
     // Add piece
     const piece = gameState.board[row][col];
@@ -212,6 +877,17 @@ function createSquare(row, col) {
         square.classList.add(`piece-${piece}`);
     }
 
+    // Mark the last move and what it captured
+    if (lastMove) {
+        const { from, to } = lastMove.mv;
+        if ((from.row === row && from.col === col) || (to.row === row && to.col === col)) {
+            square.classList.add('last-move');
+        }
+        if (lastMove.captures.some(p => p.row === row && p.col === col)) {
+            square.classList.add('captured');
+        }
+    }
+
     // Highlight selected square
     if (selectedSquare && selectedSquare.row === row && selectedSquare.col === col) {
         square.classList.add('selected');
@@ -234,7 +910,11 @@ function handleSquareClick(row, col) {
         return;
     }
 
-    if (gameState.current_player !== playerSide) {
+    if (!playerSide) {
+        return;
+    }
+
+    if (gameState.bot_thinking || gameState.current_player !== playerSide) {
         showMessage('Wait for your turn!', 'warning');
         return;
     }